@@ -147,6 +147,30 @@ impl QueueClient
 
         Ok(())
     }
+
+    /// Запись блока в исходном виде, с сохранением идентификатора записи
+    /// 
+    /// Аргументы
+    /// - `rid` - идентификатор записи, должен быть следующим в очереди;
+    ///   блок 0 следующего лог файла переключает лог файл
+    /// - `block` - блок
+    pub async fn write_raw(&self, rid:RecId, block:&Block) -> Result<RecId,ClientError> {
+        let mut res = self.http_client.put(format!("{base}/queue/record/{lid}/{bid}/raw",
+            base=&self.base_url,
+            lid=rid.log_id,
+            bid=rid.block_id,
+        ))
+        .send_body(block.to_bytes()).await?;
+
+        if res.status() != StatusCode::OK {
+            return Err(ClientError::Status { 
+                code: res.status().as_u16(), 
+                body: std::str::from_utf8( &res.body().await.unwrap() ).unwrap().to_string()
+            });
+        }
+
+        res.json::<TailIdRaw>().await?.try_into()
+    }
 }
 
 #[test]
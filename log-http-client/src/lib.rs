@@ -4,6 +4,9 @@ pub use domain::*;
 mod client;
 pub use client::*;
 
+mod replicator;
+pub use replicator::*;
+
 #[cfg(test)]
 mod tests {
     #[allow(unused)]
@@ -0,0 +1,343 @@
+//! Репликация очереди
+//!
+//! Реплика периодически опрашивает сервер-источник и копирует новые записи в локальную очередь.
+//!
+//! Идентификаторы лог файлов очереди последовательны (`0, 1, 2, ...`),
+//! поэтому если локальная очередь пополняется только репликатором,
+//! идентификаторы записей реплики совпадают с идентификаторами записей источника:
+//!
+//! - записи копируются по порядку в исходном виде ([LogRawWriting]), начиная с записи следующей за последней локальной
+//! - переключение лог файла на источнике повторяется локально, записью блока 0 нового лог файла
+//! - после перезапуска копирование продолжается с последней локальной записи
+//!
+//! - если в локальной очереди нет записей, а первые лог файлы источника уже исключены (retention),
+//!   то копирование начинается с первого лог файла источника ([ReplicaAction::Restart])
+//!
+//! Если идентификатор записанной записи не совпал с ожидаемым, репликация прекращается с ошибкой
+//! [ReplicaErr::RecIdMismatch] - локальная очередь изменена в обход репликатора.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use logs::logfile::{LogFile, FlatBuff};
+use logs::logfile::block::Block;
+use logs::logfile::block::BlockId;
+use logs::logqueue::{
+    LogFileQueue, LogNavigateLast, LogQueueFileNumID, LogQueueFileId, LogRawWriting, RecID, SharedLogQueue, RetentionAction, dispose_file
+};
+use serde::Serialize;
+
+use super::*;
+
+/// Ошибки репликации
+#[derive(Debug,Clone)]
+pub enum ReplicaErr {
+    /// Ошибка обращения к источнику
+    Client(ClientError),
+
+    /// Ошибка локальной очереди
+    Local(String),
+
+    /// Локальная очередь пуста
+    LocalQueueEmpty,
+
+    /// На источнике нет лог файла, в который пишет реплика
+    SourceLogNotFound { log_id: u128 },
+
+    /// Источник не сообщил кол-во записей в лог файле
+    SourceCountUnknown { log_id: u128 },
+
+    /// В лог файле источника меньше записей чем в реплике
+    SourceBehind { local: RecId, source_count: u32 },
+
+    /// Идентификатор записи в реплике не совпал с идентификатором источника
+    RecIdMismatch { expected: RecId, actual: RecId },
+}
+
+impl From<ClientError> for ReplicaErr {
+    fn from(value: ClientError) -> Self {
+        Self::Client(value)
+    }
+}
+
+/// Локальная очередь, в которую копируются записи
+pub trait ReplicaTarget {
+    /// Последняя запись
+    fn last_record( &self ) -> Result<Option<RecId>,ReplicaErr>;
+
+    /// Запись блока в исходном виде, результат - идентификатор записи
+    /// 
+    /// Блок 0 следующего лог файла - переключение лог файла
+    fn write_raw( &self, rid: RecId, block: &Block ) -> Result<RecId,ReplicaErr>;
+
+    /// Начало копирования с лог файла источника `log_id`, см. [ReplicaAction::Restart]
+    /// 
+    /// Допустимо только для очереди без записей, результат - последняя запись (блок 0 лог файла `log_id`)
+    fn restart( &self, log_id: u128 ) -> Result<RecId,ReplicaErr>;
+}
+
+/// Переключение очереди без записей на лог файл `log_id`, начальный лог файл удаляется
+fn restart_queue<BUFF: FlatBuff>( q: &mut (dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<BUFF>> + '_), log_id: u128 ) -> Result<RecId,ReplicaErr> {
+    let files = q.files();
+    let empty = files.len() == 1 && files[0].2.count().map(|count| count <= 1).unwrap_or(false);
+    if !empty {
+        return Err(ReplicaErr::Local(format!("can't start from source log file {log_id}, local queue is not empty")));
+    }
+
+    let (_, new_id) = q.switch_to(LogQueueFileNumID { id: log_id, previous: log_id.checked_sub(1) })
+        .map_err(|e| ReplicaErr::Local(format!("{e:?}")))?;
+    if let Some((_, file, _)) = q.remove_head().map_err(|e| ReplicaErr::Local(format!("{e:?}")))? {
+        dispose_file(&file, &RetentionAction::Delete)
+            .map_err(|e| ReplicaErr::Local(format!("can't remove {}: {e}", file.display())))?;
+    }
+    Ok(RecId::new(new_id.id(), 0))
+}
+
+impl<'a,BUFF> ReplicaTarget for Arc<Mutex<dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<BUFF>> + 'a>>
+where
+    BUFF: FlatBuff,
+{
+    fn last_record( &self ) -> Result<Option<RecId>,ReplicaErr> {
+        let q = self.lock().map_err(|e| ReplicaErr::Local(e.to_string()))?;
+        let rid = q.last_record().map_err(|e| ReplicaErr::Local(format!("{e:?}")))?;
+        Ok(rid.map(|rid| RecId::new(rid.log_file_id.id(), rid.block_id.value())))
+    }
+
+    fn write_raw( &self, rid: RecId, block: &Block ) -> Result<RecId,ReplicaErr> {
+        let mut q = self.lock().map_err(|e| ReplicaErr::Local(e.to_string()))?;
+        let rid = RecID { 
+            log_file_id: LogQueueFileNumID { id: rid.log_id, previous: None }, 
+            block_id: BlockId::new(rid.block_id) 
+        };
+        let rid = q.write_raw(rid, block).map_err(|e| ReplicaErr::Local(format!("{e:?}")))?;
+        Ok(RecId::new(rid.log_file_id.id(), rid.block_id.value()))
+    }
+
+    fn restart( &self, log_id: u128 ) -> Result<RecId,ReplicaErr> {
+        let mut q = self.lock().map_err(|e| ReplicaErr::Local(e.to_string()))?;
+        restart_queue(&mut *q, log_id)
+    }
+}
+
+impl<'a,BUFF> ReplicaTarget for SharedLogQueue<'a,LogQueueFileNumID,PathBuf,BUFF>
+where
+    BUFF: FlatBuff,
+{
+    fn last_record( &self ) -> Result<Option<RecId>,ReplicaErr> {
+        let q = self.read().map_err(|e| ReplicaErr::Local(format!("{e:?}")))?;
+        let rid = q.last_record().map_err(|e| ReplicaErr::Local(format!("{e:?}")))?;
+        Ok(rid.map(|rid| RecId::new(rid.log_file_id.id(), rid.block_id.value())))
+    }
+
+    fn write_raw( &self, rid: RecId, block: &Block ) -> Result<RecId,ReplicaErr> {
+        let rid = RecID { 
+            log_file_id: LogQueueFileNumID { id: rid.log_id, previous: None }, 
+            block_id: BlockId::new(rid.block_id) 
+        };
+        let rid = SharedLogQueue::write_raw(self, rid, block).map_err(|e| ReplicaErr::Local(format!("{e:?}")))?;
+        Ok(RecId::new(rid.log_file_id.id(), rid.block_id.value()))
+    }
+
+    fn restart( &self, log_id: u128 ) -> Result<RecId,ReplicaErr> {
+        let mut q = self.exclusive().map_err(|e| ReplicaErr::Local(format!("{e:?}")))?;
+        restart_queue(&mut *q, log_id)
+    }
+}
+
+/// Следующее действие репликации
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ReplicaAction {
+    /// Скопировать запись
+    Copy(RecId),
+
+    /// Переключить лог файл, ожидаемый идентификатор нового лог файла
+    Switch { log_id: u128 },
+
+    /// Начать копирование с лог файла источника - в реплике нет записей,
+    /// а лог файл реплики на источнике уже исключен
+    Restart { log_id: u128 },
+
+    /// Новых записей нет
+    Idle,
+}
+
+/// Определение следующего действия репликации
+///
+/// Аргументы
+/// - `position` - последняя локальная запись
+/// - `files` - лог файлы источника
+pub fn replica_action( position: RecId, files: &LogFiles ) -> Result<ReplicaAction,ReplicaErr> {
+    if position.block_id == 0 && !files.files.iter().any(|f| f.log_id == position.log_id) {
+        let first = files.files.iter().map(|f| f.log_id).min();
+        if let Some(log_id) = first.filter(|first| *first > position.log_id) {
+            return Ok(ReplicaAction::Restart { log_id });
+        }
+    }
+
+    let count = source_count(position.log_id, files)?;
+    let next_block = position.block_id as u64 + 1;
+
+    if (count as u64) < next_block {
+        return Err(ReplicaErr::SourceBehind { local: position, source_count: count });
+    }
+
+    if (count as u64) > next_block {
+        return Ok(ReplicaAction::Copy(RecId::new(position.log_id, position.block_id + 1)));
+    }
+
+    Ok(files.files.iter()
+        .map(|f| f.log_id)
+        .filter(|id| *id > position.log_id)
+        .min()
+        .map(|log_id| ReplicaAction::Switch { log_id })
+        .unwrap_or(ReplicaAction::Idle))
+}
+
+/// Отставание реплики - кол-во записей источника после указанной
+///
+/// Аргументы
+/// - `position` - последняя локальная запись
+/// - `files` - лог файлы источника
+pub fn replica_lag( position: RecId, files: &LogFiles ) -> Result<u64,ReplicaErr> {
+    let count = source_count(position.log_id, files)? as u64;
+    let mut lag = count.saturating_sub(position.block_id as u64 + 1);
+
+    for f in files.files.iter().filter(|f| f.log_id > position.log_id) {
+        lag += f.items_count.ok_or(ReplicaErr::SourceCountUnknown { log_id: f.log_id })? as u64;
+    }
+
+    Ok(lag)
+}
+
+fn source_count( log_id: u128, files: &LogFiles ) -> Result<u32,ReplicaErr> {
+    files.files.iter()
+        .find(|f| f.log_id == log_id)
+        .ok_or(ReplicaErr::SourceLogNotFound { log_id })?
+        .items_count
+        .ok_or(ReplicaErr::SourceCountUnknown { log_id })
+}
+
+/// Состояние репликации
+#[derive(Debug,Clone,Serialize)]
+pub struct ReplicaStatus {
+    /// Последняя локальная запись
+    pub position: RecId,
+
+    /// Кол-во записей источника, которые еще не скопированы
+    pub lag: u64,
+
+    /// Кол-во скопированных записей за последний шаг
+    pub copied: usize,
+}
+
+/// Репликатор
+pub struct Replicator<T: ReplicaTarget> {
+    /// Источник
+    pub source: QueueClient,
+
+    /// Локальная очередь
+    pub target: T,
+
+    /// Максимальное кол-во записей, копируемых за один шаг
+    pub batch_size: usize,
+}
+
+impl<T: ReplicaTarget> Replicator<T> {
+    fn position( &self ) -> Result<RecId,ReplicaErr> {
+        self.target.last_record()?.ok_or(ReplicaErr::LocalQueueEmpty)
+    }
+
+    async fn copy( &self, rid: RecId ) -> Result<(),ReplicaErr> {
+        let block = self.source.record(rid).await?;
+        let actual = self.target.write_raw(rid, &block)?;
+        if actual != rid {
+            return Err(ReplicaErr::RecIdMismatch { expected: rid, actual });
+        }
+        Ok(())
+    }
+
+    /// Текущее состояние репликации, без копирования
+    pub async fn status( &self ) -> Result<ReplicaStatus,ReplicaErr> {
+        let position = self.position()?;
+        let files = self.source.files().await?;
+        Ok(ReplicaStatus { position, lag: replica_lag(position, &files)?, copied: 0 })
+    }
+
+    /// Шаг репликации - копирование не более `batch_size` записей
+    pub async fn step( &self ) -> Result<ReplicaStatus,ReplicaErr> {
+        let mut files = self.source.files().await?;
+        let mut copied = 0usize;
+
+        while copied < self.batch_size {
+            let position = self.position()?;
+            match replica_action(position, &files)? {
+                ReplicaAction::Idle => break,
+                ReplicaAction::Copy(rid) => {
+                    self.copy(rid).await?;
+                    copied += 1;
+                },
+                ReplicaAction::Restart { log_id } => {
+                    self.target.restart(log_id)?;
+                    copied += 1;
+                },
+                ReplicaAction::Switch { log_id } => {
+                    // блок 0 нового лог файла - его идентификатор
+                    self.copy(RecId::new(log_id, 0)).await?;
+                    copied += 1;
+                    files = self.source.files().await?;
+                }
+            }
+        }
+
+        let position = self.position()?;
+        Ok(ReplicaStatus { position, lag: replica_lag(position, &files)?, copied })
+    }
+
+    /// Репликация до остановки процесса
+    ///
+    /// Аргументы
+    /// - `poll_interval` - пауза между опросами источника, когда новых записей нет или произошла ошибка
+    /// - `on_step` - получает результат каждого шага
+    pub async fn run<F>( &self, poll_interval: Duration, mut on_step: F )
+    where
+        F: FnMut(Result<ReplicaStatus,ReplicaErr>)
+    {
+        loop {
+            let res = self.step().await;
+            let idle = !matches!(&res, Ok(status) if status.copied > 0);
+            on_step(res);
+            if idle {
+                actix_rt::time::sleep(poll_interval).await;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_replica_action() {
+    let file = |log_id:u128, items_count:u32| LogFileInfo {
+        log_id,
+        log_file: format!("{log_id}.binlog"),
+        items_count: Some(items_count),
+        bytes_count: None,
+    };
+
+    let files = LogFiles { files: vec![file(0, 3)] };
+    assert_eq!(replica_action(RecId::new(0, 0), &files).unwrap(), ReplicaAction::Copy(RecId::new(0, 1)));
+    assert_eq!(replica_action(RecId::new(0, 2), &files).unwrap(), ReplicaAction::Idle);
+    assert_eq!(replica_lag(RecId::new(0, 0), &files).unwrap(), 2);
+    assert!(matches!(replica_action(RecId::new(0, 3), &files), Err(ReplicaErr::SourceBehind { .. })));
+    assert!(matches!(replica_action(RecId::new(1, 0), &files), Err(ReplicaErr::SourceLogNotFound { log_id: 1 })));
+
+    // первые лог файлы источника исключены
+    let files = LogFiles { files: vec![file(5, 3), file(6, 1)] };
+    assert_eq!(replica_action(RecId::new(0, 0), &files).unwrap(), ReplicaAction::Restart { log_id: 5 });
+    assert!(matches!(replica_action(RecId::new(0, 2), &files), Err(ReplicaErr::SourceLogNotFound { log_id: 0 })));
+
+    let files = LogFiles { files: vec![file(2, 4), file(0, 3), file(1, 2)] };
+    assert_eq!(replica_action(RecId::new(0, 2), &files).unwrap(), ReplicaAction::Switch { log_id: 1 });
+    assert_eq!(replica_action(RecId::new(1, 1), &files).unwrap(), ReplicaAction::Switch { log_id: 2 });
+    assert_eq!(replica_lag(RecId::new(0, 2), &files).unwrap(), 6);
+    assert_eq!(replica_lag(RecId::new(2, 3), &files).unwrap(), 0);
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
-use std::{env, path::PathBuf, fs};
+use std::{env, path::PathBuf, fs, collections::BTreeMap};
 
-use super::{WebServer, QueueConfig, RaftConfig};
+use super::{WebServer, QueueConfig, RaftConfig, ReplicaConfig};
 
 /// Настройки приложения
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,20 +13,47 @@ pub struct AppConfig {
     /// Настройки веб сервера
     pub web_server: WebServer,
 
-    /// Настройки очереди
+    /// Настройки очереди `default`
     pub queue: QueueConfig,
 
+    /// Именованные очереди, доступны по адресу `/queue/{name}/...`
+    /// 
+    /// Шаблоны путей могут содержать переменную `${queue.name}`
+    #[serde(default)]
+    pub queues: BTreeMap<String,QueueConfig>,
+
+    /// Файл с настройками очередей, созданных через api `/queues`
+    #[serde(default="queues_file_default")]
+    pub queues_file: String,
+
+    /// Каталог, внутри которого должны находиться все пути очередей, созданных через api `/queues`:
+    /// лог файлы, файл позиций потребителей, каталоги архива
+    #[serde(default="queues_base_dir_default")]
+    pub queues_base_dir: String,
+
     #[serde(default)]
     pub raft: RaftConfig,
+
+    /// Настройки репликации
+    #[serde(default)]
+    pub replica: ReplicaConfig,
 }
 
+fn queues_file_default() -> String { "${work.dir}/app_data/meta/queues.json".to_string() }
+
+fn queues_base_dir_default() -> String { "${work.dir}/app_data".to_string() }
+
 impl Default for AppConfig {
     fn default() -> Self {
         AppConfig {  
             work_dir: ".".to_string(),
             web_server: WebServer::default(),
             queue: QueueConfig::default(),
+            queues: BTreeMap::new(),
+            queues_file: queues_file_default(),
+            queues_base_dir: queues_base_dir_default(),
             raft: RaftConfig::default(),
+            replica: ReplicaConfig::default(),
         }
     }
 }
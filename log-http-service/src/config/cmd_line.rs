@@ -21,6 +21,12 @@ pub struct CmdLineParams {
     pub raft_id: Option<String>,
 
     pub votes_min_count: Option<u32>,
+
+    /// - `-replica.source` _url_
+    /// - `-replicate` _url_
+    /// 
+    /// Указывает сервер-источник репликации
+    pub replica_source: Option<String>,
 }
 
 impl CmdLineParams {
@@ -35,6 +41,7 @@ impl CmdLineParams {
                         "-port" => (cmdl,"-port"),
                         "-work.dir" | "-wd" => (cmdl,"-wd"),
                         "-raft.id" | "-id" => (cmdl, "-raft.id"),
+                        "-replica.source" | "-replicate" => (cmdl, "-replica.source"),
                         //"-raft.votes_min" | "-raft.votes" | "-votes" => (),
                         _ => (cmdl,state)
                     }
@@ -43,6 +50,7 @@ impl CmdLineParams {
                 "-port" => ( CmdLineParams { web_server_port:Some(u16::from_str_radix(&arg, 10).unwrap()), ..cmdl }, "state" ),
                 "-wd" => ( CmdLineParams { work_dir:Some(arg.clone()) , ..cmdl }, "state" ),   
                 "-raft.id" => ( CmdLineParams { raft_id:Some(arg.clone()), .. cmdl}, "state" ),
+                "-replica.source" => ( CmdLineParams { replica_source:Some(arg.clone()), .. cmdl}, "state" ),
                 _ => (cmdl,state)
                 
             }
@@ -57,12 +65,20 @@ impl CmdLineParams {
                 static_files: conf.web_server.static_files.clone(), 
                 host: self.web_server_host.clone().unwrap_or(conf.web_server.host.clone()), 
                 port: self.web_server_port.clone().unwrap_or(conf.web_server.port.clone()),
+                max_body_size: conf.web_server.max_body_size,
             }, 
             queue: conf.queue.clone(),
+            queues: conf.queues.clone(),
+            queues_file: conf.queues_file.clone(),
+            queues_base_dir: conf.queues_base_dir.clone(),
             raft: super::RaftConfig {
                 id: self.raft_id.clone().map(|c| NodeId::Name(c)).unwrap_or(conf.raft.id.clone()),
                 .. conf.raft
             },
+            replica: super::ReplicaConfig {
+                source: self.replica_source.clone().or(conf.replica.source.clone()),
+                .. conf.replica
+            },
         }
     }
 }
\ No newline at end of file
@@ -12,4 +12,7 @@ mod cmd_line;
 pub use cmd_line::*;
 
 mod raft;
-pub use raft::*;
\ No newline at end of file
+pub use raft::*;
+
+mod replica;
+pub use replica::*;
\ No newline at end of file
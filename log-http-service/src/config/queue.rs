@@ -1,3 +1,4 @@
+use logs::logqueue::ValidateLevel;
 use serde::{Deserialize, Serialize};
 
 /// Настройки очереди
@@ -8,13 +9,263 @@ pub struct QueueConfig {
 
     /// Создание нового лог файла
     pub new_file: QueueNewFile,
+
+    /// Ключи опций блока, по которым строится вторичный индекс
+    #[serde(default)]
+    pub index_keys: Vec<String>,
+
+    /// Хранить индекс смещений блоков рядом с лог файлом
+    #[serde(default="offset_index_default")]
+    pub offset_index: bool,
+
+    /// Служебный лог файл позиций потребителей,
+    /// должен находиться вне каталога лог файлов очереди
+    #[serde(default="consumers_file_default")]
+    pub consumers_file: String,
+
+    /// Кол-во последних ключей идемпотентности (`Idempotency-Key`), по которым выполняется дедупликация
+    #[serde(default="idempotency_window_default")]
+    pub idempotency_window: usize,
+
+    /// Ограничения хранения лог файлов
+    #[serde(default)]
+    pub retention: QueueRetention,
+
+    /// Автоматическое переключение лог файла
+    #[serde(default)]
+    pub rotation: QueueRotation,
+
+    /// Архив лог файлов, `None` - архив не используется
+    #[serde(default)]
+    pub archive: Option<QueueArchive>,
+
+    /// Проверка очереди при открытии
+    #[serde(default)]
+    pub validate: QueueValidate,
 }
 
+fn offset_index_default() -> bool { true }
+
+fn consumers_file_default() -> String { "${work.dir}/app_data/meta/consumers.binlog".to_string() }
+
+fn idempotency_window_default() -> usize { 10000 }
+
 impl Default for QueueConfig {
     fn default() -> Self {
         Self { 
             find: QueueFind::default(), 
-            new_file: QueueNewFile::default()
+            new_file: QueueNewFile::default(),
+            index_keys: vec![],
+            offset_index: offset_index_default(),
+            consumers_file: consumers_file_default(),
+            idempotency_window: idempotency_window_default(),
+            retention: QueueRetention::default(),
+            rotation: QueueRotation::default(),
+            archive: None,
+            validate: QueueValidate::default(),
+        }
+    }
+}
+
+impl QueueConfig {
+    /// Настройки именованной очереди
+    /// 
+    /// Служебный лог файл потребителей и каталог загрузки из архива по умолчанию
+    /// общие для всех очередей - для именованной очереди они заменяются на каталоги
+    /// с именем очереди `${queue.name}`
+    pub fn named( self, name: &str ) -> Self {
+        if name == crate::queues::DEFAULT_QUEUE {
+            return self;
+        }
+
+        Self {
+            consumers_file: if self.consumers_file == consumers_file_default() {
+                "${work.dir}/app_data/meta/${queue.name}/consumers.binlog".to_string()
+            } else {
+                self.consumers_file
+            },
+            archive: self.archive.map(|archive| QueueArchive {
+                cache_dir: if archive.cache_dir == archive_cache_dir_default() {
+                    "${work.dir}/app_data/archive_cache/${queue.name}".to_string()
+                } else {
+                    archive.cache_dir
+                },
+                ..archive
+            }),
+            ..self
+        }
+    }
+}
+
+/// Ограничения хранения лог файлов
+/// 
+/// Самые старые лог файлы, нарушающие любое из ограничений, исключаются из очереди фоновой задачей,
+/// актуальный лог файл не исключается. Если ограничения не заданы - лог файлы хранятся без ограничения
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueRetention {
+    /// Максимальный суммарный размер лог файлов в байтах
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+
+    /// Максимальный возраст лог файла в секундах - по времени последней записи (опция `time`),
+    /// либо по времени изменения файла
+    #[serde(default)]
+    pub max_age_sec: Option<u64>,
+
+    /// Максимальное кол-во лог файлов
+    #[serde(default)]
+    pub max_files: Option<usize>,
+
+    /// Каталог, в который переносятся исключенные лог файлы, если не указан - файлы удаляются
+    /// 
+    /// Может содержать переменные `${work.dir}`, `${exe.dir}`
+    #[serde(default)]
+    pub archive_dir: Option<String>,
+
+    /// Интервал проверки ограничений в миллисекундах
+    #[serde(default="retention_interval_ms_default")]
+    pub interval_ms: u64,
+}
+
+fn retention_interval_ms_default() -> u64 { 60000 }
+
+impl Default for QueueRetention {
+    fn default() -> Self {
+        Self { 
+            max_bytes: None, 
+            max_age_sec: None, 
+            max_files: None, 
+            archive_dir: None, 
+            interval_ms: retention_interval_ms_default() 
+        }
+    }
+}
+
+/// Автоматическое переключение лог файла
+/// 
+/// Актуальный лог файл переключается, когда выполнено любое из условий.
+/// Условия проверяются после добавления записи и периодически фоновой задачей.
+/// В режиме реплики не применяется - реплика повторяет переключения источника
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueRotation {
+    /// Размер лог файла в байтах
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+
+    /// Кол-во блоков в лог файле
+    #[serde(default)]
+    pub max_blocks: Option<u32>,
+
+    /// Время в секундах с первой записи в лог файле
+    #[serde(default)]
+    pub interval_sec: Option<u64>,
+
+    /// Интервал фоновой проверки в миллисекундах
+    #[serde(default="rotation_check_ms_default")]
+    pub check_ms: u64,
+}
+
+fn rotation_check_ms_default() -> u64 { 1000 }
+
+impl Default for QueueRotation {
+    fn default() -> Self {
+        Self { 
+            max_bytes: None, 
+            max_blocks: None, 
+            interval_sec: None, 
+            check_ms: rotation_check_ms_default() 
+        }
+    }
+}
+
+/// Архив лог файлов
+/// 
+/// Лог файлы, кроме актуального, выгружаются в хранилище фоновой задачей.
+/// Лог файлы, удаленные с диска (см. [QueueRetention]), загружаются из архива при обращении к ним
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueArchive {
+    /// Хранилище
+    pub store: ArchiveStoreConfig,
+
+    /// Каталог, в который загружаются лог файлы из архива
+    #[serde(default="archive_cache_dir_default")]
+    pub cache_dir: String,
+
+    /// Интервал выгрузки в миллисекундах
+    #[serde(default="archive_interval_ms_default")]
+    pub interval_ms: u64,
+}
+
+fn archive_cache_dir_default() -> String { "${work.dir}/app_data/archive_cache".to_string() }
+
+fn archive_interval_ms_default() -> u64 { 60000 }
+
+/// Проверка очереди при открытии
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueValidate {
+    /// Уровень проверки
+    #[serde(default)]
+    pub level: QueueValidateLevel,
+
+    /// Сверять контрольные суммы лог файлов с архивом (если архив указан)
+    #[serde(default)]
+    pub checksums: bool,
+}
+
+/// Уровень проверки очереди
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all="lowercase")]
+pub enum QueueValidateLevel {
+    /// Цепочка идентификаторов лог файлов
+    Quick,
+
+    /// Дополнительно заголовки всех блоков, как и [ValidateLevel::default]
+    #[default]
+    Headers,
+
+    /// Дополнительно все блоки целиком
+    Full,
+}
+
+impl From<QueueValidateLevel> for ValidateLevel {
+    fn from(value: QueueValidateLevel) -> Self {
+        match value {
+            QueueValidateLevel::Quick => ValidateLevel::Quick,
+            QueueValidateLevel::Headers => ValidateLevel::Headers,
+            QueueValidateLevel::Full => ValidateLevel::Full,
+        }
+    }
+}
+
+/// Хранилище архива
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag="type", rename_all="lowercase")]
+pub enum ArchiveStoreConfig {
+    /// Каталог, может содержать переменные `${work.dir}`, `${exe.dir}`, `${queue.name}`
+    Dir { path: String },
+
+    /// Локальная заглушка S3 (например MinIO на том же узле), адрес `http://host:port`,
+    /// без TLS - ключи и данные передаются открытым текстом; префикс может содержать `${queue.name}`
+    S3 {
+        endpoint: String,
+        bucket: String,
+        #[serde(default="s3_region_default")]
+        region: String,
+        access_key: String,
+        secret_key: String,
+        #[serde(default)]
+        prefix: String,
+    },
+}
+
+fn s3_region_default() -> String { "us-east-1".to_string() }
+
+impl QueueArchive {
+    /// Описание хранилища для журнала, без ключей доступа
+    pub fn store_name( &self ) -> String {
+        match &self.store {
+            ArchiveStoreConfig::Dir { path } => format!("dir {path}"),
+            ArchiveStoreConfig::S3 { endpoint, bucket, prefix, .. } => format!("s3 {endpoint}/{bucket}/{prefix}"),
         }
     }
 }
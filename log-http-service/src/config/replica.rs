@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// Настройки репликации
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicaConfig {
+    /// Адрес сервера-источника, например `http://localhost:8080`
+    /// 
+    /// Если не указан, то репликация выключена
+    #[serde(default)]
+    pub source: Option<String>,
+
+    /// Пауза между опросами источника в миллисекундах, когда новых записей нет
+    #[serde(default="poll_interval_ms_default")]
+    pub poll_interval_ms: u64,
+
+    /// Максимальное кол-во записей, копируемых за один шаг
+    #[serde(default="batch_size_default")]
+    pub batch_size: usize,
+}
+
+fn poll_interval_ms_default() -> u64 { 1000 }
+
+fn batch_size_default() -> usize { 100 }
+
+impl Default for ReplicaConfig {
+    fn default() -> Self {
+        Self { 
+            source: None, 
+            poll_interval_ms: poll_interval_ms_default(), 
+            batch_size: batch_size_default() 
+        }
+    }
+}
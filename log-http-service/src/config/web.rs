@@ -10,14 +10,21 @@ pub struct WebServer {
 
     /// Порт на котором весит сервер
     pub port: u16,
+
+    /// Максимальный размер тела запроса на добавление записи, байт
+    #[serde(default="max_body_size_default")]
+    pub max_body_size: usize,
 }
 
+fn max_body_size_default() -> usize { 64 * 1024 * 1024 }
+
 impl Default for WebServer {
     fn default() -> Self {
         Self { 
             static_files: None,
             host: "127.0.0.1".to_string(), 
-            port: 8080 
+            port: 8080,
+            max_body_size: max_body_size_default(),
         }
     }
 }
\ No newline at end of file
@@ -13,35 +13,20 @@ mod static_api;
 /// Raft
 mod raft;
 
+/// Реестр очередей
+mod queues;
+
 use actix_cors::Cors;
-use actix_web::{web, App, HttpServer, guard};
+use actix_web::{web, App, HttpServer, guard, dev::Service};
 use config::AppConfig;
-use logs::{logqueue::{find_logs::FsLogFind, LogQueueConf, LogQueueFileNumID, LogQueueFileNumIDOpen, ValidateStub, LogFileQueue}, bbuff::absbuff::FileBuff, logfile::LogFile};
-use logs::logqueue::path_template2;
 use path_template::PathTemplateParser;
-use std::{env, path::PathBuf, sync::{Arc, Mutex}, marker::PhantomData};
-use log::{info, debug};
+use std::{path::PathBuf, sync::{Arc, Mutex}, time::Duration};
+use log::{info, debug, warn};
+use log_http_client::{QueueClient, Replicator};
 use actix_web::middleware::Logger;
 use env_logger::Env;
 
-use crate::{state::AppState, config::CmdLineParams};
-
-
-/// Очередь
-static mut QUEUE_GLOBAL: Option<Arc<Mutex<dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>>  >>> = None;
-
-/// Работа с очередю
-/// 
-/// Аргументы
-/// 
-/// - `work` - функция получающая ссылку на очередь
-pub fn queue<F,R>( work:F ) -> R 
-where
-    F: Fn( Arc<Mutex<dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>>>> ) -> R
-{
-    let q = unsafe{ QUEUE_GLOBAL.clone().unwrap() };
-    work(q)
-}
+use crate::{state::{AppState, ReplicaState}, config::CmdLineParams, queues::{QueueRegistry, DEFAULT_QUEUE}};
 
 /// Входная точка программы
 #[actix_web::main]
@@ -58,13 +43,8 @@ async fn main() -> std::io::Result<()> {
     info!("starting server on {}:{}", &app_conf.web_server.host, app_conf.web_server.port);
     let conf_t = app_conf.clone();
 
-    fn template_vars<'a>( tp: PathTemplateParser<'a>, conf:Arc<AppConfig> ) -> PathTemplateParser<'a> {
-        tp.with_variable("exe.dir", env::current_exe().map(|f| f.parent().unwrap().to_str().unwrap().to_string() ).unwrap() )
-          .with_variable("work.dir", conf.work_dir.to_string())
-    }
-
     let template_parser = move || {
-        template_vars(PathTemplateParser::default(),conf_t.clone())
+        queues::template_vars(PathTemplateParser::default(), &conf_t, DEFAULT_QUEUE)
     };
 
     // static files ..........
@@ -74,41 +54,55 @@ async fn main() -> std::io::Result<()> {
     });
     let static_files_opt = Arc::new(Mutex::new(static_files_opt));
 
-    // queue ..........
-    debug!("openning queue");
-    let fs_log_find = FsLogFind::new( 
-        &template_parser().parse(&app_conf.queue.find.root).unwrap().generate(), 
-        "*.binlog", 
-        true ).unwrap();
-
-    let log_queue_conf: LogQueueConf<LogQueueFileNumID, PathBuf, FileBuff, _, _, _, _> = {
-        let conf = app_conf.clone();
-        LogQueueConf {
-            find_files: fs_log_find,
-            open_log_file: LogQueueFileNumIDOpen,
-            validate: ValidateStub,
-            new_file: path_template2( &app_conf.queue.new_file.template, move |tp| template_vars(tp, conf.clone())).unwrap(),
-            _p: PhantomData.clone(),
-        }
-    };    
-
-    let queue = log_queue_conf.open().unwrap();
-    let queue: Arc<Mutex<dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>>  >> = Arc::new(Mutex::new(queue));
-
-    unsafe {
-        QUEUE_GLOBAL = Some(queue);
+    // queues ..........
+    debug!("openning queues");
+    let queues = Arc::new(QueueRegistry::new(app_conf.clone()));
+    queues.open_all().unwrap();
+
+    // replica ..........
+    let replica = Arc::new(Mutex::new(ReplicaState { source: app_conf.replica.source.clone(), ..ReplicaState::default() }));
+    if let Some(source) = app_conf.replica.source.clone() {
+        info!("replicate from {source}");
+        let replicator = Replicator {
+            source: QueueClient { http_client: Arc::new(awc::Client::default()), base_url: source },
+            target: queues.get(DEFAULT_QUEUE).unwrap().queue.clone(),
+            batch_size: app_conf.replica.batch_size,
+        };
+        let poll_interval = Duration::from_millis(app_conf.replica.poll_interval_ms);
+        let replica = replica.clone();
+        actix_web::rt::spawn(async move {
+            replicator.run(poll_interval, |res| {
+                let mut state = replica.lock().unwrap();
+                match res {
+                    Ok(status) => {
+                        if status.copied > 0 { debug!("replicated {} records, lag {}", status.copied, status.lag); }
+                        state.status = Some(status);
+                        state.error = None;
+                    },
+                    Err(err) => {
+                        warn!("replication error {err:?}");
+                        state.error = Some(format!("{err:?}"));
+                    }
+                }
+            }).await
+        });
     }
 
-    info!("queue openned");
-
     // configure atix ...........
+    let max_body_size = app_conf.web_server.max_body_size;
     HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
             .allow_any_method()
             .allow_any_header();
 
-        let app = App::new();
+        // адрес /queue/{name}/... заменяется на /queue/... с выбором очереди,
+        // журнал запросов содержит исходный адрес
+        let app = App::new()
+            .wrap_fn(|mut req, srv| {
+                queues::select_queue(&mut req);
+                srv.call(req)
+            });
         let app = 
             app
                 .wrap(cors)
@@ -116,6 +110,9 @@ async fn main() -> std::io::Result<()> {
 
         let app = app.app_data(web::Data::new(AppState {
             static_files: static_files_opt.clone(),
+            queues: queues.clone(),
+            replica: replica.clone(),
+            max_body_size,
         }));
 
         // https://peterevans.dev/posts/how-to-host-swagger-docs-with-github-pages/
@@ -124,6 +121,7 @@ async fn main() -> std::io::Result<()> {
             .service(web::resource("/{name}.{ext:html|css|js|png|jpg}").route(web::route().guard(guard::Get()).to(static_api::get_static)));
         let app = app.service(static_api::hello);
         let app = app.service(web::scope("/queue").configure(queue_api::queue_api_route));
+        let app = app.service(web::scope("/queues").configure(queue_api::queues_api_route));
         app
     })
     .bind((app_conf.clone().web_server.host.clone(), app_conf.web_server.port))?
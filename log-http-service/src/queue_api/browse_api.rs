@@ -0,0 +1,525 @@
+//! Просмотр содержимого очереди в браузере
+//!
+//! Страницы html со списком лог файлов, записей и содержимым записи.
+//! Переход между записями - через [LogNavigate::next_record] / [LogNavigate::previous_record],
+//! данные записи скачиваются через ручки `/record/{log_id}/{block_id}/raw` и `/plain`
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use actix_web::{web, get, HttpResponse};
+use actix_web::http::header::ContentType;
+use actix_web::Result;
+use logs::bbuff::absbuff::FileBuff;
+use logs::logfile::{BlockFilter, LogFile};
+use logs::logfile::block::{BlockId, BlockOptions};
+use logs::logqueue::*;
+use serde::Deserialize;
+
+use crate::queues::{NamedQueue, DEFAULT_QUEUE};
+use crate::queue_api::{parse_rec_id, ApiErr};
+
+/// Кол-во записей на странице по умолчанию
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// Максимальное кол-во записей на странице
+const MAX_PAGE_SIZE: u32 = 1000;
+
+/// Сколько байт данных показывать на странице записи
+const PREVIEW_LIMIT: usize = 64 * 1024;
+
+/// Сколько записей просматривается при поиске за один запрос
+const SEARCH_SCAN_LIMIT: u32 = 100_000;
+
+type QRecID = RecID<LogQueueFileNumID>;
+
+/// Очередь, доступ на чтение
+type Queue = dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>>;
+
+/// Экранирование текста для html
+fn esc( text: &str ) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Кодирование значения параметра url
+fn url_param( text: &str ) -> String {
+    let mut out = String::with_capacity(text.len());
+    for b in text.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            _ => { let _ = write!(out, "%{b:02X}"); }
+        }
+    }
+    out
+}
+
+/// Шестнадцатеричный дамп: смещение, 16 байт, ASCII
+fn hex_dump( data: &[u8] ) -> String {
+    let mut out = String::new();
+    for (line, chunk) in data.chunks(16).enumerate() {
+        let _ = write!(out, "{:08x} ", line * 16);
+        for i in 0..16 {
+            match chunk.get(i) {
+                Some(b) => { let _ = write!(out, " {b:02x}"); }
+                None => out.push_str("   "),
+            }
+        }
+        out.push_str("  ");
+        out.extend(chunk.iter().map(|b| if (0x20..0x7f).contains(b) { *b as char } else { '.' }));
+        out.push('\n');
+    }
+    out
+}
+
+/// Опции блока, упорядоченные по ключу
+fn sorted_options( options: &BlockOptions ) -> BTreeMap<String,String> {
+    options.clone().into_iter().map(|(k,v)| (k.to_string(), v.to_string())).collect()
+}
+
+/// Ссылки страниц очереди
+struct Links {
+    /// Префикс пути очереди: `/queue` или `/queue/{name}`
+    base: String,
+}
+
+impl Links {
+    fn new( queue: &NamedQueue ) -> Self {
+        if queue.name == DEFAULT_QUEUE {
+            Self { base: "/queue".to_string() }
+        } else {
+            Self { base: format!("/queue/{}", queue.name) }
+        }
+    }
+
+    fn index( &self ) -> String {
+        format!("{}/browse", self.base)
+    }
+
+    fn records( &self, from: &QRecID, count: u32 ) -> String {
+        format!("{}/browse/records?from={}/{}&count={count}", self.base, from.log_file_id.id, from.block_id.value())
+    }
+
+    fn last_records( &self, count: u32 ) -> String {
+        format!("{}/browse/records?count={count}", self.base)
+    }
+
+    fn record( &self, rid: &QRecID ) -> String {
+        format!("{}/browse/record/{}/{}", self.base, rid.log_file_id.id, rid.block_id.value())
+    }
+
+    fn raw( &self, rid: &QRecID ) -> String {
+        format!("{}/record/{}/{}/raw", self.base, rid.log_file_id.id, rid.block_id.value())
+    }
+
+    fn plain( &self, rid: &QRecID ) -> String {
+        format!("{}/record/{}/{}/plain", self.base, rid.log_file_id.id, rid.block_id.value())
+    }
+
+    fn search( &self, filter: &str, before: Option<&QRecID>, count: u32 ) -> String {
+        let mut link = format!("{}/browse/search?where={}&count={count}", self.base, url_param(filter));
+        if let Some(rid) = before {
+            let _ = write!(link, "&before={}/{}", rid.log_file_id.id, rid.block_id.value());
+        }
+        link
+    }
+
+    /// Страница html с навигацией и формой поиска
+    fn page( &self, queue: &str, title: &str, filter: &str, body: &str ) -> HttpResponse {
+        let html = format!(
+r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{queue}: {title}</title>
+<style>
+body {{ font-family: sans-serif; font-size: 14px; margin: 1em 2em; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ccc; padding: 2px 6px; text-align: left; vertical-align: top; }}
+th {{ background: #eee; }}
+pre {{ background: #f6f6f6; padding: 0.5em; overflow: auto; }}
+nav a, .paging a {{ margin-right: 1em; }}
+.error {{ color: #b00; }}
+</style>
+</head>
+<body>
+<nav><b>{queue}</b> <a href="{index}">files</a> <a href="{last}">last records</a>
+<form style="display:inline" method="get" action="{search}">
+<input name="where" size="40" placeholder="mime=text/* and size>1k" value="{filter}">
+<input type="submit" value="search">
+</form></nav>
+<h3>{title}</h3>
+{body}
+</body>
+</html>
+"#,
+            queue = esc(queue),
+            title = esc(title),
+            index = self.index(),
+            last = self.last_records(DEFAULT_PAGE_SIZE),
+            search = format_args!("{}/browse/search", self.base),
+            filter = esc(filter),
+        );
+        HttpResponse::Ok().content_type(ContentType::html()).body(html)
+    }
+}
+
+/// Строка таблицы записей
+fn record_row( links: &Links, q: &Queue, rid: &QRecID, out: &mut String ) {
+    let id = format!("{}/{}", rid.log_file_id.id, rid.block_id.value());
+    match q.info(rid.clone()) {
+        Ok(info) => {
+            let options = sorted_options(&info.block_options).iter()
+                .map(|(k,v)| format!("{}={}", esc(k), esc(v)))
+                .collect::<Vec<_>>().join(" ");
+            let _ = writeln!(out,
+                "<tr><td><a href=\"{}\">{id}</a></td><td>{}</td><td>{options}</td><td><a href=\"{}\" download>raw</a> <a href=\"{}\">plain</a></td></tr>",
+                links.record(rid), info.data_size.0, links.raw(rid), links.plain(rid));
+        }
+        Err(err) => {
+            let _ = writeln!(out, "<tr><td>{id}</td><td colspan=\"3\" class=\"error\">{}</td></tr>", esc(&format!("{err:?}")));
+        }
+    }
+}
+
+const RECORDS_HEADER: &str = "<table>\n<tr><th>record</th><th>data size</th><th>options</th><th>download</th></tr>\n";
+
+/// Список лог файлов очереди
+#[get("/browse")]
+pub async fn browse_index( queue: NamedQueue ) -> Result<HttpResponse,ApiErr> {
+    let links = Links::new(&queue);
+    queue.with(|q| {
+        let q = q.read()?;
+        let mut body = String::new();
+        body.push_str("<table>\n<tr><th>log id</th><th>file</th><th>records</th><th>bytes</th></tr>\n");
+        for (log_id, file, log) in q.files().iter() {
+            let first = RecID { log_file_id: *log_id, block_id: BlockId::new(0) };
+            let _ = writeln!(body, "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                links.records(&first, DEFAULT_PAGE_SIZE),
+                log_id.id(),
+                esc(&file.to_string_lossy()),
+                log.count().map(|c| c.to_string()).unwrap_or("-".to_string()),
+                log.bytes_count().map(|c| c.to_string()).unwrap_or("-".to_string()),
+            );
+        }
+        body.push_str("</table>\n");
+        Ok(links.page(&queue.name, "log files", "", &body))
+    })
+}
+
+#[derive(Deserialize)]
+pub struct RecordsQuery {
+    /// Первая запись страницы `{log_id}/{block_id}`, по умолчанию - последние записи
+    from: Option<String>,
+
+    /// Кол-во записей на странице
+    count: Option<u32>,
+}
+
+/// Страница записей
+struct RecordsPage {
+    /// Записи страницы, по порядку
+    records: Vec<QRecID>,
+
+    /// Первая запись предыдущей страницы
+    prev: Option<QRecID>,
+
+    /// Первая запись следующей страницы
+    next: Option<QRecID>,
+}
+
+/// Записи страницы начиная с `from`, без `from` - последние `count` записей
+///
+/// Результат - `None`, если очередь пуста
+fn records_page( q: &Queue, from: Option<QRecID>, count: u32 ) -> Result<Option<RecordsPage>,ApiErr> {
+    let start = match from {
+        Some(from) => resolve_rec_id(q, from),
+        None => match q.last_record()? {
+            Some(mut rid) => {
+                for _ in 1..count {
+                    match q.previous_record(rid.clone())? {
+                        Some(prev) => rid = prev,
+                        None => break,
+                    }
+                }
+                rid
+            }
+            None => return Ok(None),
+        }
+    };
+
+    let mut prev = None;
+    let mut rid = start.clone();
+    for _ in 0..count {
+        match q.previous_record(rid.clone())? {
+            Some(p) => { rid = p; prev = Some(rid.clone()); }
+            None => break,
+        }
+    }
+
+    let mut records = Vec::new();
+    let mut rid = Some(start);
+    for _ in 0..count {
+        let cur = match rid {
+            Some(cur) => cur,
+            None => break,
+        };
+        records.push(cur.clone());
+        rid = q.next_record(cur)?;
+    }
+    let next = rid.filter(|next| q.info(next.clone()).is_ok());
+
+    Ok(Some(RecordsPage { records, prev, next }))
+}
+
+/// Страница записей очереди
+///
+/// Без `from` выводятся последние `count` записей
+#[get("/browse/records")]
+pub async fn browse_records( queue: NamedQueue, query: web::Query<RecordsQuery> ) -> Result<HttpResponse,ApiErr> {
+    let count = query.count.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let from = query.from.as_deref().map(|from| parse_rec_id("from", from)).transpose()?;
+    let links = Links::new(&queue);
+    queue.with(|q| {
+        let q = q.read()?;
+
+        let mut body = String::new();
+        let page = match records_page(&*q, from, count)? {
+            Some(page) => page,
+            None => {
+                body.push_str("<p>queue is empty</p>\n");
+                return Ok(links.page(&queue.name, "records", "", &body));
+            }
+        };
+
+        body.push_str("<div class=\"paging\">");
+        if let Some(prev) = &page.prev {
+            let _ = write!(body, "<a href=\"{}\">&larr; previous</a>", links.records(prev, count));
+        }
+        if let Some(next) = &page.next {
+            let _ = write!(body, "<a href=\"{}\">next &rarr;</a>", links.records(next, count));
+        }
+        body.push_str("</div>\n");
+        body.push_str(RECORDS_HEADER);
+        for rid in &page.records {
+            record_row(&links, &*q, rid, &mut body);
+        }
+        body.push_str("</table>\n");
+
+        let title = match page.records.first() {
+            Some(start) => format!("records from {}/{}", start.log_file_id.id, start.block_id.value()),
+            None => "records".to_string(),
+        };
+        Ok(links.page(&queue.name, &title, "", &body))
+    })
+}
+
+/// Страница записи: заголовок, опции, данные
+#[get("/browse/record/{log:[0-9]+}/{block:[0-9]+}")]
+pub async fn browse_record( queue: NamedQueue, path: web::Path<(String,u32)> ) -> Result<HttpResponse,ApiErr> {
+    let (log_id, block_id) = path.into_inner();
+    let rid = parse_rec_id("record", &format!("{log_id}/{block_id}"))?;
+    let links = Links::new(&queue);
+    queue.with(|q| {
+        let q = q.read()?;
+        let rid = resolve_rec_id(&*q, rid);
+
+        let info = q.info(rid.clone())?;
+        let rec = q.read(rid.clone())?;
+
+        let mut body = String::new();
+        body.push_str("<div class=\"paging\">");
+        if let Some(prev) = q.previous_record(rid.clone())? {
+            let _ = write!(body, "<a href=\"{}\">&larr; previous</a>", links.record(&prev));
+        }
+        let _ = write!(body, "<a href=\"{}\">records</a>", links.records(&rid, DEFAULT_PAGE_SIZE));
+        if let Some(next) = q.next_record(rid.clone())? {
+            if q.info(next.clone()).is_ok() {
+                let _ = write!(body, "<a href=\"{}\">next &rarr;</a>", links.record(&next));
+            }
+        }
+        body.push_str("</div>\n");
+
+        body.push_str("<table>\n");
+        let _ = writeln!(body, "<tr><th>log file</th><td>{}</td></tr>", esc(&info.log_file.to_string_lossy()));
+        let _ = writeln!(body, "<tr><th>position</th><td>{}</td></tr>", info.position.value());
+        let _ = writeln!(body, "<tr><th>head size</th><td>{}</td></tr>", info.head_size.0);
+        let _ = writeln!(body, "<tr><th>data size</th><td>{}</td></tr>", info.data_size.0);
+        let _ = writeln!(body, "<tr><th>tail size</th><td>{}</td></tr>", info.tail_size.0);
+        let _ = writeln!(body, "<tr><th>download</th><td><a href=\"{}\" download>raw</a> <a href=\"{}\">plain</a></td></tr>",
+            links.raw(&rid), links.plain(&rid));
+        body.push_str("</table>\n");
+
+        body.push_str("<h4>options</h4>\n<table>\n<tr><th>key</th><th>value</th></tr>\n");
+        for (k, v) in sorted_options(&rec.options) {
+            let _ = writeln!(body, "<tr><td>{}</td><td>{}</td></tr>", esc(&k), esc(&v));
+        }
+        body.push_str("</table>\n");
+
+        let data = &rec.data[..rec.data.len().min(PREVIEW_LIMIT)];
+        let is_text = rec.options.get("mime")
+            .map(|m| m.value().starts_with("text/") || m.value().ends_with("json"))
+            .unwrap_or(false);
+        body.push_str("<h4>data</h4>\n");
+        match std::str::from_utf8(data) {
+            Ok(text) if is_text => { let _ = writeln!(body, "<pre>{}</pre>", esc(text)); }
+            _ => { let _ = writeln!(body, "<pre>{}</pre>", esc(&hex_dump(data))); }
+        }
+        if rec.data.len() > PREVIEW_LIMIT {
+            let _ = writeln!(body, "<p>shown {PREVIEW_LIMIT} of {} bytes</p>", rec.data.len());
+        }
+
+        let title = format!("record {}/{}", rid.log_file_id.id, rid.block_id.value());
+        Ok(links.page(&queue.name, &title, "", &body))
+    })
+}
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    /// Фильтр по метаданным блока, см. [BlockFilter]
+    #[serde(rename = "where")]
+    filter: String,
+
+    /// Искать записи перед указанной `{log_id}/{block_id}`, по умолчанию - с конца очереди
+    before: Option<String>,
+
+    /// Кол-во записей на странице
+    count: Option<u32>,
+}
+
+/// Результат поиска
+struct SearchPage {
+    /// Найденные записи, от последней к первой
+    found: Vec<QRecID>,
+
+    /// Кол-во просмотренных записей
+    scanned: u32,
+
+    /// Продолжение поиска - перед этой записью
+    more: Option<QRecID>,
+}
+
+/// Поиск не более `count` записей перед `before` (по умолчанию - с конца очереди)
+fn search_page( q: &Queue, filter: &BlockFilter, before: Option<QRecID>, count: u32 ) -> Result<SearchPage,ApiErr> {
+    let mut rid = match before {
+        Some(before) => q.previous_record(resolve_rec_id(q, before))?,
+        None => q.last_record()?,
+    };
+
+    let mut found = Vec::new();
+    let mut scanned = 0;
+    let mut last = None;
+    while let Some(cur) = rid.clone() {
+        if found.len() as u32 >= count || scanned >= SEARCH_SCAN_LIMIT {
+            break;
+        }
+        scanned += 1;
+        if let Ok(info) = q.info(cur.clone()) {
+            if filter.matches(&info.block_options, info.data_size.0 as u64) {
+                found.push(cur.clone());
+            }
+        }
+        last = Some(cur.clone());
+        rid = q.previous_record(cur)?;
+    }
+
+    Ok(SearchPage { found, scanned, more: rid.and(last) })
+}
+
+/// Поиск записей по метаданным, от последней записи к первой
+#[get("/browse/search")]
+pub async fn browse_search( queue: NamedQueue, query: web::Query<SearchQuery> ) -> Result<HttpResponse,ApiErr> {
+    let query = query.into_inner();
+    let count = query.count.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let before = query.before.as_deref().map(|before| parse_rec_id("before", before)).transpose()?;
+    let links = Links::new(&queue);
+
+    let filter = match query.filter.parse::<BlockFilter>() {
+        Ok(filter) => filter,
+        Err(err) => {
+            let body = format!("<p class=\"error\">{}</p>\n", esc(&err.to_string()));
+            return Ok(links.page(&queue.name, "search", &query.filter, &body));
+        }
+    };
+
+    queue.with(|q| {
+        let q = q.read()?;
+        let page = search_page(&*q, &filter, before, count)?;
+
+        let mut body = String::new();
+        let _ = writeln!(body, "<p>found {}, scanned {}</p>", page.found.len(), page.scanned);
+        body.push_str(RECORDS_HEADER);
+        for rid in &page.found {
+            record_row(&links, &*q, rid, &mut body);
+        }
+        body.push_str("</table>\n");
+        if let Some(more) = &page.more {
+            let _ = writeln!(body, "<div class=\"paging\"><a href=\"{}\">more &rarr;</a></div>",
+                links.search(&query.filter, Some(more), count));
+        }
+        Ok(links.page(&queue.name, &format!("search {}", query.filter), &query.filter, &body))
+    })
+}
+
+#[test]
+fn test_browse_helpers() {
+    assert_eq!(esc("<a href=\"x\">&'"), "&lt;a href=&quot;x&quot;&gt;&amp;&#39;");
+    assert_eq!(url_param("mime=text/* and size>1k"), "mime%3Dtext%2F%2A%20and%20size%3E1k");
+    assert_eq!(hex_dump(b"AB\x00"), format!("00000000  41 42 00{}  AB.\n", "   ".repeat(13)));
+}
+
+#[test]
+fn test_browse_paging() {
+    use crate::queue_api::test_queue;
+
+    // 4 лог файла, в каждом блок 0 с идентификатором лог файла и записи
+    let queue = test_queue("browse_paging", &[2, 3, 1, 2]);
+    let q = queue.read().unwrap();
+    let ids = |rids: &[QRecID]| rids.iter().map(|r| format!("{}/{}", r.log_file_id.id, r.block_id.value())).collect::<Vec<_>>();
+
+    // вперед, начиная со второго лог файла
+    let mut all = Vec::new();
+    let mut from = Some(parse_rec_id("from", "1/0").unwrap());
+    while let Some(start) = from {
+        let page = records_page(&*q, Some(start), 3).unwrap().unwrap();
+        all.extend(ids(&page.records));
+        from = page.next;
+    }
+    assert_eq!(all, vec!["1/0", "1/1", "1/2", "1/3", "2/0", "2/1", "3/0", "3/1", "3/2"]);
+
+    // назад от последней страницы
+    let mut page = records_page(&*q, None, 3).unwrap().unwrap();
+    assert_eq!(ids(&page.records), vec!["3/0", "3/1", "3/2"]);
+    let mut pages = 1;
+    while let Some(prev) = page.prev.clone() {
+        page = records_page(&*q, Some(parse_rec_id("from", &ids(&[prev])[0]).unwrap()), 3).unwrap().unwrap();
+        pages += 1;
+    }
+    assert_eq!(pages, 4);
+    assert_eq!(ids(&page.records), vec!["0/0", "0/1", "0/2"]);
+
+    // поиск по одной записи, продолжение с записи из запроса
+    let filter: BlockFilter = "mime=text/plain".parse().unwrap();
+    let mut found = Vec::new();
+    let mut before = None;
+    loop {
+        let page = search_page(&*q, &filter, before, 1).unwrap();
+        found.extend(ids(&page.found));
+        match page.more {
+            Some(more) => before = Some(parse_rec_id("before", &ids(&[more])[0]).unwrap()),
+            None => break,
+        }
+    }
+    assert_eq!(found, vec!["3/2", "3/1", "2/1", "1/3", "1/2", "1/1", "0/2", "0/1"]);
+}
@@ -0,0 +1,125 @@
+use actix_web::{web, Responder, get, post, delete};
+use actix_web::Result;
+use logs::logfile::block::BlockId;
+use logs::logqueue::*;
+use serde::Serialize;
+
+use crate::queues::NamedQueue;
+use crate::queue_api::{ID, ApiErr};
+
+/// Позиция потребителя
+#[derive(Serialize)]
+struct ConsumerInfo {
+    /// Имя потребителя
+    name: String,
+
+    /// Последняя обработанная запись
+    offset: ID,
+
+    /// Кол-во записей в очереди после последней обработанной
+    #[serde(skip_serializing_if="Option::is_none")]
+    lag: Option<u64>,
+
+    #[serde(skip_serializing_if="Option::is_none")]
+    lag_error: Option<String>,
+}
+
+fn consumer_info( queue: &NamedQueue, name: String, offset: RecID<LogQueueFileNumID> ) -> Result<ConsumerInfo,ApiErr> {
+    queue.with(|q| {
+        let q = q.read()?;
+        let (lag, lag_error) = match consumer_lag(&*q, &offset) {
+            Ok(lag) => (Some(lag), None),
+            Err(err) => (None, Some(format!("{err:?}")))
+        };
+        Ok(ConsumerInfo { name, offset: offset.into(), lag, lag_error })
+    })
+}
+
+/// Позиции всех потребителей
+#[get("/consumers")]
+pub async fn get_consumers( queue: NamedQueue ) -> Result<impl Responder,ApiErr> {
+    #[derive(Serialize)]
+    struct Res {
+        consumers: Vec<ConsumerInfo>,
+
+        #[serde(skip_serializing_if="Option::is_none")]
+        last_record: Option<ID>,
+
+        current_log_id: String,
+    }
+
+    let consumers = queue.consumers.lock()
+        .map_err(|err| ApiErr::CantLockQueue { error: err.to_string() })?
+        .consumers();
+
+    let consumers = consumers.into_iter()
+        .map(|(name, offset)| consumer_info(&queue, name, offset))
+        .collect::<Result<Vec<_>,_>>()?;
+
+    let (last_record, current_log_id) = queue.with(|q| {
+        let q = q.read()?;
+        Ok::<_,ApiErr>((q.last_record()?, q.current_log_id()?))
+    })?;
+
+    Ok(web::Json(Res { 
+        consumers, 
+        last_record: last_record.map(|r| r.into()), 
+        current_log_id: current_log_id.id.to_string() 
+    }))
+}
+
+/// Позиция потребителя
+#[get("/consumer/{name}")]
+pub async fn get_consumer( queue: NamedQueue, path: web::Path<String> ) -> Result<impl Responder,ApiErr> {
+    let name = path.into_inner();
+    let offset = queue.consumers.lock()
+        .map_err(|err| ApiErr::CantLockQueue { error: err.to_string() })?
+        .fetch(&name)
+        .ok_or(ApiErr::RecordNotFound)?;
+
+    Ok(web::Json(consumer_info(&queue, name, offset)?))
+}
+
+/// Фиксация позиции потребителя
+/// 
+/// Запись должна существовать в очереди
+#[post("/consumer/{name}/{log:[0-9]+}/{block:[0-9]+}")]
+pub async fn commit_consumer( queue: NamedQueue, path: web::Path<(String,String,u32)> ) -> Result<impl Responder,ApiErr> {
+    let (name, log_id, block_id) = path.into_inner();
+    let log_id = log_id.parse::<u128>()
+        .map_err(|err| ApiErr::InvalidParameter { name: "log".to_string(), error: err.to_string() })?;
+    let offset = RecID {
+        log_file_id: LogQueueFileNumID { id: log_id, previous: None },
+        block_id: BlockId::new(block_id)
+    };
+
+    // позиция сохраняется с идентификатором лог файла из очереди
+    let offset = queue.with(|q| {
+        let q = q.read()?;
+        q.info(offset.clone())?;
+        Ok::<_,ApiErr>(resolve_rec_id(&*q, offset))
+    })?;
+
+    queue.consumers.lock()
+        .map_err(|err| ApiErr::CantLockQueue { error: err.to_string() })?
+        .commit(&name, offset.clone())?;
+
+    Ok(web::Json(consumer_info(&queue, name, offset)?))
+}
+
+/// Удаление потребителя
+#[delete("/consumer/{name}")]
+pub async fn remove_consumer( queue: NamedQueue, path: web::Path<String> ) -> Result<impl Responder,ApiErr> {
+    let name = path.into_inner();
+
+    #[derive(Serialize)]
+    struct Res {
+        removed: bool
+    }
+
+    let removed = queue.consumers.lock()
+        .map_err(|err| ApiErr::CantLockQueue { error: err.to_string() })?
+        .remove(&name)?;
+
+    Ok(web::Json(Res { removed }))
+}
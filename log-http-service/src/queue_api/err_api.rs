@@ -3,7 +3,6 @@ use logs::logqueue::LoqErr;
 use std::fmt::Display;
 use std::fmt::Debug;
 use std::path::PathBuf;
-use std::sync::PoisonError;
 
 #[derive(Debug)]
 pub enum ApiErr 
@@ -23,7 +22,33 @@ pub enum ApiErr
         error: String,
     },
     QueueIsEmpy,
-    LoqErr(String)
+    RecordNotFound,
+    InvalidParameter {
+        name: String,
+        error: String,
+    },
+    LoqErr(String),
+    ConsumerErr(String),
+    TailNotMatched {
+        expected: String,
+        actual: Option<String>,
+    },
+    QueueNotFound {
+        name: String,
+    },
+    QueueExists {
+        name: String,
+    },
+    QueueOpen {
+        name: String,
+        error: String,
+    },
+    PayloadTooLarge {
+        limit: usize,
+    },
+    ReplicaReadOnly {
+        name: String,
+    },
 }
 
 impl Display for ApiErr {
@@ -46,14 +71,39 @@ impl error::ResponseError for ApiErr {
                 format!("CantLockQueue: {error}"),
             Self::QueueIsEmpy =>
                 format!("QueueIsEmpy"),
+            Self::RecordNotFound =>
+                format!("RecordNotFound"),
+            Self::InvalidParameter { name, error } =>
+                format!("InvalidParameter {name}: {error}"),
             Self::LoqErr(err) =>
-                format!("LoqErr: {err}")
+                format!("LoqErr: {err}"),
+            Self::ConsumerErr(err) =>
+                format!("ConsumerErr: {err}"),
+            Self::TailNotMatched { expected, actual } =>
+                format!("TailNotMatched expected={expected} actual={}", actual.as_deref().unwrap_or("none")),
+            Self::QueueNotFound { name } =>
+                format!("QueueNotFound {name}"),
+            Self::QueueExists { name } =>
+                format!("QueueExists {name}"),
+            Self::QueueOpen { name, error } =>
+                format!("QueueOpen {name}: {error}"),
+            Self::PayloadTooLarge { limit } =>
+                format!("PayloadTooLarge limit={limit}"),
+            Self::ReplicaReadOnly { name } =>
+                format!("ReplicaReadOnly {name}"),
         })
     }
 
     fn status_code(&self) -> actix_swagger::StatusCode {
         match self {
             Self::BlockErr(_) => actix_swagger::StatusCode::INTERNAL_SERVER_ERROR,
+            Self::RecordNotFound => actix_swagger::StatusCode::NOT_FOUND,
+            Self::InvalidParameter { name:_, error:_ } => actix_swagger::StatusCode::BAD_REQUEST,
+            Self::TailNotMatched { expected:_, actual:_ } => actix_swagger::StatusCode::PRECONDITION_FAILED,
+            Self::QueueNotFound { name:_ } => actix_swagger::StatusCode::NOT_FOUND,
+            Self::QueueExists { name:_ } => actix_swagger::StatusCode::CONFLICT,
+            Self::PayloadTooLarge { limit:_ } => actix_swagger::StatusCode::PAYLOAD_TOO_LARGE,
+            Self::ReplicaReadOnly { name:_ } => actix_swagger::StatusCode::CONFLICT,
             _ => actix_swagger::StatusCode::INTERNAL_SERVER_ERROR
         }
     }
@@ -65,16 +115,27 @@ impl std::convert::From<logs::logfile::block::BlockErr> for ApiErr {
     }
 }
 
-impl<F,L> std::convert::From<PoisonError<std::sync::MutexGuard<'_, (dyn logs::logqueue::LogFileQueue<logs::logqueue::LogQueueFileNumID, F, L> + 'static)>>>
-for ApiErr {
-    fn from(value: PoisonError<std::sync::MutexGuard<'_, (dyn logs::logqueue::LogFileQueue<logs::logqueue::LogQueueFileNumID, F, L> + 'static)>>) -> Self {
-        Self::CantLockQueue { error: value.to_string() }
-    }
-}
-
 impl std::convert::From<LoqErr<PathBuf, logs::logqueue::LogQueueFileNumID>> for ApiErr {
     fn from(value: LoqErr<PathBuf, logs::logqueue::LogQueueFileNumID>) -> Self {
         //serde_json::to_string(&value);
-        Self::LoqErr(format!("{value:?}"))
+        match value {
+            LoqErr::TailNotMatched { expected_log_id, expected_block_id, actual } => Self::TailNotMatched { 
+                expected: format!("{}/{}", expected_log_id.id, expected_block_id.value()), 
+                actual: actual.map(|(log_id, block_id)| format!("{}/{}", log_id.id, block_id.value())) 
+            },
+            LoqErr::CantCaptureWriteLock { error } => Self::CantLockQueue { error },
+            value => Self::LoqErr(format!("{value:?}"))
+        }
     }
-}
\ No newline at end of file
+}
+
+impl std::convert::From<Box<LoqErr<PathBuf, logs::logqueue::LogQueueFileNumID>>> for ApiErr {
+    fn from(value: Box<LoqErr<PathBuf, logs::logqueue::LogQueueFileNumID>>) -> Self {
+        Self::from(*value)
+    }
+}
+impl std::convert::From<logs::logqueue::ConsumerErr> for ApiErr {
+    fn from(value: logs::logqueue::ConsumerErr) -> Self {
+        Self::ConsumerErr(format!("{value:?}"))
+    }
+}
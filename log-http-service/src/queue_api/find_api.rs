@@ -0,0 +1,54 @@
+use actix_web::{web, Responder, get};
+use actix_web::Result;
+use logs::logqueue::*;
+use serde::{Deserialize, Serialize};
+
+use crate::queues::NamedQueue;
+use crate::queue_api::{ID, ApiErr};
+
+/// Параметры поиска
+#[derive(Deserialize)]
+pub struct FindParams {
+    /// Ключ опции блока
+    pub key: String,
+
+    /// Значение опции блока
+    pub value: String,
+}
+
+/// Поиск записей по значению опции блока
+#[get("/find")]
+pub async fn find_by_option( queue: NamedQueue, params: web::Query<FindParams> ) -> Result<impl Responder,ApiErr> {
+    #[derive(Serialize)]
+    struct Res {
+        values: Vec<ID>
+    }
+
+    queue.with(|q| {
+        let q = q.read()?;
+        let found = q.find_by_option(&params.key, &params.value)?;
+        Ok(web::Json(Res { values: found.into_iter().map(ID::from).collect() }))
+    })
+}
+
+/// Параметры поиска по времени
+#[derive(Deserialize)]
+pub struct SeekParams {
+    /// Время в формате RFC 3339, например `2023-07-21T22:37:38.481669+00:00`
+    pub time: String,
+}
+
+/// Поиск первой записи со временем не раньше указанного
+#[get("/seek")]
+pub async fn seek_time( queue: NamedQueue, params: web::Query<SeekParams> ) -> Result<impl Responder,ApiErr> {
+    let time = chrono::DateTime::parse_from_rfc3339(&params.time)
+        .map_err(|err| ApiErr::InvalidParameter { name: "time".to_string(), error: err.to_string() })?;
+
+    queue.with(|q| {
+        let q = q.read()?;
+        match q.seek_time(&time)? {
+            Some(rid) => Ok(web::Json(ID::from(rid))),
+            None => Err(ApiErr::RecordNotFound)
+        }
+    })
+}
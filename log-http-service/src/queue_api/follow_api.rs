@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use actix_web::{web, get, HttpResponse, Responder};
+use actix_web::Result;
+use base64::Engine;
+use futures::Stream;
+use logs::bbuff::absbuff::FileBuff;
+use logs::logqueue::*;
+use serde::{Deserialize, Serialize};
+
+use crate::queues::{NamedQueue, SharedQueue};
+use crate::queue_api::{ID, ApiErr, parse_rec_id};
+
+/// Чтение очереди с ожиданием новых записей
+pub type QueueFollow = LogFollow<SharedQueue,LogQueueFileNumID,PathBuf,FileBuff>;
+
+/// Ошибка чтения очереди при ожидании записей
+pub type FollowErr = Box<LoqErr<PathBuf,LogQueueFileNumID>>;
+
+/// Событие подписки на очередь
+pub enum FollowEvent {
+    /// Запись
+    Record(RecID<LogQueueFileNumID>, PreparedRecord),
+
+    /// За время ожидания новых записей не появилось
+    Idle,
+}
+
+/// Максимальное время ожидания записей long-poll запроса
+pub const MAX_FOLLOW_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Следующая запись очереди, с ожиданием не дольше `timeout`
+///
+/// Ожидание новых записей асинхронное ([WriteNotify::changed]), поток не блокируется.
+/// Результат `None` - за время ожидания записей не появилось
+pub async fn follow_next( follow:&mut QueueFollow, timeout:Duration ) -> 
+    Result<Option<FollowItem<LogQueueFileNumID>>,FollowErr>
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        let notify = follow.write_notify();
+        let version = notify.version();
+        if let Some(rec) = follow.try_next()? {
+            return Ok(Some(rec));
+        }
+
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() || actix_web::rt::time::timeout(left, notify.changed(version)).await.is_err() {
+            return Ok(None);
+        }
+    }
+}
+
+/// Подписка на очередь
+///
+/// Аргументы
+/// - `queue` - очередь
+/// - `from` - первая читаемая запись
+/// - `idle` - период, через который генерируется [FollowEvent::Idle], если нет новых записей
+///
+/// Поток событий завершается после ошибки чтения очереди
+pub fn follow_stream( queue:SharedQueue, from:RecID<LogQueueFileNumID>, idle:Duration ) ->
+    Result<impl Stream<Item = Result<FollowEvent,FollowErr>>,FollowErr>
+{
+    let follow = QueueFollow::new(queue, from)?;
+    Ok(futures::stream::unfold(Some(follow), move |follow| async move {
+        let mut follow = follow?;
+        let event = match follow_next(&mut follow, idle).await {
+            Ok(None) => Ok(FollowEvent::Idle),
+            Ok(Some((rid,rec))) => Ok(FollowEvent::Record(rid, rec)),
+            Err(err) => Err(err)
+        };
+
+        let follow = if event.is_err() { None } else { Some(follow) };
+        Some((event, follow))
+    }))
+}
+
+/// Запись очереди
+#[derive(Serialize)]
+pub struct FollowRecord {
+    rid: ID,
+    options: HashMap<String,String>,
+
+    /// Данные записи в base64
+    data: String,
+}
+
+impl From<FollowItem<LogQueueFileNumID>> for FollowRecord {
+    fn from((rid, rec): FollowItem<LogQueueFileNumID>) -> Self {
+        Self {
+            rid: rid.into(),
+            options: rec.options.into(),
+            data: base64::engine::general_purpose::STANDARD.encode(&rec.data)
+        }
+    }
+}
+
+/// Параметры long-poll
+#[derive(Deserialize)]
+pub struct FollowParams {
+    /// Максимальное время ожидания записей в миллисекундах, по умолчанию 30 сек,
+    /// не больше [MAX_FOLLOW_TIMEOUT]
+    pub timeout_ms: Option<u64>,
+
+    /// Максимальное кол-во записей в ответе, по умолчанию 100
+    pub limit: Option<usize>,
+}
+
+/// Чтение записей начиная с указанной, с ожиданием новых записей (long-poll)
+///
+/// Если записей нет, то ожидает появления записи, но не дольше `timeout_ms`.
+/// Ответ содержит `next` - идентификатор для следующего запроса
+#[get("/follow/{log:[0-9]+}/{block:[0-9]+}")]
+pub async fn follow_poll( queue: NamedQueue, path: web::Path<(String,u32)>, params: web::Query<FollowParams> ) -> Result<impl Responder,ApiErr> {
+    let (log_id, block_id) = path.into_inner();
+    let from = parse_rec_id("follow", &format!("{log_id}/{block_id}"))?;
+    let timeout = Duration::from_millis(params.timeout_ms.unwrap_or(30_000)).min(MAX_FOLLOW_TIMEOUT);
+    let limit = params.limit.unwrap_or(100).max(1);
+
+    #[derive(Serialize)]
+    struct Res {
+        values: Vec<FollowRecord>,
+        next: ID,
+    }
+
+    let mut follow = QueueFollow::new(queue.queue, from)?;
+    let mut values = Vec::<FollowRecord>::new();
+
+    // первая запись - с ожиданием
+    if let Some(rec) = follow_next(&mut follow, timeout).await? {
+        values.push(rec.into());
+        while values.len() < limit {
+            match follow.try_next()? {
+                Some(rec) => values.push(rec.into()),
+                None => break
+            }
+        }
+    }
+
+    let res = Res { values, next: follow.position().into() };
+    Ok(web::Json(res))
+}
+
+/// Подписка на записи очереди (Server-Sent Events)
+///
+/// Каждая запись передается событием `record`, идентификатор события - `{log_id}/{block_id}`.
+/// При отсуствии записей периодически передается комментарий `keep-alive`
+#[get("/subscribe/{log:[0-9]+}/{block:[0-9]+}")]
+pub async fn follow_sse( queue: NamedQueue, path: web::Path<(String,u32)> ) -> Result<HttpResponse,ApiErr> {
+    use futures::StreamExt;
+
+    let (log_id, block_id) = path.into_inner();
+    let from = parse_rec_id("subscribe", &format!("{log_id}/{block_id}"))?;
+
+    let events = follow_stream(queue.queue, from, Duration::from_secs(15))?
+        .map(|event| {
+            let text = match event {
+                Ok(FollowEvent::Idle) => ": keep-alive\n\n".to_string(),
+                Ok(FollowEvent::Record(rid, rec)) => {
+                    let id = format!("{}/{}", rid.log_file_id.id, rid.block_id.value());
+                    let json = serde_json::to_string(&FollowRecord::from((rid, rec)))
+                        .unwrap_or_default();
+                    format!("event: record\nid: {id}\ndata: {json}\n\n")
+                },
+                Err(err) => format!("event: error\ndata: {err:?}\n\n")
+            };
+            Ok::<_,actix_web::Error>(web::Bytes::from(text))
+        });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .append_header(("Cache-Control","no-cache"))
+        .streaming(events))
+}
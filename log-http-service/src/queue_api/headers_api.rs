@@ -1,12 +1,13 @@
-use crate::queue;
+use crate::queues::NamedQueue;
 use crate::queue_api::{ID, ApiErr};
 
 use std::collections::HashMap;
 
 use actix_web::{web, Responder, get};
 use actix_web::Result;
+use logs::logfile::BlockFilter;
 use logs::logqueue::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 // trait ShouldSkip { fn should_skip(&self) -> bool; }
 
@@ -18,12 +19,30 @@ use serde::Serialize;
 //     fn should_skip(&self) { self.is_none() }
 // }
 
+/// Сколько записей просматривается с фильтром `where` за один запрос
+const FILTER_SCAN_LIMIT: u32 = 100_000;
+
+#[derive(Deserialize)]
+pub struct HeadersQuery {
+    /// Фильтр по метаданным блока, см. [BlockFilter]
+    #[serde(rename = "where")]
+    filter: Option<String>,
+}
+
 /// Просмотр заголовков последних n записей
+///
+/// С фильтром `where` выводятся последние n подходящих записей,
+/// просматривается не более [FILTER_SCAN_LIMIT] записей
 #[get("/headers/last/{count}")]
-pub async fn lasn_n_headers( path: web::Path<u32> ) -> Result<impl Responder,ApiErr> {
+pub async fn lasn_n_headers( queue: NamedQueue, path: web::Path<u32>, query: web::Query<HeadersQuery> ) -> Result<impl Responder,ApiErr> {
     let cnt: u32 = path.into_inner();
-    queue(|q| {
-        let q = q.lock()?;
+    let filter = match &query.filter {
+        Some(text) => Some(text.parse::<BlockFilter>()
+            .map_err(|err| ApiErr::InvalidParameter { name: "where".to_string(), error: err.to_string() })?),
+        None => None,
+    };
+    queue.with(|q| {
+        let q = q.read()?;
 
         #[derive(Serialize)]
         struct Item {
@@ -55,6 +74,10 @@ pub async fn lasn_n_headers( path: web::Path<u32> ) -> Result<impl Responder,Api
 
             #[serde(skip_serializing_if="Option::is_none")]
             navigate_error: Option<String>,
+
+            /// Просмотр остановлен по [FILTER_SCAN_LIMIT], не все записи проверены
+            #[serde(skip_serializing_if="std::ops::Not::not")]
+            scan_limit: bool,
         }
 
         let mut res = Vec::<Item>::new();
@@ -62,14 +85,24 @@ pub async fn lasn_n_headers( path: web::Path<u32> ) -> Result<impl Responder,Api
 
         match q.last_record().unwrap() {
             None => {
-                Ok( web::Json(Result{ values: res, navigate_error:nav_err }) )
+                Ok( web::Json(Result{ values: res, navigate_error:nav_err, scan_limit: false }) )
             }
             Some(mut rid) => {
                 let mut cnt = cnt;
+                let mut scanned = 0;
+                let mut scan_limit = false;
                 while cnt > 0 {
-                    cnt -= 1;
+                    if filter.is_some() && scanned >= FILTER_SCAN_LIMIT {
+                        scan_limit = true;
+                        break;
+                    }
+                    scanned += 1;
                     match q.info(rid.clone()) {
+                        Ok(opts) if filter.as_ref()
+                            .map(|f| !f.matches(&opts.block_options, opts.data_size.0 as u64))
+                            .unwrap_or(false) => {},
                         Ok(opts) => {
+                            cnt -= 1;
                             let enc = opts.block_options.get("mime").and_then(
                                 |mime| {
                                     if mime.value().starts_with("text/") {
@@ -105,6 +138,7 @@ pub async fn lasn_n_headers( path: web::Path<u32> ) -> Result<impl Responder,Api
                                 });
                         },
                         Err(err) => {
+                            cnt -= 1;
                             res.push(
                                 Item { 
                                     rid: rid.clone().into(), 
@@ -123,7 +157,7 @@ pub async fn lasn_n_headers( path: web::Path<u32> ) -> Result<impl Responder,Api
                         }
                     }
                 }
-                Ok( web::Json(Result{ values: res, navigate_error:nav_err }) )
+                Ok( web::Json(Result{ values: res, navigate_error:nav_err, scan_limit }) )
             }
         }
     })
@@ -4,12 +4,12 @@ use serde::Serialize;
 
 use logs::logqueue::*;
 
-use crate::queue;
+use crate::queues::NamedQueue;
 use crate::queue_api::{ID, ApiErr};
 
 /// Получение списка файлов
 #[get("/log/files")]
-pub async fn get_queue_files() -> Result<impl Responder,ApiErr> {
+pub async fn get_queue_files( queue: NamedQueue ) -> Result<impl Responder,ApiErr> {
     #[derive(Serialize)]
     struct LogFileInfo {
         log_id: String,
@@ -28,8 +28,8 @@ pub async fn get_queue_files() -> Result<impl Responder,ApiErr> {
         files: Vec<LogFileInfo>
     }
 
-    queue(|q| {
-        let q = q.lock()?;
+    queue.with(|q| {
+        let q = q.read()?;
         Ok(web::Json(Res {
             files: q.files().iter().map(|(log_id,f,l)|
                 LogFileInfo { 
@@ -53,9 +53,9 @@ pub async fn get_queue_files() -> Result<impl Responder,ApiErr> {
 
 /// Получение текущее id последней записи
 #[get("/tail/id")]
-async fn get_cur_id() -> Result<impl Responder,ApiErr> {
-    queue(|q| {
-        let q = q.lock()?; 
+async fn get_cur_id( queue: NamedQueue ) -> Result<impl Responder,ApiErr> {
+    queue.with(|q| {
+        let q = q.read()?; 
         match q.last_record()? {
             Some(rid) => Ok(web::Json( ID::from(rid) )),
             None => Err(ApiErr::QueueIsEmpy)
@@ -0,0 +1,280 @@
+use actix_web::{web, Responder, post, HttpRequest};
+use actix_web::Result;
+use actix_web::error::PayloadError;
+use actix_web::http::header::{CONTENT_TYPE, CONTENT_ENCODING, IF_MATCH, ETAG};
+use chrono::Utc;
+use futures::{Stream, StreamExt};
+use logs::logfile::block::BlockOptions;
+use logs::logqueue::*;
+use log::{info, warn};
+
+use crate::queues::{NamedQueue, WritableQueue};
+use crate::queue_api::{ID, ApiErr};
+use crate::queue_api::rec_id::parse_rec_id;
+use crate::queue_api::plain_api::record_time;
+use crate::state::AppState;
+
+/// Префикс заголовков, которые сохраняются в опциях блока
+pub const OPT_HEADER_PREFIX: &str = "x-opt-";
+
+/// Заголовок - ключ идемпотентности
+pub const IDEMPOTENCY_HEADER: &str = "idempotency-key";
+
+/// Опции, которые заполняет сервис, их нельзя задать заголовком `X-Opt-{key}`
+///
+/// `time` должно возрастать в порядке записи - на этом основан поиск по времени
+pub const RESERVED_OPTIONS: [&str; 5] = ["time", "mime", "encoding", "content_encoding", IDEMPOTENCY_KEY];
+
+/// Опции блока из заголовков запроса
+/// 
+/// - `Content-Type` - опция `mime`, параметр `charset` - опция `encoding`
+/// - `Content-Encoding` - опция `content_encoding`
+/// - `X-Opt-{key}` - опция `{key}` (имя в нижнем регистре), опции [RESERVED_OPTIONS] - ошибка
+/// - опция `time` - время добавления записи
+fn header_options( req: &HttpRequest, default_mime: &str ) -> Result<BlockOptions,ApiErr> {
+    let header = |name: &str, value: &actix_web::http::header::HeaderValue| {
+        value.to_str()
+            .map(|v| v.to_string())
+            .map_err(|err| ApiErr::InvalidParameter { name: name.to_string(), error: err.to_string() })
+    };
+
+    let mut opts = BlockOptions::default();
+    opts.set("time", record_time(Utc::now()))?;
+
+    let content_type = match req.headers().get(CONTENT_TYPE) {
+        Some(value) => header(CONTENT_TYPE.as_str(), value)?,
+        None => default_mime.to_string()
+    };
+    let mut params = content_type.split(';').map(|s| s.trim());
+    let mime = params.next().filter(|m| !m.is_empty()).unwrap_or(default_mime);
+    opts.set("mime", mime)?;
+    for param in params {
+        if let Some((name, value)) = param.split_once('=') {
+            if name.trim().eq_ignore_ascii_case("charset") {
+                opts.set("encoding", value.trim().trim_matches('"'))?;
+            }
+        }
+    }
+
+    if let Some(value) = req.headers().get(CONTENT_ENCODING) {
+        opts.set("content_encoding", header(CONTENT_ENCODING.as_str(), value)?)?;
+    }
+
+    for (name, value) in req.headers().iter() {
+        if let Some(key) = name.as_str().strip_prefix(OPT_HEADER_PREFIX) {
+            if RESERVED_OPTIONS.contains(&key) {
+                return Err(ApiErr::InvalidParameter { name: name.to_string(), error: format!("option {key} is reserved") });
+            }
+            if !key.is_empty() {
+                opts.set(key, header(name.as_str(), value)?)?;
+            }
+        }
+    }
+
+    Ok(opts)
+}
+
+/// Максимальный размер тела запроса, см. [crate::config::WebServer::max_body_size]
+fn max_body_size( req: &HttpRequest ) -> usize {
+    req.app_data::<web::Data<AppState>>()
+        .map(|state| state.max_body_size)
+        .unwrap_or(usize::MAX)
+}
+
+/// Чтение тела запроса без распаковки (`Content-Encoding` сохраняется в опциях)
+/// 
+/// Если тело больше `limit` байт - ошибка [ApiErr::PayloadTooLarge]
+async fn read_body<S>( mut payload: S, limit: usize ) -> Result<Vec<u8>,ApiErr>
+where
+    S: Stream<Item = Result<web::Bytes,PayloadError>> + Unpin
+{
+    let mut data = Vec::<u8>::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|err| ApiErr::InvalidParameter { name: "body".to_string(), error: err.to_string() })?;
+        if data.len() + chunk.len() > limit {
+            return Err(ApiErr::PayloadTooLarge { limit });
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+/// Ожидаемая последняя запись очереди из заголовка `If-Match`
+/// 
+/// Значение - идентификатор записи `{log_id}/{block_id}`, допускаются кавычки и префикс `W/`;
+/// `*` - без проверки
+fn expected_tail( req: &HttpRequest ) -> Result<Option<RecID<LogQueueFileNumID>>,ApiErr> {
+    let value = match req.headers().get(IF_MATCH) {
+        Some(value) => value.to_str()
+            .map_err(|err| ApiErr::InvalidParameter { name: IF_MATCH.to_string(), error: err.to_string() })?,
+        None => return Ok(None)
+    };
+
+    let value = value.trim();
+    let value = value.strip_prefix("W/").unwrap_or(value).trim_matches('"');
+    if value == "*" {
+        return Ok(None);
+    }
+
+    Ok(Some(parse_rec_id(IF_MATCH.as_str(), value)?))
+}
+
+/// Запись, с проверкой последней записи очереди, если она указана
+fn append( queue: &NamedQueue, expected: Option<RecID<LogQueueFileNumID>>, record: PreparedRecord ) -> Result<RecID<LogQueueFileNumID>,ApiErr> {
+    queue.with(|q|{
+        let q = q.writer()?;
+        Ok(match expected {
+            Some(expected) => q.append_if_tail(expected, record)?,
+            None => q.write(&record)?
+        })
+    })
+}
+
+/// Переключение лог файла, если выполнены условия [NamedQueue::rotation]
+/// 
+/// Условия проверяются с доступом читателя, монопольный доступ захватывается только для переключения,
+/// условия перепроверяются под ним - лог файл мог уже переключить параллельный запрос.
+/// Ошибка переключения не влияет на результат уже выполненной записи
+pub fn rotate_tail( queue: &NamedQueue ) {
+    if queue.rotation.is_empty() {
+        return;
+    }
+
+    let res = queue.with(|q|{
+        let now = Utc::now().fixed_offset();
+        let due = {
+            let q = q.read().map_err(|err| format!("{err:?}"))?;
+            rotation_due(&*q, &queue.rotation, now).map_err(|err| format!("{err:?}"))?
+        };
+        if !due {
+            return Ok(None);
+        }
+
+        let mut q = q.exclusive().map_err(|err| format!("{err:?}"))?;
+        rotate_if_due(&mut *q, &queue.rotation, now).map_err(|err| format!("{err:?}"))
+    });
+    match res {
+        Ok(Some((file, _))) => info!("log file switched to {}", file.display()),
+        Ok(None) => {},
+        Err(err) => warn!("can't switch log file: {err}")
+    }
+}
+
+/// Ответ на добавление записи, заголовок `ETag` - идентификатор добавленной записи,
+/// его можно передать в `If-Match` следующего запроса
+pub fn inserted( id: ID ) -> impl Responder {
+    let etag = format!("\"{}/{}\"", id.log_id, id.block_id);
+    web::Json(id).customize().insert_header((ETAG, etag))
+}
+
+/// Добавление записи с учетом ключа идемпотентности и ожидаемой последней записи
+/// 
+/// Если в запросе указан заголовок `Idempotency-Key` и запись с таким ключем уже добавлена,
+/// то возвращается идентификатор ранее добавленной записи, новая запись не добавляется.
+/// Ключ сохраняется в опции блока [IDEMPOTENCY_KEY]
+/// 
+/// Если указан заголовок `If-Match` и последняя запись очереди отличается от указанной,
+/// то запись не добавляется - ошибка [ApiErr::TailNotMatched]
+pub fn write_idempotent( queue: &NamedQueue, req: &HttpRequest, mut record: PreparedRecord ) -> Result<ID,ApiErr> {
+    let expected = expected_tail(req)?;
+    let key = match req.headers().get(IDEMPOTENCY_HEADER) {
+        Some(value) => Some(value.to_str()
+            .map_err(|err| ApiErr::InvalidParameter { name: IDEMPOTENCY_HEADER.to_string(), error: err.to_string() })?
+            .to_string()),
+        None => None
+    };
+
+    let key = match key {
+        Some(key) => key,
+        None => {
+            let rid = append(queue, expected, record)?;
+            rotate_tail(queue);
+            return Ok(rid.into());
+        }
+    };
+
+    // блокировка окна удерживается на время записи, что бы одновременные запросы с одним ключем не создали дубликат
+    let mut dedup = queue.dedup.lock()
+        .map_err(|err| ApiErr::CantLockQueue { error: err.to_string() })?;
+
+    if let Some(rid) = dedup.get(&key) {
+        return Ok(rid.into());
+    }
+
+    record.options.set(IDEMPOTENCY_KEY, key.clone())?;
+    let rid = append(queue, expected, record)?;
+
+    dedup.insert(&key, rid.clone());
+    rotate_tail(queue);
+    Ok(rid.into())
+}
+
+/// Добавление записи с произвольными данными
+/// 
+/// Опции блока берутся из заголовков запроса, `mime` по умолчанию `application/octet-stream`
+#[post("/insert")]
+pub async fn insert_bytes( queue: WritableQueue, req: HttpRequest, payload: web::Payload ) -> Result<impl Responder,ApiErr> {
+    let options = header_options(&req, "application/octet-stream")?;
+    let data = read_body(payload, max_body_size(&req)).await?;
+    Ok(inserted(write_idempotent(&queue, &req, PreparedRecord { data, options })?))
+}
+
+/// Добавление json записи
+/// 
+/// Тело запроса должно быть корректным json, сохраняется без изменений с `mime=application/json`;
+/// сжатое тело (`Content-Encoding`) не принимается - его нельзя проверить без распаковки
+#[post("/insert/json")]
+pub async fn insert_json( queue: WritableQueue, req: HttpRequest, payload: web::Payload ) -> Result<impl Responder,ApiErr> {
+    let mut options = header_options(&req, "application/json")?;
+    options.set("mime", "application/json")?;
+    if options.get("encoding").is_none() {
+        options.set("encoding", "utf-8")?;
+    }
+
+    if let Some(encoding) = options.get("content_encoding") {
+        if !encoding.value().eq_ignore_ascii_case("identity") {
+            return Err(ApiErr::InvalidParameter {
+                name: CONTENT_ENCODING.to_string(),
+                error: format!("compressed body ({}) not supported, use /insert", encoding.value())
+            });
+        }
+    }
+
+    let data = read_body(payload, max_body_size(&req)).await?;
+    serde_json::from_slice::<serde_json::Value>(&data)
+        .map_err(|err| ApiErr::InvalidParameter { name: "body".to_string(), error: err.to_string() })?;
+
+    Ok(inserted(write_idempotent(&queue, &req, PreparedRecord { data, options })?))
+}
+
+#[test]
+fn test_read_body_limit() {
+    let body = || futures::stream::iter(vec![
+        Ok(web::Bytes::from_static(b"0123")),
+        Ok(web::Bytes::from_static(b"4567")),
+    ]);
+
+    let data = futures::executor::block_on(read_body(body(), 8)).unwrap();
+    assert_eq!(data, b"01234567");
+
+    let res = futures::executor::block_on(read_body(body(), 7));
+    assert!(matches!(res, Err(ApiErr::PayloadTooLarge { limit: 7 })));
+}
+
+#[test]
+fn test_header_options_reserved() {
+    use actix_web::test::TestRequest;
+
+    let req = TestRequest::default()
+        .insert_header((CONTENT_TYPE, "text/plain; charset=utf-8"))
+        .insert_header(("X-Opt-Source", "cam1"))
+        .to_http_request();
+    let opts = header_options(&req, "application/octet-stream").unwrap();
+    assert_eq!(opts.get("mime").map(|v| v.value().to_string()), Some("text/plain".to_string()));
+    assert_eq!(opts.get("source").map(|v| v.value().to_string()), Some("cam1".to_string()));
+
+    for key in RESERVED_OPTIONS {
+        let req = TestRequest::default().insert_header((format!("X-Opt-{key}"), "x")).to_http_request();
+        assert!(matches!(header_options(&req, "text/plain"), Err(ApiErr::InvalidParameter { .. })), "{key}");
+    }
+}
@@ -3,14 +3,14 @@ use actix_web::Result;
 use logs::logqueue::*;
 use serde::Serialize;
 
-use crate::queue;
+use crate::queues::WritableQueue;
 use crate::queue_api::ApiErr;
 
 /// Переключение лог файла
 #[post("/tail/switch")]
-pub async fn log_switch() -> Result<impl Responder,ApiErr> {
-    queue(|q|{
-        let mut q = q.lock()?;
+pub async fn log_switch( queue: WritableQueue ) -> Result<impl Responder,ApiErr> {
+    queue.with(|q|{
+        let mut q = q.exclusive()?;
         let res = q.switch()?;
 
         #[derive(Serialize)]
@@ -7,8 +7,18 @@ mod info_api;
 mod headers_api;
 mod log_switch_api;
 mod plain_api;
+mod insert_api;
 mod raw_api;
 mod ver_api;
+mod find_api;
+mod range_api;
+mod browse_api;
+
+mod follow_api;
+mod consumer_api;
+mod replica_api;
+mod queues_api;
+mod validate_api;
 
 mod err_api;
 pub use err_api::*;
@@ -20,10 +30,74 @@ pub fn queue_api_route( cfg: &mut web::ServiceConfig ) {
      .service(info_api::get_cur_id)
      .service(headers_api::lasn_n_headers)
      .service(plain_api::insert_plain)
+     .service(insert_api::insert_bytes)
+     .service(insert_api::insert_json)
      .service(plain_api::read_plain)
      .service(raw_api::read_block)
      .service(raw_api::write_block)
+     .service(raw_api::write_block_exact)
+     .service(range_api::read_range)
+     .service(find_api::find_by_option)
+     .service(find_api::seek_time)
+     .service(follow_api::follow_poll)
+     .service(follow_api::follow_sse)
+     .service(consumer_api::get_consumers)
+     .service(consumer_api::get_consumer)
+     .service(consumer_api::commit_consumer)
+     .service(consumer_api::remove_consumer)
+     .service(replica_api::replica_status)
      .service(ver_api::get_version)
+     .service(validate_api::validate_queue)
+     .service(browse_api::browse_index)
+     .service(browse_api::browse_records)
+     .service(browse_api::browse_record)
+     .service(browse_api::browse_search)
      .service(log_switch_api::log_switch);
  }
- 
\ No newline at end of file
+ 
+/// настройка ручек управления очередями
+pub fn queues_api_route( cfg: &mut web::ServiceConfig ) {
+    cfg
+     .service(queues_api::list_queues)
+     .service(queues_api::get_queue)
+     .service(queues_api::create_queue)
+     .service(queues_api::remove_queue);
+}
+
+/// Очередь для тестов в каталоге `./target/test/{name}`
+///
+/// Лог файлы очереди содержат по `records[i]` текстовых записей `{log}/{n}`
+#[cfg(test)]
+pub(crate) fn test_queue( name: &str, records: &[u32] ) -> crate::queues::SharedQueue {
+    use std::marker::PhantomData;
+    use std::path::PathBuf;
+    use logs::bbuff::absbuff::FileBuff;
+    use logs::logfile::block::BlockOptions;
+    use logs::logqueue::find_logs::FsLogFind;
+    use logs::logqueue::*;
+
+    let root = PathBuf::from(format!("./target/test/{name}"));
+    if root.exists() { std::fs::remove_dir_all(&root).unwrap(); }
+    std::fs::create_dir_all(&root).unwrap();
+
+    let conf: LogQueueConf<LogQueueFileNumID, PathBuf, FileBuff, _, _, _, _> = LogQueueConf {
+        find_files: FsLogFind::new(root.to_str().unwrap(), "*.binlog", true).unwrap(),
+        open_log_file: LogQueueFileNumIDOpen,
+        validate: ValidateStub,
+        new_file: path_template(root.to_str().unwrap(), "${root}/${time:local:yyyy-mm-ddThh-mi-ss}-${rnd:5}.binlog").unwrap(),
+        _p: PhantomData,
+    };
+    let queue = SharedLogQueue::new(conf.open().unwrap());
+
+    for (log, count) in records.iter().enumerate() {
+        if log > 0 {
+            queue.exclusive().unwrap().switch().unwrap();
+        }
+        for n in 0..*count {
+            let mut options = BlockOptions::default();
+            options.set("mime", "text/plain").unwrap();
+            queue.writer().unwrap().write(&PreparedRecord { data: format!("{log}/{n}").into_bytes(), options }).unwrap();
+        }
+    }
+    queue
+}
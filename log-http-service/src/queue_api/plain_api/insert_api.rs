@@ -1,4 +1,4 @@
-use actix_web::{web, Responder, post};
+use actix_web::{Responder, post, HttpRequest};
 use actix_web::Result;
 use chrono::{DateTime, Utc};
 use date_format::{DateFormatParser, Format};
@@ -8,22 +8,28 @@ use parse::Parser;
 use encoding::all::UTF_8;
 use encoding::{Encoding, EncoderTrap};
 
-use crate::queue;
-use crate::queue_api::{ID, ApiErr};
+use crate::queue_api::ApiErr;
+use crate::queue_api::insert_api::{write_idempotent, inserted};
+use crate::queues::WritableQueue;
 
 struct PlainText {
     content: String,
     time: DateTime<Utc>,
 }
 
+/// Значение опции `time` - время добавления записи
+pub fn record_time( time: DateTime<Utc> ) -> String {
+    let df: date_format::DateFormat = DateFormatParser::default().parse("utc:yyyy-mm-ddThh:mi:ss.s6zhm").unwrap().0;
+    time.format(df)
+}
+
 impl From<PlainText> for PreparedRecord {
     fn from(value: PlainText) -> Self {
         let res = UTF_8.encode(&value.content, EncoderTrap::Ignore).unwrap();
-        let df: date_format::DateFormat = DateFormatParser::default().parse("utc:yyyy-mm-ddThh:mi:ss.s6zhm").unwrap().0;
 
         let mut opts = BlockOptions::default();
         opts.set("encoding", UTF_8.name()).unwrap();
-        opts.set("time", value.time.format(df)).unwrap();
+        opts.set("time", record_time(value.time)).unwrap();
         opts.set("mime", "text/plain").unwrap();
 
         PreparedRecord { 
@@ -35,12 +41,8 @@ impl From<PlainText> for PreparedRecord {
 
 /// Добавление plain записи
 #[post("/insert/plain")]
-pub async fn insert_plain(req_body: String) -> Result<impl Responder,ApiErr> {
-    queue(|q|{
-        let q = q.lock()?;
-        let pr: PreparedRecord = PlainText { content: req_body.clone(), time: Utc::now() }.into();
-        let rid = q.write( &pr )?;
-        let id: ID = rid.into();
-        Ok( web::Json(id) )
-    })
+pub async fn insert_plain(queue: WritableQueue, req: HttpRequest, req_body: String) -> Result<impl Responder,ApiErr> {
+    let pr: PreparedRecord = PlainText { content: req_body, time: Utc::now() }.into();
+    let id = write_idempotent(&queue, &req, pr)?;
+    Ok( inserted(id) )
 }
@@ -4,7 +4,13 @@ use logs::logqueue::*;
 use serde::Deserialize;
 use futures::{future::ok, stream::once};
 
-use crate::{queue, queue_api::ApiErr};
+use crate::{queues::NamedQueue, queue_api::ApiErr};
+
+/// Типы данных, которые отдаются для показа в браузере, остальные - как вложение (`Content-Disposition: attachment`)
+const INLINE_MIME: [&str; 3] = ["text/plain", "application/json", "application/octet-stream"];
+
+/// Заголовки ответа, которые не заменяются опциями блока (`opt2head`)
+const PROTECTED_HEADERS: [&str; 4] = ["content-type", "content-encoding", "content-disposition", "x-content-type-options"];
 
 #[derive(Deserialize,Clone)]
 pub struct RawBodyOpts {
@@ -17,7 +23,7 @@ pub struct RawBodyOpts {
 
 /// Получение тела записи
 #[get("/record/{log:[0-9]+}/{block:[0-9]+}/plain")]
-pub async fn read_plain(path: web::Path<(String,u32)>, query:web::Query<RawBodyOpts>) -> Result<HttpResponse,ApiErr> {
+pub async fn read_plain(queue: NamedQueue, path: web::Path<(String,u32)>, query:web::Query<RawBodyOpts>) -> Result<HttpResponse,ApiErr> {
     let raw_opt = query.into_inner();
 
     let (log_id, block_id) = path.into_inner();
@@ -29,8 +35,8 @@ pub async fn read_plain(path: web::Path<(String,u32)>, query:web::Query<RawBodyO
 
     let prefix = raw_opt.clone().opt_prefix.unwrap_or("".to_string());
 
-    queue(move |q| {
-        let q = q.lock()?;
+    queue.with(move |q| {
+        let q = q.read()?;
         let rec = q.read(rec_id.clone())?;
 
         let bytes = web::Bytes::from(rec.data);
@@ -38,16 +44,21 @@ pub async fn read_plain(path: web::Path<(String,u32)>, query:web::Query<RawBodyO
 
         let mut response = HttpResponse::Ok();
 
-        let ct = || {
-            rec.options.get("mime").map(|mime| {
-                match mime.value() {
-                    "text/plain" => "text/plain",
-                    _ => "application/octet-stream"
-                }
-            }).unwrap_or("application/octet-stream")
-        };
+        let ct = rec.options.get("mime")
+            .map(|mime| mime.value().to_string())
+            .unwrap_or("application/octet-stream".to_string());
 
-        let response = response.content_type(ct());
+        // mime задает клиент при добавлении записи, браузер не должен исполнять данные (text/html, svg ...)
+        // на странице сервиса
+        let essence = ct.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        let response = response.content_type(ct);
+        response.insert_header((actix_web::http::header::X_CONTENT_TYPE_OPTIONS, "nosniff"));
+        if !INLINE_MIME.contains(&essence.as_str()) {
+            response.insert_header((actix_web::http::header::CONTENT_DISPOSITION, "attachment"));
+        }
+        if let Some(content_encoding) = rec.options.get("content_encoding") {
+            response.insert_header((actix_web::http::header::CONTENT_ENCODING, content_encoding.value()));
+        }
 
         let response = if raw_opt.clone().opt2head.unwrap_or(false) {
             let mut itr = rec.options.into_iter();
@@ -58,7 +69,9 @@ pub async fn read_plain(path: web::Path<(String,u32)>, query:web::Query<RawBodyO
                             key = k.value(),
                             pref = prefix
                         );
-                        response.append_header((k, v.value()));
+                        if !PROTECTED_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(&k)) {
+                            response.append_header((k, v.value()));
+                        }
                     },
                     None => {
                         break response
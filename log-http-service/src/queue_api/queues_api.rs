@@ -0,0 +1,78 @@
+use actix_web::{web, Responder, HttpResponse, get, put, delete};
+use actix_web::Result;
+use serde::Serialize;
+
+use crate::config::QueueConfig;
+use crate::queue_api::ApiErr;
+use crate::queues::{NamedQueue, QueueSource};
+use crate::state::AppState;
+
+/// Сведения об очереди
+#[derive(Serialize)]
+struct QueueInfo {
+    /// Имя очереди
+    name: String,
+
+    /// Откуда взяты настройки
+    source: QueueSource,
+
+    /// Каталог лог файлов
+    root: String,
+
+    /// Кол-во лог файлов
+    files_count: usize,
+}
+
+impl From<NamedQueue> for QueueInfo {
+    fn from(queue: NamedQueue) -> Self {
+        let files_count = queue.with(|q| q.read().map(|q| q.files().len()).unwrap_or_default());
+        Self {
+            name: queue.name,
+            source: queue.source,
+            root: queue.root.to_string_lossy().to_string(),
+            files_count,
+        }
+    }
+}
+
+/// Список очередей
+#[get("")]
+pub async fn list_queues( state: web::Data<AppState> ) -> Result<impl Responder,ApiErr> {
+    #[derive(Serialize)]
+    struct Res {
+        queues: Vec<QueueInfo>
+    }
+
+    Ok(web::Json(Res { queues: state.queues.list().into_iter().map(QueueInfo::from).collect() }))
+}
+
+/// Настройки очереди
+#[get("/{name}")]
+pub async fn get_queue( state: web::Data<AppState>, path: web::Path<String> ) -> Result<impl Responder,ApiErr> {
+    let name = path.into_inner();
+    let queue = state.queues.get(&name).ok_or(ApiErr::QueueNotFound { name })?;
+    Ok(web::Json((*queue.config).clone()))
+}
+
+/// Создание очереди
+///
+/// Тело запроса - настройки очереди, как в секции `queue` файла конфигурации
+#[put("/{name}")]
+pub async fn create_queue( state: web::Data<AppState>, path: web::Path<String>, conf: web::Json<QueueConfig> ) -> Result<impl Responder,ApiErr> {
+    let queue = state.queues.create(&path.into_inner(), conf.into_inner())?;
+    Ok(HttpResponse::Created().json(QueueInfo::from(queue)))
+}
+
+/// Удаление очереди, созданной через api
+///
+/// Лог файлы очереди остаются на диске
+#[delete("/{name}")]
+pub async fn remove_queue( state: web::Data<AppState>, path: web::Path<String> ) -> Result<impl Responder,ApiErr> {
+    #[derive(Serialize)]
+    struct Res {
+        removed: bool
+    }
+
+    state.queues.remove(&path.into_inner())?;
+    Ok(web::Json(Res { removed: true }))
+}
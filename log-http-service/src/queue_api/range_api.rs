@@ -0,0 +1,237 @@
+use std::path::PathBuf;
+
+use actix_web::{web, get, HttpResponse};
+use actix_web::Result;
+use logs::bbuff::absbuff::FileBuff;
+use logs::logfile::LogFile;
+use logs::logqueue::*;
+use serde::Deserialize;
+
+use crate::queues::{NamedQueue, SharedQueue};
+use crate::queue_api::{ApiErr, parse_rec_id};
+use crate::queue_api::follow_api::FollowRecord;
+use crate::queue_api::raw_api::raw_block_bytes;
+
+/// Кол-во записей, читаемых за одну блокировку очереди
+const CHUNK_SIZE: usize = 64;
+
+/// Граница частей multipart, случайная для каждого ответа (RFC 2046) - что бы не совпасть с данными записей
+fn multipart_boundary() -> String {
+    use rand::Rng;
+    let rnd: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+    format!("queue-record-{rnd}")
+}
+
+/// Формат выгрузки
+#[derive(Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all="lowercase")]
+pub enum RangeFormat {
+    /// Последовательность кадров: `log_id: u128 LE`, `block_id: u32 LE`, `size: u64 LE`, байты блока
+    #[default]
+    Raw,
+
+    /// Строка json на запись, данные в base64
+    Ndjson,
+
+    /// `multipart/mixed`, часть на запись
+    Multipart,
+}
+
+/// Параметры выгрузки
+#[derive(Deserialize)]
+pub struct RangeParams {
+    /// Первая запись `{log_id}/{block_id}`
+    pub from: String,
+
+    /// Последняя запись (включительно) `{log_id}/{block_id}`, по умолчанию - до конца очереди
+    pub to: Option<String>,
+
+    /// Максимальное кол-во записей
+    pub limit: Option<u64>,
+
+    /// Формат, по умолчанию `raw`
+    #[serde(default)]
+    pub format: RangeFormat,
+}
+
+/// Курсор выгрузки
+struct RangeCursor {
+    queue: SharedQueue,
+    next: Option<RecID<LogQueueFileNumID>>,
+    to: Option<(u128,u32)>,
+    left: Option<u64>,
+    format: RangeFormat,
+    boundary: String,
+    finished: bool,
+}
+
+impl RangeCursor {
+    /// Чтение очередной порции записей
+    fn read_chunk( &mut self ) -> Result<Option<web::Bytes>,ApiErr> {
+        if self.finished { return Ok(None); }
+
+        let mut out = Vec::<u8>::new();
+        let mut count = 0usize;
+
+        {
+            let queue = self.queue.clone();
+            let q = queue.read()?;
+            while count < CHUNK_SIZE {
+                let rid = match self.next.clone() {
+                    Some(rid) => rid,
+                    None => break
+                };
+
+                let after_to = self.to
+                    .map(|to| (rid.log_file_id.id, rid.block_id.value()) > to)
+                    .unwrap_or(false);
+                if after_to || self.left == Some(0) {
+                    self.next = None;
+                    break;
+                }
+
+                self.write_record(&*q, rid.clone(), &mut out)?;
+                count += 1;
+                self.left = self.left.map(|l| l - 1);
+                self.next = q.next_record(rid)?;
+            }
+        }
+
+        if self.next.is_none() {
+            self.finished = true;
+            if self.format == RangeFormat::Multipart {
+                out.extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+            }
+        }
+
+        if out.is_empty() { Ok(None) } else { Ok(Some(web::Bytes::from(out))) }
+    }
+
+    fn write_record( 
+        &self, 
+        q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>>, 
+        rid: RecID<LogQueueFileNumID>, 
+        out: &mut Vec<u8> 
+    ) -> Result<(),ApiErr> {
+        match self.format {
+            RangeFormat::Raw => {
+                let bytes = raw_block_bytes(q, rid.clone())?;
+                out.extend_from_slice(&rid.log_file_id.id.to_le_bytes());
+                out.extend_from_slice(&rid.block_id.value().to_le_bytes());
+                out.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+                out.extend_from_slice(&bytes);
+            },
+            RangeFormat::Ndjson => {
+                let rec = q.read(rid.clone())?;
+                let line = serde_json::to_string(&FollowRecord::from((rid, rec)))
+                    .map_err(|err| ApiErr::LoqErr(err.to_string()))?;
+                out.extend_from_slice(line.as_bytes());
+                out.push(b'\n');
+            },
+            RangeFormat::Multipart => {
+                let bytes = raw_block_bytes(q, rid.clone())?;
+                out.extend_from_slice(format!(
+                    "--{}\r\nContent-Type: application/octet-stream\r\nContent-ID: {}/{}\r\nContent-Length: {}\r\n\r\n",
+                    self.boundary, rid.log_file_id.id, rid.block_id.value(), bytes.len()
+                ).as_bytes());
+                out.extend_from_slice(&bytes);
+                out.extend_from_slice(b"\r\n");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Выгрузка диапазона записей
+/// 
+/// Записи передаются потоком (chunked transfer), очередь читается порциями по мере отправки,
+/// переход между лог файлами выполняется автоматически
+#[get("/records")]
+pub async fn read_range( queue: NamedQueue, params: web::Query<RangeParams> ) -> Result<HttpResponse,ApiErr> {
+    let from = parse_rec_id("from", &params.from)?;
+    let to = match &params.to {
+        Some(to) => {
+            let to = parse_rec_id("to", to)?;
+            Some((to.log_file_id.id, to.block_id.value()))
+        },
+        None => None
+    };
+
+    // первая запись должна существовать
+    let from = queue.with(|q| {
+        let q = q.read()?;
+        q.info(from.clone()).map_err(|_| ApiErr::RecordNotFound)?;
+        Ok::<_,ApiErr>(resolve_rec_id(&*q, from))
+    })?;
+
+    let mut cursor = RangeCursor { 
+        queue: queue.queue,
+        next: Some(from), 
+        to, 
+        left: params.limit, 
+        format: params.format, 
+        boundary: multipart_boundary(),
+        finished: false 
+    };
+
+    // первая порция читается сразу, что бы ошибка чтения вернулась статусом ответа
+    let first = cursor.read_chunk()?;
+    let boundary = cursor.boundary.clone();
+
+    let tail = futures::stream::unfold(cursor, |mut cursor| async move {
+        match cursor.read_chunk() {
+            Ok(Some(bytes)) => Some((Ok(bytes), cursor)),
+            Ok(None) => None,
+            Err(err) => {
+                cursor.finished = true;
+                Some((Err(actix_web::Error::from(err)), cursor))
+            }
+        }
+    });
+    let body = futures::StreamExt::chain(
+        futures::stream::iter(first.map(Ok::<_,actix_web::Error>)),
+        tail
+    );
+
+    let content_type = match params.format {
+        RangeFormat::Raw => "application/octet-stream".to_string(),
+        RangeFormat::Ndjson => "application/x-ndjson".to_string(),
+        RangeFormat::Multipart => format!("multipart/mixed; boundary={boundary}"),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .streaming(body))
+}
+
+#[test]
+fn test_range_across_files() {
+    use crate::queue_api::test_queue;
+
+    let queue = test_queue("range_across_files", &[2, 3, 1, 2]);
+    let mut cursor = RangeCursor {
+        queue,
+        next: Some(parse_rec_id("from", "1/1").unwrap()),
+        to: None,
+        left: None,
+        format: RangeFormat::Ndjson,
+        boundary: multipart_boundary(),
+        finished: false
+    };
+
+    let mut lines = Vec::new();
+    while let Some(bytes) = cursor.read_chunk().unwrap() {
+        lines.extend(String::from_utf8(bytes.to_vec()).unwrap().lines().map(|l| l.to_string()));
+    }
+
+    // записи 1/1..1/3, затем в каждом следующем лог файле блок 0 с идентификатором и записи
+    assert_eq!(lines.len(), 3 + 2 + 3);
+    assert!(lines.last().unwrap().contains(r#""log_id":"3","block_id":"2""#));
+
+    // граница multipart - своя для каждого ответа
+    assert_ne!(multipart_boundary(), cursor.boundary);
+}
@@ -1,15 +1,19 @@
+use std::path::PathBuf;
+
 use actix_web::{web, get, Error, HttpResponse};
+use logs::bbuff::absbuff::FileBuff;
+use logs::logfile::LogFile;
 use logs::logfile::block::BlockId;
 use logs::logqueue::*;
 use futures::{future::ok, stream::once};
 use crate::queue_api::ApiErr;
-use crate::queue;
+use crate::queues::NamedQueue;
 
 const CACHE_1DAY_TTL: &str = "max-age=86400";
 
 /// Получение тела записи
 #[get("/record/{log:[0-9]+}/{block:[0-9]+}/raw")]
-pub async fn read_block( path: web::Path<(String,u32)> ) -> Result<HttpResponse,ApiErr> {
+pub async fn read_block( queue: NamedQueue, path: web::Path<(String,u32)> ) -> Result<HttpResponse,ApiErr> {
     let (log_id, block_id) = path.into_inner();
     let log_id = u128::from_str_radix(&log_id,10).unwrap();
     
@@ -17,34 +21,34 @@ pub async fn read_block( path: web::Path<(String,u32)> ) -> Result<HttpResponse,
     let block_id = BlockId::new(block_id);
     let rec_id = RecID { log_file_id: log_id, block_id: block_id };
 
-    queue(|q|{
-        let q = q.lock()?;
-
-        let b_info = q.info(rec_id.clone())?;
-        let tot_size = 
-            b_info.data_size.0 as u64 + 
-            b_info.head_size.0 as u64 +
-            b_info.tail_size.0 as u64;
-
-        let mut bytes = Vec::<u8>::with_capacity(tot_size as usize);
-        bytes.resize(tot_size as usize, 0u8);
-
-        let offset = b_info.position;
+    queue.with(|q|{
+        let q = q.read()?;
+        let bytes = web::Bytes::from(raw_block_bytes(&*q, rec_id)?);
+        let body = once(ok::<_,Error>(bytes));
 
-        let reads_size = q.read_raw_bytes(log_id, offset, &mut bytes)?;
-        if reads_size != tot_size as u64 {
-            Err(ApiErr::RawReadBlockDataTruncated { expected_size: tot_size, actual_size: reads_size })
-        }else{
-            let bytes = web::Bytes::from(bytes);
-            let body = once(ok::<_,Error>(bytes));
-
-            Ok(HttpResponse::Ok()
-                .content_type("application/octet-stream")
-                .append_header(("Cache-Control",CACHE_1DAY_TTL))
-                .streaming(body))
-        }
+        Ok(HttpResponse::Ok()
+            .content_type("application/octet-stream")
+            .append_header(("Cache-Control",CACHE_1DAY_TTL))
+            .streaming(body))
     })
 }
 
-
-
+/// Чтение байтов блока записи
+pub fn raw_block_bytes( 
+    q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>>, 
+    rec_id: RecID<LogQueueFileNumID> 
+) -> Result<Vec<u8>,ApiErr> {
+    let b_info = q.info(rec_id.clone())?;
+    let tot_size = 
+        b_info.data_size.0 as u64 + 
+        b_info.head_size.0 as u64 +
+        b_info.tail_size.0 as u64;
+
+    let mut bytes = vec![0u8; tot_size as usize];
+    let reads_size = q.read_raw_bytes(rec_id.log_file_id, b_info.position, &mut bytes)?;
+    if reads_size != tot_size {
+        return Err(ApiErr::RawReadBlockDataTruncated { expected_size: tot_size, actual_size: reads_size });
+    }
+
+    Ok(bytes)
+}
@@ -1,8 +1,9 @@
 use actix_web::{web, Responder};
-use actix_web::post;
+use actix_web::{post, put};
+use logs::logfile::LogErr;
 use logs::logfile::block::{BlockId, Block};
 use logs::logqueue::*;
-use crate::queue;
+use crate::queues::WritableQueue;
 use crate::queue_api::{ID, ApiErr};
 
 struct WriteBlock(Block);
@@ -14,7 +15,7 @@ impl From<WriteBlock> for PreparedRecord {
 }
 
 #[post("/record/{log:[0-9]+}/{block:[0-9]+}/raw")]
-pub async fn write_block( bytes:web::Bytes, path: web::Path<(String,u32)> ) -> Result<impl Responder,ApiErr> {
+pub async fn write_block( queue: WritableQueue, bytes:web::Bytes, path: web::Path<(String,u32)> ) -> Result<impl Responder,ApiErr> {
     let (log_id, block_id) = path.into_inner();
     let log_id = u128::from_str_radix(&log_id,10).unwrap();
     
@@ -22,8 +23,8 @@ pub async fn write_block( bytes:web::Bytes, path: web::Path<(String,u32)> ) -> R
     let block_id = BlockId::new(block_id);
     let _rec_id = RecID { log_file_id: log_id, block_id: block_id };
 
-    queue(|q|{
-        let q = q.lock()?;
+    queue.with(|q|{
+        let q = q.writer()?;
 
         let cur_id = match q.last_record()? {
             Some(v) => Ok(v),
@@ -47,4 +48,41 @@ pub async fn write_block( bytes:web::Bytes, path: web::Path<(String,u32)> ) -> R
 
         Ok(web::Json(rid))
     })
-}
\ No newline at end of file
+}
+
+/// Запись блока в исходном виде с сохранением идентификатора записи (репликация)
+/// 
+/// - `{log}/{block}` - идентификатор записи в очереди источника, должен быть следующим в очереди
+/// - блок 0 следующего лог файла - переключение лог файла
+#[put("/record/{log:[0-9]+}/{block:[0-9]+}/raw")]
+pub async fn write_block_exact( queue: WritableQueue, bytes:web::Bytes, path: web::Path<(String,u32)> ) -> Result<impl Responder,ApiErr> {
+    let (log_id, block_id) = path.into_inner();
+    let log_id = log_id.parse::<u128>()
+        .map_err(|err| ApiErr::InvalidParameter { name: "log".to_string(), error: err.to_string() })?;
+
+    let rec_id = RecID { 
+        log_file_id: LogQueueFileNumID { id: log_id, previous: None }, 
+        block_id: BlockId::new(block_id) 
+    };
+
+    let bytes = bytes.to_vec();
+    let block = Block::from_bytes(&bytes)?;
+
+    queue.with(|q|{
+        let rid = q.write_raw(rec_id, &block)
+            .map_err(|err| match err {
+                LoqErr::RawWriteLogIdNotMatched { expected, actual } => ApiErr::RecIdNotMatch { 
+                    expect_log_id: expected.id().to_string(), actual_log_id: actual.id().to_string(), 
+                    expect_block_id: block_id.to_string(), actual_block_id: block_id.to_string()
+                },
+                LoqErr::LogDataWrite { file:_, error: LogErr::BlockIdNotMatched { expected, actual } } => ApiErr::RecIdNotMatch { 
+                    expect_log_id: log_id.to_string(), actual_log_id: log_id.to_string(), 
+                    expect_block_id: expected.to_string(), actual_block_id: actual.to_string()
+                },
+                err => err.into()
+            })?;
+
+        let rid: ID = rid.into();
+        Ok(web::Json(rid))
+    })
+}
@@ -1,6 +1,9 @@
 use serde::Serialize;
+use logs::logfile::block::BlockId;
 use logs::logqueue::*;
 
+use super::ApiErr;
+
 #[derive(Serialize)]
 pub struct ID {
     pub log_id: String,
@@ -16,3 +19,20 @@ impl From<RecID<LogQueueFileNumID>> for ID {
     }
 }
 
+
+/// Разбор идентификатора записи в формате `{log_id}/{block_id}`
+/// 
+/// Аргументы
+/// - `name` - имя параметра, для сообщения об ошибке
+/// - `value` - значение
+pub fn parse_rec_id( name:&str, value:&str ) -> Result<RecID<LogQueueFileNumID>,ApiErr> {
+    let err = |error:String| ApiErr::InvalidParameter { name: name.to_string(), error };
+    let (log_id, block_id) = value.split_once('/')
+        .ok_or_else(|| err(format!("expect {{log_id}}/{{block_id}}, found {value}")))?;
+    let log_id = log_id.parse::<u128>().map_err(|e| err(e.to_string()))?;
+    let block_id = block_id.parse::<u32>().map_err(|e| err(e.to_string()))?;
+    Ok(RecID { 
+        log_file_id: LogQueueFileNumID { id: log_id, previous: None }, 
+        block_id: BlockId::new(block_id) 
+    })
+}
@@ -0,0 +1,14 @@
+use actix_web::{web, Responder, get};
+use actix_web::Result;
+
+use crate::queue_api::ApiErr;
+use crate::state::AppState;
+
+/// Состояние репликации
+#[get("/replica")]
+pub async fn replica_status( state: web::Data<AppState> ) -> Result<impl Responder,ApiErr> {
+    let replica = state.replica.lock()
+        .map_err(|err| ApiErr::CantLockQueue { error: err.to_string() })?
+        .clone();
+    Ok(web::Json(replica))
+}
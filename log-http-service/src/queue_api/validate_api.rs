@@ -0,0 +1,115 @@
+use std::path::PathBuf;
+
+use actix_web::{web, Responder, get};
+use actix_web::Result;
+use serde::{Deserialize, Serialize};
+
+use logs::logqueue::*;
+
+use crate::config::QueueValidateLevel;
+use crate::queues::NamedQueue;
+use crate::queue_api::ApiErr;
+
+#[derive(Deserialize)]
+pub struct ValidateQuery {
+    /// Уровень проверки, по умолчанию - из настроек очереди
+    level: Option<QueueValidateLevel>,
+}
+
+/// Сведения о лог файле
+#[derive(Serialize)]
+struct FileInfo {
+    log_file: String,
+
+    #[serde(skip_serializing_if="Option::is_none")]
+    log_id: Option<String>,
+
+    #[serde(skip_serializing_if="Option::is_none")]
+    previous: Option<String>,
+
+    #[serde(skip_serializing_if="Option::is_none")]
+    blocks: Option<u32>,
+
+    #[serde(skip_serializing_if="Option::is_none")]
+    bytes: Option<u64>,
+
+    #[serde(skip_serializing_if="Option::is_none")]
+    verified_blocks: Option<u32>,
+}
+
+/// Найденная проблема
+#[derive(Serialize)]
+struct IssueInfo {
+    /// Вид проблемы
+    kind: &'static str,
+
+    /// Проблема не позволяет открыть очередь
+    error: bool,
+
+    /// Описание
+    message: String,
+
+    /// Лог файлы, к которым относится проблема
+    files: Vec<String>,
+}
+
+/// Отчет о проверке очереди
+#[derive(Serialize)]
+struct Res {
+    ok: bool,
+    level: &'static str,
+
+    /// Идентификаторы лог файлов в порядке цепочки
+    chain: Vec<String>,
+    files: Vec<FileInfo>,
+    issues: Vec<IssueInfo>,
+}
+
+impl From<ValidateReport<PathBuf>> for Res {
+    fn from(report: ValidateReport<PathBuf>) -> Self {
+        let path = |f: &PathBuf| f.to_string_lossy().to_string();
+        Self {
+            ok: report.is_ok(),
+            level: report.level.name(),
+            chain: report.chain.iter().map(|id| id.to_string()).collect(),
+            files: report.files.iter().map(|f| FileInfo {
+                log_file: path(&f.file),
+                log_id: f.log_id.map(|id| id.id.to_string()),
+                previous: f.log_id.and_then(|id| id.previous).map(|id| id.to_string()),
+                blocks: f.blocks,
+                bytes: f.bytes,
+                verified_blocks: f.verified_blocks,
+            }).collect(),
+            issues: report.issues.iter().map(|i| IssueInfo {
+                kind: i.kind(),
+                error: i.is_error(),
+                message: i.to_string(),
+                files: i.files().iter().map(path).collect(),
+            }).collect(),
+        }
+    }
+}
+
+/// Проверка лог файлов очереди
+/// 
+/// Доступ писателя удерживается только на время получения списка лог файлов и размера актуального лог файла,
+/// проверка выполняется в отдельном потоке без блокировки - записи, добавленные во время проверки, не проверяются
+#[get("/validate")]
+pub async fn validate_queue( queue: NamedQueue, query: web::Query<ValidateQuery> ) -> Result<impl Responder,ApiErr> {
+    let level = query.level.unwrap_or(queue.config.validate.level);
+    let validator = QueueValidator::new(level.into());
+
+    let files = queue.with(|q| {
+        let q = q.writer()?;
+        let (tail_id, _, tail) = q.tail();
+        let size = tail.bytes_count().and_then(|bytes| Ok(LogSize { bytes, blocks: tail.count()? }))
+            .map_err(|err| ApiErr::LoqErr(format!("{err:?}")))?;
+        Ok::<_,ApiErr>(q.files().into_iter()
+            .map(|(id, file, log)| (file, log, if id == tail_id { Some(size) } else { None }))
+            .collect::<Vec<_>>())
+    })?;
+
+    let report = actix_web::rt::task::spawn_blocking(move || validator.report_sized(&files)).await
+        .map_err(|err| ApiErr::LoqErr(err.to_string()))?;
+    Ok(web::Json(Res::from(report)))
+}
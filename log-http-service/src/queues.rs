@@ -0,0 +1,712 @@
+use std::collections::BTreeMap;
+use std::future::{ready, Ready};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{env, fs, marker::PhantomData, ops::Deref, time::Duration};
+
+use actix_web::dev::{Payload, ServiceRequest};
+use actix_web::http::Uri;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use log::{info, warn};
+use logs::bbuff::absbuff::FileBuff;
+use logs::logfile::LogFile;
+use logs::logqueue::archive::{Archive, ArchivedQueue, LocalDirStore, S3Store};
+use logs::logqueue::find_logs::FsLogFind;
+use logs::logqueue::{
+    LogQueueConf, SharedLogQueue, LogQueueFileNumID, IndexedLogFileOpen, QueueValidator, LoqErr, ConsumerOffsets, FileOffsetsSnapshot, SNAPSHOT_BLOCKS, DedupWindow, RecID,
+    path_template2, restore_dedup_window, Retention, RetentionAction, apply_retention_if, Rotation, rotate_if_due
+};
+use path_template::PathTemplateParser;
+use serde::Serialize;
+
+use crate::config::{AppConfig, QueueConfig, ArchiveStoreConfig};
+use crate::queue_api::ApiErr;
+use crate::state::AppState;
+
+/// Имя очереди, доступной по адресу `/queue/...`
+pub const DEFAULT_QUEUE: &str = "default";
+
+/// Имена, которые совпадают с адресами `/queue/{name}` очереди по умолчанию
+/// и не могут быть именами очередей
+const RESERVED_NAMES: [&str; 16] = [
+    "browse", "consumer", "consumers", "find", "follow", "headers", "insert", "log",
+    "record", "records", "replica", "seek", "subscribe", "tail", "validate", "version",
+];
+
+/// Переменные шаблонов путей
+///
+/// - `${exe.dir}` - каталог программы
+/// - `${work.dir}` - рабочий каталог
+/// - `${queue.name}` - имя очереди
+pub fn template_vars<'a>( tp: PathTemplateParser<'a>, conf: &AppConfig, queue_name: &str ) -> PathTemplateParser<'a> {
+    tp.with_variable("exe.dir", env::current_exe().map(|f| f.parent().unwrap().to_str().unwrap().to_string() ).unwrap() )
+      .with_variable("work.dir", conf.work_dir.to_string())
+      .with_variable("queue.name", queue_name.to_string())
+}
+
+/// Очередь, доступная из всех потоков веб сервера
+///
+/// Чтение выполняется параллельно с записью, см. [SharedLogQueue]
+pub type SharedQueue = SharedLogQueue<'static,LogQueueFileNumID,PathBuf,FileBuff>;
+
+/// Откуда взяты настройки очереди
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all="lowercase")]
+pub enum QueueSource {
+    /// Файл конфигурации `dloghw.json`
+    Config,
+
+    /// Создана через api `/queues`
+    Api,
+}
+
+/// Именованная очередь и ее состояние
+#[derive(Clone)]
+pub struct NamedQueue {
+    /// Имя очереди
+    pub name: String,
+
+    /// Настройки очереди
+    pub config: Arc<QueueConfig>,
+
+    /// Откуда взяты настройки
+    pub source: QueueSource,
+
+    /// Каталог лог файлов
+    pub root: PathBuf,
+
+    /// Очередь
+    pub queue: SharedQueue,
+
+    /// Позиции потребителей очереди
+    pub consumers: Arc<Mutex<ConsumerOffsets<LogQueueFileNumID,FileBuff>>>,
+
+    /// Окно дедупликации по ключу идемпотентности
+    pub dedup: Arc<Mutex<DedupWindow<RecID<LogQueueFileNumID>>>>,
+
+    /// Условия переключения лог файла, проверяются после добавления записи
+    pub rotation: Rotation,
+
+    /// Очередь - реплика источника (`replica.source`), записи добавляются только репликацией
+    pub replica: bool,
+
+    /// Служебный лог файл позиций потребителей
+    consumers_file: PathBuf,
+
+    /// Хранилище архива
+    archive_store: Option<StoreLocation>,
+
+    /// Очередь удалена из реестра, фоновые задачи завершаются
+    removed: Arc<AtomicBool>,
+}
+
+impl NamedQueue {
+    /// Работа с очередью
+    ///
+    /// Аргументы
+    ///
+    /// - `work` - функция получающая ссылку на очередь
+    pub fn with<F,R>( &self, work:F ) -> R
+    where
+        F: FnOnce( SharedQueue ) -> R
+    {
+        work(self.queue.clone())
+    }
+
+    /// Очередь удалена из реестра
+    pub fn is_removed( &self ) -> bool {
+        self.removed.load(Ordering::SeqCst)
+    }
+}
+
+/// Имя очереди запроса, определяется [select_queue]
+#[derive(Clone)]
+struct QueueName( String );
+
+/// Очередь запроса - `/queue/{name}/...` или очередь по умолчанию для `/queue/...`
+impl FromRequest for NamedQueue {
+    type Error = ApiErr;
+    type Future = Ready<Result<Self,ApiErr>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let name = req.extensions().get::<QueueName>()
+            .map(|n| n.0.clone())
+            .unwrap_or_else(|| DEFAULT_QUEUE.to_string());
+
+        ready(
+            req.app_data::<web::Data<AppState>>()
+                .and_then(|state| state.queues.get(&name))
+                .ok_or(ApiErr::QueueNotFound { name })
+        )
+    }
+}
+
+/// Очередь запроса, допускающая запись
+///
+/// Для очереди реплики ([NamedQueue::replica]) возвращает [ApiErr::ReplicaReadOnly]
+pub struct WritableQueue( pub NamedQueue );
+
+impl Deref for WritableQueue {
+    type Target = NamedQueue;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FromRequest for WritableQueue {
+    type Error = ApiErr;
+    type Future = Ready<Result<Self,ApiErr>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        ready(
+            NamedQueue::from_request(req, payload).into_inner().and_then(|queue|
+                match queue.replica {
+                    true => Err(ApiErr::ReplicaReadOnly { name: queue.name }),
+                    false => Ok(WritableQueue(queue))
+                }
+            )
+        )
+    }
+}
+
+/// Выбор очереди по адресу запроса
+///
+/// Адрес `/queue/{name}/...` зарегистрированной очереди заменяется на `/queue/...`,
+/// имя очереди сохраняется в запросе и используется при получении [NamedQueue]
+pub fn select_queue( req: &mut ServiceRequest ) {
+    let registry = match req.app_data::<web::Data<AppState>>() {
+        Some(state) => state.queues.clone(),
+        None => return
+    };
+
+    let (name, rest) = match req.path().strip_prefix("/queue/").and_then(|p| p.split_once('/')) {
+        Some((name, rest)) => (name.to_string(), rest.to_string()),
+        None => return
+    };
+    if registry.get(&name).is_none() {
+        return;
+    }
+
+    let uri = match req.uri().query() {
+        Some(query) => format!("/queue/{rest}?{query}"),
+        None => format!("/queue/{rest}")
+    };
+    if let Ok(uri) = uri.parse::<Uri>() {
+        req.match_info_mut().get_mut().update(&uri);
+        req.head_mut().uri = uri;
+        req.extensions_mut().insert(QueueName(name));
+    }
+}
+
+/// Проверка имени очереди
+///
+/// Имя - латинские буквы, цифры, `-`, `_`, не более 64 символов
+pub fn validate_name( name: &str ) -> Result<(),ApiErr> {
+    let err = |error: &str| ApiErr::InvalidParameter { name: "name".to_string(), error: format!("{error}: {name}") };
+    if name.is_empty() || name.len() > 64 {
+        return Err(err("expect 1..64 chars"));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(err("expect latin letters, digits, '-', '_'"));
+    }
+    if RESERVED_NAMES.contains(&name) {
+        return Err(err("reserved name"));
+    }
+    Ok(())
+}
+
+/// Реестр очередей
+pub struct QueueRegistry {
+    app_conf: Arc<AppConfig>,
+    queues: RwLock<BTreeMap<String,NamedQueue>>,
+
+    /// Создание и удаление очередей выполняется последовательно
+    changes: Mutex<()>,
+}
+
+impl QueueRegistry {
+    pub fn new( app_conf: Arc<AppConfig> ) -> Self {
+        Self { app_conf, queues: RwLock::new(BTreeMap::new()), changes: Mutex::new(()) }
+    }
+
+    /// Очередь по имени
+    pub fn get( &self, name: &str ) -> Option<NamedQueue> {
+        self.queues.read().ok().and_then(|q| q.get(name).cloned())
+    }
+
+    /// Все очереди
+    pub fn list( &self ) -> Vec<NamedQueue> {
+        self.queues.read().map(|q| q.values().cloned().collect()).unwrap_or_default()
+    }
+
+    fn resolve( &self, name: &str, template: &str ) -> Result<PathBuf,String> {
+        let mut tmpl = template_vars(PathTemplateParser::default(), &self.app_conf, name).parse(template)?;
+        Ok(PathBuf::from(tmpl.generate()))
+    }
+
+    /// Расположение хранилища архива
+    fn store_location( &self, name: &str, store: &ArchiveStoreConfig ) -> Result<StoreLocation,String> {
+        Ok(match store {
+            ArchiveStoreConfig::Dir { path } => StoreLocation::Dir(self.resolve(name, path)?),
+            ArchiveStoreConfig::S3 { endpoint, bucket, prefix, .. } => StoreLocation::S3 {
+                endpoint: endpoint.trim_end_matches('/').to_string(),
+                bucket: bucket.clone(),
+                prefix: prefix.replace("${queue.name}", name),
+            },
+        })
+    }
+
+    /// Проверка путей очереди, созданной через api - все пути должны находиться внутри [AppConfig::queues_base_dir]
+    fn check_api_paths( &self, name: &str, conf: &QueueConfig ) -> Result<(),String> {
+        let base = self.resolve(name, &self.app_conf.queues_base_dir)?;
+        let mut paths = vec![
+            ("find.root", conf.find.root.clone()),
+            ("new_file.template", conf.new_file.template.clone()),
+            ("consumers_file", conf.consumers_file.clone()),
+        ];
+        if let Some(dir) = &conf.retention.archive_dir {
+            paths.push(("retention.archive_dir", dir.clone()));
+        }
+        if let Some(archive) = &conf.archive {
+            paths.push(("archive.cache_dir", archive.cache_dir.clone()));
+            if let ArchiveStoreConfig::Dir { path } = &archive.store {
+                paths.push(("archive.store.path", path.clone()));
+            }
+        }
+
+        for (param, template) in paths {
+            let path = self.resolve(name, &template)?;
+            if !is_within(&base, &path) {
+                return Err(format!("{param} {} is outside of {}", path.display(), base.display()));
+            }
+        }
+        Ok(())
+    }
+
+    fn queues_file( &self ) -> Result<PathBuf,String> {
+        self.resolve(DEFAULT_QUEUE, &self.app_conf.queues_file)
+    }
+
+    /// Открытие всех очередей: очередь по умолчанию, очереди из конфигурации
+    /// и очереди, ранее созданные через api
+    pub fn open_all( &self ) -> Result<(),String> {
+        self.open(DEFAULT_QUEUE, self.app_conf.queue.clone(), QueueSource::Config)?;
+
+        for (name, conf) in self.app_conf.queues.iter() {
+            validate_name(name).map_err(|err| format!("{err:?}"))?;
+            self.open(name, conf.clone(), QueueSource::Config)?;
+        }
+
+        let file = self.queues_file()?;
+        if file.is_file() {
+            let text = fs::read_to_string(&file).map_err(|err| format!("can't read {}: {err}", file.display()))?;
+            let created: BTreeMap<String,QueueConfig> = serde_json::from_str(&text)
+                .map_err(|err| format!("can't parse {}: {err}", file.display()))?;
+            for (name, conf) in created {
+                if self.get(&name).is_some() {
+                    warn!("queue {name} from {} already declared in config, skipped", file.display());
+                    continue;
+                }
+                self.open(&name, conf, QueueSource::Api)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Создание очереди, настройки сохраняются в [AppConfig::queues_file]
+    pub fn create( &self, name: &str, conf: QueueConfig ) -> Result<NamedQueue,ApiErr> {
+        validate_name(name)?;
+        let _changes = self.changes.lock().map_err(|err| ApiErr::CantLockQueue { error: err.to_string() })?;
+        if self.get(name).is_some() {
+            return Err(ApiErr::QueueExists { name: name.to_string() });
+        }
+        self.check_api_paths(name, &conf.clone().named(name))
+            .map_err(|error| ApiErr::InvalidParameter { name: "conf".to_string(), error })?;
+
+        let queue = self.open(name, conf, QueueSource::Api)
+            .map_err(|error| ApiErr::QueueOpen { name: name.to_string(), error })?;
+
+        if let Err(error) = self.save() {
+            self.unregister(name);
+            return Err(ApiErr::QueueOpen { name: name.to_string(), error });
+        }
+        Ok(queue)
+    }
+
+    /// Удаление очереди, созданной через api
+    ///
+    /// Лог файлы очереди остаются на диске
+    pub fn remove( &self, name: &str ) -> Result<NamedQueue,ApiErr> {
+        let _changes = self.changes.lock().map_err(|err| ApiErr::CantLockQueue { error: err.to_string() })?;
+        let queue = self.get(name).ok_or_else(|| ApiErr::QueueNotFound { name: name.to_string() })?;
+        if queue.source != QueueSource::Api {
+            return Err(ApiErr::InvalidParameter { name: "name".to_string(), error: format!("queue {name} declared in config") });
+        }
+
+        self.unregister(name);
+        self.save().map_err(|error| ApiErr::QueueOpen { name: name.to_string(), error })?;
+        info!("queue {name} removed");
+        Ok(queue)
+    }
+
+    fn unregister( &self, name: &str ) {
+        if let Some(queue) = self.queues.write().ok().and_then(|mut q| q.remove(name)) {
+            queue.removed.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Сохранение настроек очередей, созданных через api
+    fn save( &self ) -> Result<(),String> {
+        let created: BTreeMap<String,QueueConfig> = self.list().into_iter()
+            .filter(|q| q.source == QueueSource::Api)
+            .map(|q| (q.name.clone(), (*q.config).clone()))
+            .collect();
+
+        let file = self.queues_file()?;
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let text = serde_json::to_string_pretty(&created).map_err(|err| err.to_string())?;
+        let tmp = file.with_extension("tmp");
+        fs::write(&tmp, text).map_err(|err| err.to_string())?;
+        fs::rename(&tmp, &file).map_err(|err| err.to_string())
+    }
+
+    /// Открытие очереди и запуск ее фоновых задач
+    fn open( &self, name: &str, config: QueueConfig, source: QueueSource ) -> Result<NamedQueue,String> {
+        let conf = config.clone().named(name);
+        let err = |error: String| format!("queue {name}: {error}");
+
+        if source == QueueSource::Api {
+            self.check_api_paths(name, &conf).map_err(err)?;
+        }
+
+        let root = self.resolve(name, &conf.find.root).map_err(err)?;
+        let consumers_file = self.resolve(name, &conf.consumers_file).map_err(err)?;
+        let archive_store = match &conf.archive {
+            Some(archive_conf) => Some(self.store_location(name, &archive_conf.store).map_err(err)?),
+            None => None
+        };
+        for other in self.list() {
+            if roots_overlap(&other.root, &root) {
+                return Err(err(format!("log files root {} overlaps root {} of queue {}", root.display(), other.root.display(), other.name)));
+            }
+            if other.consumers_file == consumers_file {
+                return Err(err(format!("consumers file {} used by queue {}", consumers_file.display(), other.name)));
+            }
+            // имена объектов архива - идентификаторы лог файлов, у разных очередей они совпадают
+            if let (Some(store), Some(other_store)) = (&archive_store, &other.archive_store) {
+                if store.overlaps(other_store) {
+                    return Err(err(format!("archive store {store} overlaps archive store {other_store} of queue {}", other.name)));
+                }
+            }
+        }
+
+        // archive ..........
+        let archive = match &conf.archive {
+            Some(archive_conf) => {
+                let archive = match &archive_conf.store {
+                    ArchiveStoreConfig::Dir { path } =>
+                        Archive::new(LocalDirStore::new( self.resolve(name, path).map_err(err)? )),
+                    ArchiveStoreConfig::S3 { endpoint, bucket, region, access_key, secret_key, prefix } => {
+                        let store = S3Store {
+                            endpoint: endpoint.clone(), bucket: bucket.clone(), region: region.clone(),
+                            access_key: access_key.clone(), secret_key: secret_key.clone(), prefix: prefix.replace("${queue.name}", name)
+                        };
+                        store.authority()
+                            .map_err(|_| err(format!("archive endpoint {endpoint} not supported, only http:// endpoints are supported")))?;
+                        Archive::new(store)
+                    },
+                };
+                info!("queue {name} archive {}", archive_conf.store_name());
+                Some((Arc::new(archive), self.resolve(name, &archive_conf.cache_dir).map_err(err)?))
+            },
+            None => None
+        };
+
+        // queue ..........
+        let fs_log_find = FsLogFind::new(root.to_str().unwrap_or_default(), &conf.find.wildcard, conf.find.recursive)
+            .map_err(|e| err(format!("{e:?}")))?;
+
+        let log_queue_conf: LogQueueConf<LogQueueFileNumID, PathBuf, FileBuff, _, _, _, _> = {
+            let app_conf = self.app_conf.clone();
+            let queue_name = name.to_string();
+            LogQueueConf {
+                find_files: fs_log_find,
+                open_log_file: IndexedLogFileOpen {
+                    keys: conf.index_keys.clone(),
+                    offset_index: conf.offset_index,
+                },
+                validate: match (&archive, conf.validate.checksums) {
+                    (Some((archive, _)), true) => QueueValidator::new(conf.validate.level.into()).with_checksums(archive.clone()),
+                    _ => QueueValidator::new(conf.validate.level.into())
+                },
+                new_file: path_template2( &conf.new_file.template, move |tp| template_vars(tp, &app_conf, &queue_name))
+                    .map_err(|e| err(format!("{e:?}")))?,
+                _p: PhantomData,
+            }
+        };
+
+        let queue = log_queue_conf.open().map_err(|e| match e {
+            LoqErr::ValidationFailed { report } => {
+                for issue in &report.issues {
+                    warn!("queue {name} {}: {issue}", issue.kind());
+                }
+                err(format!("validation failed, {} issues, see logs-tools repair", report.issues.len()))
+            },
+            e => err(format!("{e:?}"))
+        })?;
+
+        let queue = match &archive {
+            Some((archive, cache_dir)) => SharedLogQueue::new(ArchivedQueue::new(
+                queue,
+                archive.clone(),
+                log_queue_conf.open_log_file.clone(),
+                cache_dir.clone()
+            )),
+            None => SharedLogQueue::new(queue)
+        };
+
+        // consumers ..........
+        if let Some(dir) = consumers_file.parent() {
+            fs::create_dir_all(dir).map_err(|e| err(e.to_string()))?;
+        }
+        let consumers = ConsumerOffsets::open(
+            LogFile::new(FileBuff::open_read_write(&consumers_file).map_err(|e| err(format!("{e:?}")))?)
+                .map_err(|e| err(format!("{e:?}")))?
+        ).map_err(|e| err(format!("{e:?}")))?
+        .with_snapshot(FileOffsetsSnapshot { file: consumers_file.clone() }, SNAPSHOT_BLOCKS);
+
+        // idempotency ..........
+        let dedup = restore_dedup_window(&*queue.read().map_err(|e| err(format!("{e:?}")))?, conf.idempotency_window)
+            .map_err(|e| err(format!("{e:?}")))?;
+
+        // rotation ..........
+        // реплика повторяет переключения источника
+        let replica = self.app_conf.replica.source.is_some() && name == DEFAULT_QUEUE;
+        let rotation = match replica {
+            true => Rotation::default(),
+            false => Rotation {
+                max_bytes: conf.rotation.max_bytes,
+                max_blocks: conf.rotation.max_blocks,
+                interval: conf.rotation.interval_sec.map(Duration::from_secs),
+            }
+        };
+
+        let named = NamedQueue {
+            name: name.to_string(),
+            config: Arc::new(config),
+            source,
+            root,
+            queue,
+            consumers: Arc::new(Mutex::new(consumers)),
+            consumers_file,
+            archive_store,
+            dedup: Arc::new(Mutex::new(dedup)),
+            rotation,
+            replica,
+            removed: Arc::new(AtomicBool::new(false)),
+        };
+        info!("queue {name} openned");
+
+        self.spawn_tasks(&named, &conf, archive.map(|(archive, _)| archive))?;
+
+        self.queues.write()
+            .map_err(|e| err(e.to_string()))?
+            .insert(name.to_string(), named.clone());
+        Ok(named)
+    }
+
+    /// Фоновые задачи очереди: выгрузка в архив, переключение и ограничения хранения лог файлов,
+    /// задачи завершаются после удаления очереди из реестра
+    fn spawn_tasks( &self, named: &NamedQueue, conf: &QueueConfig, archive: Option<Arc<Archive>> ) -> Result<(),String> {
+        let name = named.name.clone();
+
+        // archive upload ..........
+        if let (Some(archive), Some(archive_conf)) = (archive.clone(), conf.archive.as_ref()) {
+            let interval = Duration::from_millis(archive_conf.interval_ms);
+            let named = named.clone();
+            actix_web::rt::spawn(async move {
+                while !named.is_removed() {
+                    // лог файлы кроме актуального не изменяются, выгрузка выполняется без блокировки очереди
+                    let files = named.with(|q| q.read().map(|q| Archive::sealed_files(&*q)).unwrap_or_default());
+                    let archive = archive.clone();
+                    match actix_web::rt::task::spawn_blocking(move || archive.upload_missing(&files)).await {
+                        Ok(Ok(uploaded)) => for meta in uploaded { info!("queue {} log file {} archived, sha256={}", named.name, meta.file, meta.sha256) },
+                        Ok(Err(err)) => warn!("queue {} archive error {err:?}", named.name),
+                        Err(err) => warn!("queue {} archive error {err}", named.name)
+                    }
+                    actix_web::rt::time::sleep(interval).await;
+                }
+            });
+        }
+
+        // rotation ..........
+        if !named.rotation.is_empty() {
+            info!("queue {name} rotation {:?}", named.rotation);
+
+            let check = Duration::from_millis(conf.rotation.check_ms);
+            let named = named.clone();
+            actix_web::rt::spawn(async move {
+                loop {
+                    actix_web::rt::time::sleep(check).await;
+                    if named.is_removed() {
+                        break;
+                    }
+                    let res = named.with(|q| {
+                        let mut q = q.exclusive().map_err(|err| format!("{err:?}"))?;
+                        rotate_if_due(&mut *q, &named.rotation, chrono::Utc::now().fixed_offset())
+                            .map_err(|err| format!("{err:?}"))
+                    });
+                    match res {
+                        Ok(Some((file, _))) => info!("queue {} log file switched to {}", named.name, file.display()),
+                        Ok(None) => {},
+                        Err(err) => warn!("queue {} rotation error {err}", named.name)
+                    }
+                }
+            });
+        }
+
+        // retention ..........
+        let retention = Retention {
+            max_bytes: conf.retention.max_bytes,
+            max_age: conf.retention.max_age_sec.map(Duration::from_secs),
+            max_files: conf.retention.max_files,
+        };
+        if !retention.is_empty() {
+            let action = match &conf.retention.archive_dir {
+                Some(dir) => RetentionAction::Archive(self.resolve(&name, dir)?),
+                None => RetentionAction::Delete
+            };
+            info!("queue {name} retention {retention:?}, expired files: {action:?}");
+
+            let interval = Duration::from_millis(conf.retention.interval_ms);
+            let named = named.clone();
+            actix_web::rt::spawn(async move {
+                while !named.is_removed() {
+                    // выгрузка в архив и удаление файлов - блокирующие операции, выполняются вне потоков веб сервера
+                    let (task_named, archive, retention, action) = (named.clone(), archive.clone(), retention.clone(), action.clone());
+                    let res = actix_web::rt::task::spawn_blocking(move || task_named.with(|q| {
+                        // лог файл удаляется с диска только после выгрузки в архив,
+                        // выгрузка выполняется без блокировки очереди, под монопольным доступом только проверка
+                        // по уже полученным сведениям - не выгруженные лог файлы остаются до следующей проверки
+                        if let Some(archive) = &archive {
+                            let files = Archive::sealed_files(&*q.read().map_err(|err| format!("{err:?}"))?);
+                            archive.upload_missing(&files).map_err(|err| format!("{err:?}"))?;
+                        }
+                        let mut q = q.exclusive().map_err(|err| format!("{err:?}"))?;
+                        apply_retention_if(&mut *q, &retention, &action, chrono::Utc::now().fixed_offset(), |log_id| {
+                            archive.as_ref().map(|archive| archive.is_uploaded(log_id.id)).unwrap_or(true)
+                        }).map_err(|err| format!("{err:?}"))
+                    })).await;
+                    match res {
+                        Ok(Ok(removed)) => for file in removed { info!("queue {} log file {} removed from queue", named.name, file.display()) },
+                        Ok(Err(err)) => warn!("queue {} retention error {err}", named.name),
+                        Err(err) => warn!("queue {} retention error {err}", named.name)
+                    }
+                    actix_web::rt::time::sleep(interval).await;
+                }
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Путь без `.` и `..`, без обращения к файловой системе
+fn normalize( path: &Path ) -> PathBuf {
+    let mut res = PathBuf::new();
+    for comp in path.components() {
+        match comp {
+            Component::CurDir => {},
+            Component::ParentDir => { res.pop(); },
+            comp => res.push(comp),
+        }
+    }
+    res
+}
+
+/// Каталоги лог файлов совпадают или один вложен в другой
+fn roots_overlap( a: &Path, b: &Path ) -> bool {
+    let (a, b) = (normalize(a), normalize(b));
+    a.starts_with(&b) || b.starts_with(&a)
+}
+
+/// Путь `path` совпадает с каталогом `base` или находится внутри него
+fn is_within( base: &Path, path: &Path ) -> bool {
+    normalize(path).starts_with(normalize(base))
+}
+
+/// Расположение хранилища архива
+#[derive(Debug, Clone, PartialEq)]
+enum StoreLocation {
+    /// Каталог
+    Dir(PathBuf),
+
+    /// Корзина S3 и префикс имен объектов
+    S3 { endpoint: String, bucket: String, prefix: String },
+}
+
+impl StoreLocation {
+    /// Хранилища пересекаются - имена объектов одного могут совпасть с именами объектов другого
+    fn overlaps( &self, other: &StoreLocation ) -> bool {
+        match (self, other) {
+            (StoreLocation::Dir(a), StoreLocation::Dir(b)) => roots_overlap(a, b),
+            (StoreLocation::S3 { endpoint: e1, bucket: b1, prefix: p1 }, StoreLocation::S3 { endpoint: e2, bucket: b2, prefix: p2 }) =>
+                e1 == e2 && b1 == b2 && (p1.starts_with(p2.as_str()) || p2.starts_with(p1.as_str())),
+            _ => false
+        }
+    }
+}
+
+impl std::fmt::Display for StoreLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreLocation::Dir(dir) => write!(f, "{}", dir.display()),
+            StoreLocation::S3 { endpoint, bucket, prefix } => write!(f, "{endpoint}/{bucket}/{prefix}"),
+        }
+    }
+}
+
+#[test]
+fn test_roots_overlap() {
+    assert!(roots_overlap(Path::new("/data/queue"), Path::new("/data/queue")));
+    assert!(roots_overlap(Path::new("/data/queue"), Path::new("/data/queue/orders")));
+    assert!(roots_overlap(Path::new("/data/queue/orders"), Path::new("/data/queue")));
+    assert!(roots_overlap(Path::new("/data/queue"), Path::new("/data/other/../queue/./orders")));
+    assert!(!roots_overlap(Path::new("/data/queue"), Path::new("/data/queue2")));
+    assert!(!roots_overlap(Path::new("/data/queue"), Path::new("/data/orders")));
+
+    assert!(is_within(Path::new("./app_data"), Path::new("app_data/orders/1.binlog")));
+    assert!(!is_within(Path::new("./app_data"), Path::new("app_data/../etc")));
+    assert!(!is_within(Path::new("/data"), Path::new("/etc/passwd")));
+}
+
+#[test]
+fn test_store_overlaps() {
+    let s3 = |bucket: &str, prefix: &str| StoreLocation::S3 {
+        endpoint: "http://127.0.0.1:9000".to_string(), bucket: bucket.to_string(), prefix: prefix.to_string()
+    };
+    assert!(s3("logs", "").overlaps(&s3("logs", "orders/")));
+    assert!(s3("logs", "orders/").overlaps(&s3("logs", "orders/")));
+    assert!(!s3("logs", "orders/").overlaps(&s3("logs", "events/")));
+    assert!(!s3("logs", "").overlaps(&s3("other", "")));
+
+    let dir = |d: &str| StoreLocation::Dir(PathBuf::from(d));
+    assert!(dir("/archive").overlaps(&dir("/archive/orders")));
+    assert!(!dir("/archive/events").overlaps(&dir("/archive/orders")));
+    assert!(!dir("/archive").overlaps(&s3("logs", "")));
+}
+
+#[test]
+fn test_validate_name() {
+    assert!(validate_name("orders").is_ok());
+    assert!(validate_name("orders-2023_07").is_ok());
+    assert!(validate_name("").is_err());
+    assert!(validate_name("a/b").is_err());
+    assert!(validate_name("очередь").is_err());
+    assert!(validate_name(&"a".repeat(65)).is_err());
+    assert!(validate_name("log").is_err());
+    assert!(validate_name("records").is_err());
+}
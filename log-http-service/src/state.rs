@@ -1,6 +1,35 @@
 use std::{path::PathBuf, sync::{Mutex, Arc}};
 
+use log_http_client::ReplicaStatus;
+use serde::Serialize;
+
+use crate::queues::QueueRegistry;
+
 #[derive(Clone)]
 pub struct AppState {
     pub static_files: Arc<Mutex<Option<PathBuf>>>,
-}
\ No newline at end of file
+
+    /// Очереди
+    pub queues: Arc<QueueRegistry>,
+
+    /// Состояние репликации очереди по умолчанию
+    pub replica: Arc<Mutex<ReplicaState>>,
+
+    /// Максимальный размер тела запроса на добавление записи
+    pub max_body_size: usize,
+}
+
+/// Состояние репликации
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ReplicaState {
+    /// Сервер-источник, `None` - репликация выключена
+    pub source: Option<String>,
+
+    /// Результат последнего успешного шага репликации
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub status: Option<ReplicaStatus>,
+
+    /// Ошибка последнего шага репликации
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub error: Option<String>,
+}
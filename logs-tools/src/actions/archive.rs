@@ -0,0 +1,489 @@
+//! Экспорт и импорт записей лог файла / очереди в архивные форматы
+//!
+//! Форматы
+//! - tar, zip - файл на блок `blocks/{номер:08}.bin` и `manifest.json` с опциями блоков
+//! - ndjson - строка json на блок, данные в base64
+//!
+//! Опции блоков ([BlockOptions]) и тип данных ([DataId]) сохраняются как есть, в том числе имя файла и время,
+//! указанные при добавлении записи.
+//! Блок 0 с идентификатором лог файла очереди не выгружается
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Local;
+use logs::bbuff::absbuff::FileBuff;
+use logs::logfile::LogFile;
+use logs::logfile::block::*;
+use logs::logqueue::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::err::LogToolErr;
+use crate::actions::queue::{Queue, QueueLocation};
+
+/// Имя манифеста в tar/zip
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Версия формата манифеста
+const MANIFEST_VERSION: u32 = 1;
+
+/// Формат архива
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Tar,
+    Zip,
+    Ndjson,
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tar" => Ok(Self::Tar),
+            "zip" => Ok(Self::Zip),
+            "ndjson" | "jsonl" => Ok(Self::Ndjson),
+            _ => Err(format!("unknown archive format {s}, expect tar, zip or ndjson"))
+        }
+    }
+}
+
+impl ArchiveFormat {
+    /// Формат указанный явно, либо по расширению файла; `-` (stdin/stdout) - ndjson
+    pub fn resolve( format: Option<ArchiveFormat>, archive: &str ) -> Result<Self, LogToolErr> {
+        if let Some(format) = format {
+            return Ok(format);
+        }
+        if archive == "-" {
+            return Ok(Self::Ndjson);
+        }
+        Path::new(archive).extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| ext.to_lowercase().parse().ok())
+            .ok_or_else(|| LogToolErr::InvalidArg(format!("can't detect archive format of {archive}, use -archive tar|zip|ndjson")))
+    }
+}
+
+/// Запись архива
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveRecord {
+    /// Откуда запись: `{log_id}/{block_id}` для очереди, `{block_id}` для лог файла
+    pub source: String,
+
+    /// Опции блока
+    pub options: BTreeMap<String,String>,
+
+    /// Тип данных блока, см. [DataId]
+    pub data_type: u32,
+
+    /// Данные блока
+    pub data: Vec<u8>,
+}
+
+impl ArchiveRecord {
+    fn block_options( &self ) -> Result<BlockOptions, LogToolErr> {
+        let mut options = BlockOptions::default();
+        for (k, v) in &self.options {
+            options.set(k.as_str(), v.as_str())?;
+        }
+        Ok(options)
+    }
+}
+
+/// Тип данных записей, выгруженных без типа
+fn user_data_type() -> u32 {
+    DataId::user_data().value()
+}
+
+/// Запись манифеста tar/zip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManifestRecord {
+    file: String,
+    source: String,
+    options: BTreeMap<String,String>,
+    #[serde(default = "user_data_type")]
+    data_type: u32,
+    data_size: u64,
+    sha256: String,
+}
+
+/// Манифест tar/zip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    created: String,
+    records: Vec<ManifestRecord>,
+}
+
+/// Строка ndjson
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NdjsonRecord {
+    source: String,
+    options: BTreeMap<String,String>,
+    #[serde(default = "user_data_type")]
+    data_type: u32,
+
+    /// Данные в base64
+    data: String,
+}
+
+fn json_err( err: serde_json::Error ) -> LogToolErr {
+    LogToolErr::Json(err.to_string())
+}
+
+fn zip_err( err: zip::result::ZipError ) -> LogToolErr {
+    LogToolErr::InvalidArg(format!("zip: {err}"))
+}
+
+/// Запись архива, манифест пишется в [ArchiveWriter::finish]
+enum ArchiveWriter {
+    Tar { builder: tar::Builder<Box<dyn Write>>, manifest: Vec<ManifestRecord> },
+    Zip { writer: zip::ZipWriter<File>, manifest: Vec<ManifestRecord> },
+    Ndjson { out: Box<dyn Write> },
+}
+
+impl ArchiveWriter {
+    fn create( archive: &str, format: ArchiveFormat ) -> Result<Self, LogToolErr> {
+        let out: Box<dyn Write> = if archive == "-" {
+            if format == ArchiveFormat::Zip {
+                return Err(LogToolErr::InvalidArg("zip can't be written to stdout".to_string()));
+            }
+            Box::new(std::io::stdout().lock())
+        } else if format == ArchiveFormat::Zip {
+            return Ok(Self::Zip { writer: zip::ZipWriter::new(File::create(archive)?), manifest: vec![] });
+        } else {
+            Box::new(std::io::BufWriter::new(File::create(archive)?))
+        };
+
+        Ok(match format {
+            ArchiveFormat::Tar => Self::Tar { builder: tar::Builder::new(out), manifest: vec![] },
+            _ => Self::Ndjson { out },
+        })
+    }
+
+    fn manifest_record( index: usize, record: &ArchiveRecord ) -> ManifestRecord {
+        ManifestRecord {
+            file: format!("blocks/{index:08}.bin"),
+            source: record.source.clone(),
+            options: record.options.clone(),
+            data_type: record.data_type,
+            data_size: record.data.len() as u64,
+            sha256: hex::encode(Sha256::digest(&record.data)),
+        }
+    }
+
+    fn tar_append( builder: &mut tar::Builder<Box<dyn Write>>, name: &str, data: &[u8] ) -> Result<(), LogToolErr> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(Local::now().timestamp().max(0) as u64);
+        header.set_cksum();
+        builder.append_data(&mut header, name, data)?;
+        Ok(())
+    }
+
+    fn write( &mut self, record: &ArchiveRecord ) -> Result<(), LogToolErr> {
+        match self {
+            Self::Tar { builder, manifest } => {
+                let item = Self::manifest_record(manifest.len(), record);
+                Self::tar_append(builder, &item.file, &record.data)?;
+                manifest.push(item);
+            }
+            Self::Zip { writer, manifest } => {
+                let item = Self::manifest_record(manifest.len(), record);
+                let opts = zip::write::FileOptions::default()
+                    .compression_method(zip::CompressionMethod::Deflated);
+                writer.start_file(item.file.clone(), opts).map_err(zip_err)?;
+                writer.write_all(&record.data)?;
+                manifest.push(item);
+            }
+            Self::Ndjson { out } => {
+                let line = serde_json::to_string(&NdjsonRecord {
+                    source: record.source.clone(),
+                    options: record.options.clone(),
+                    data_type: record.data_type,
+                    data: BASE64.encode(&record.data),
+                }).map_err(json_err)?;
+                writeln!(out, "{line}")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn manifest_json( records: Vec<ManifestRecord> ) -> Result<Vec<u8>, LogToolErr> {
+        let manifest = Manifest {
+            version: MANIFEST_VERSION,
+            created: Local::now().to_rfc3339(),
+            records,
+        };
+        serde_json::to_vec_pretty(&manifest).map_err(json_err)
+    }
+
+    fn finish( self ) -> Result<(), LogToolErr> {
+        match self {
+            Self::Tar { mut builder, manifest } => {
+                let json = Self::manifest_json(manifest)?;
+                Self::tar_append(&mut builder, MANIFEST_FILE, &json)?;
+                builder.into_inner()?.flush()?;
+            }
+            Self::Zip { mut writer, manifest } => {
+                let json = Self::manifest_json(manifest)?;
+                writer.start_file(MANIFEST_FILE, zip::write::FileOptions::default()).map_err(zip_err)?;
+                writer.write_all(&json)?;
+                writer.finish().map_err(zip_err)?;
+            }
+            Self::Ndjson { mut out } => out.flush()?,
+        }
+        Ok(())
+    }
+}
+
+/// Чтение записей архива
+///
+/// Для tar/zip записи читаются в порядке манифеста, контрольная сумма данных проверяется.
+/// Манифест tar записан в конце архива: сначала читается манифест, затем блоки по одному,
+/// поэтому tar читается только из файла
+fn read_archive<F>( archive: &str, format: ArchiveFormat, mut consumer: F ) -> Result<usize, LogToolErr>
+where
+    F: FnMut(ArchiveRecord) -> Result<(), LogToolErr>
+{
+    let input = || -> Result<Box<dyn Read>, LogToolErr> {
+        Ok(if archive == "-" { Box::new(std::io::stdin().lock()) } else { Box::new(File::open(archive)?) })
+    };
+
+    let mut count = 0;
+    match format {
+        ArchiveFormat::Ndjson => {
+            for (line_no, line) in BufReader::new(input()?).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() { continue; }
+                let rec: NdjsonRecord = serde_json::from_str(&line)
+                    .map_err(|err| LogToolErr::Json(format!("line {}: {err}", line_no + 1)))?;
+                let data = BASE64.decode(rec.data.as_bytes())
+                    .map_err(|err| LogToolErr::InvalidArg(format!("line {}: data: {err}", line_no + 1)))?;
+                consumer(ArchiveRecord { source: rec.source, options: rec.options, data_type: rec.data_type, data })?;
+                count += 1;
+            }
+        }
+        ArchiveFormat::Tar => {
+            if archive == "-" {
+                return Err(LogToolErr::InvalidArg("tar can't be read from stdin, use ndjson".to_string()));
+            }
+
+            let mut manifest = None;
+            let mut tar = tar::Archive::new(File::open(archive)?);
+            for entry in tar.entries_with_seek()? {
+                let mut entry = entry?;
+                if entry.path()?.as_ref() == Path::new(MANIFEST_FILE) {
+                    let mut data = Vec::new();
+                    entry.read_to_end(&mut data)?;
+                    manifest = Some(data);
+                }
+            }
+            let manifest = manifest
+                .ok_or_else(|| LogToolErr::InvalidArg(format!("{MANIFEST_FILE} not found in {archive}")))?;
+
+            // блоки записаны в порядке манифеста
+            let mut tar = tar::Archive::new(File::open(archive)?);
+            let mut entries = tar.entries_with_seek()?;
+            count = read_manifest(&manifest, |name| {
+                for entry in entries.by_ref() {
+                    let mut entry = entry?;
+                    if entry.path()?.as_ref() == Path::new(name) {
+                        let mut data = Vec::new();
+                        entry.read_to_end(&mut data)?;
+                        return Ok(data);
+                    }
+                }
+                Err(LogToolErr::InvalidArg(format!("{name} not found")))
+            }, &mut consumer)?;
+        }
+        ArchiveFormat::Zip => {
+            if archive == "-" {
+                return Err(LogToolErr::InvalidArg("zip can't be read from stdin".to_string()));
+            }
+            let mut zip = zip::ZipArchive::new(File::open(archive)?).map_err(zip_err)?;
+            let mut read_file = |name: &str| -> Result<Vec<u8>, LogToolErr> {
+                let mut file = zip.by_name(name).map_err(zip_err)?;
+                let mut data = Vec::new();
+                file.read_to_end(&mut data)?;
+                Ok(data)
+            };
+            let manifest = read_file(MANIFEST_FILE)?;
+            count = read_manifest(&manifest, read_file, &mut consumer)?;
+        }
+    }
+    Ok(count)
+}
+
+fn read_manifest<R, F>( manifest: &[u8], mut read_file: R, consumer: &mut F ) -> Result<usize, LogToolErr>
+where
+    R: FnMut(&str) -> Result<Vec<u8>, LogToolErr>,
+    F: FnMut(ArchiveRecord) -> Result<(), LogToolErr>
+{
+    let manifest: Manifest = serde_json::from_slice(manifest).map_err(json_err)?;
+    if manifest.version != MANIFEST_VERSION {
+        return Err(LogToolErr::InvalidArg(format!("unsupported manifest version {}", manifest.version)));
+    }
+
+    let count = manifest.records.len();
+    for item in manifest.records {
+        let data = read_file(&item.file)?;
+        let sha256 = hex::encode(Sha256::digest(&data));
+        if sha256 != item.sha256 {
+            return Err(LogToolErr::InvalidArg(format!("{} checksum mismatch, expect {} found {sha256}", item.file, item.sha256)));
+        }
+        consumer(ArchiveRecord { source: item.source, options: item.options, data_type: item.data_type, data })?;
+    }
+    Ok(count)
+}
+
+fn block_record( source: String, block: Block ) -> ArchiveRecord {
+    ArchiveRecord {
+        source,
+        options: block.head.block_options.into_iter().map(|(k,v)| (k.to_string(), v.to_string())).collect(),
+        data_type: block.head.data_type_id.value(),
+        data: *block.data,
+    }
+}
+
+/// Экспорт блоков лог файла
+///
+/// Блок 0 с идентификатором лог файла очереди ([LogQueueFileNumID]) пропускается
+pub fn export_log_file<P: AsRef<Path>>( log_file: P, archive: &str, format: ArchiveFormat ) -> Result<(), LogToolErr> {
+    let log = LogFile::new(FileBuff::open_read_only(log_file)?)?;
+    let mut writer = ArchiveWriter::create(archive, format)?;
+    let mut count = 0;
+    for b in 0..log.count()? {
+        let block = log.read_block(BlockId::new(b))?;
+        if b == 0 {
+            if let Ok(log_id) = LogQueueFileNumID::block_read(&block) {
+                eprintln!("block 0 is queue log id {log_id}, skipped");
+                continue;
+            }
+        }
+        writer.write(&block_record(b.to_string(), block))?;
+        count += 1;
+    }
+    writer.finish()?;
+    eprintln!("exported {count} records");
+    Ok(())
+}
+
+/// Экспорт записей очереди, блоки с идентификаторами лог файлов (блок 0) пропускаются
+pub fn export_queue( location: &QueueLocation, archive: &str, format: ArchiveFormat ) -> Result<(), LogToolErr> {
+    let queue = match location.open(false)? {
+        Some(queue) => queue,
+        None => return Err(LogToolErr::InvalidArg(format!("no log files in {}", location.root))),
+    };
+
+    let mut writer = ArchiveWriter::create(archive, format)?;
+    let mut count = 0;
+    for (log_id, _, log) in queue.files() {
+        for b in 1..log.count()? {
+            let block = log.read_block(BlockId::new(b))?;
+            writer.write(&block_record(format!("{}/{b}", log_id.id), block))?;
+            count += 1;
+        }
+    }
+    writer.finish()?;
+    eprintln!("exported {count} records");
+    Ok(())
+}
+
+/// Импорт записей в лог файл, файл создается если отсутствует
+pub fn import_log_file<P: AsRef<Path>>( archive: &str, format: ArchiveFormat, log_file: P ) -> Result<(), LogToolErr> {
+    let mut log = LogFile::new(FileBuff::open_read_write(log_file)?)?;
+    let count = read_archive(archive, format, |rec| {
+        log.write_data(DataId::new(rec.data_type), &rec.block_options()?, &rec.data)?;
+        Ok(())
+    })?;
+    println!("imported {count} records");
+    Ok(())
+}
+
+/// Импорт записей в очередь, тип данных записей сохраняется
+///
+/// Перед каждой записью лог файл переключается, если выполнено одно из условий `rotation`
+pub fn import_queue( archive: &str, format: ArchiveFormat, location: &QueueLocation, rotation: &Rotation ) -> Result<(), LogToolErr> {
+    let mut queue = location.open(true)?.unwrap();
+    let count = read_archive(archive, format, |rec| {
+        rotate_if_due(&mut queue, rotation, Local::now().fixed_offset())?;
+        let options = rec.block_options()?;
+        let record = PreparedRecord { data: rec.data, options };
+        let q: &Queue = &queue;
+        write_data(q, DataId::new(rec.data_type), &record)?;
+        Ok(())
+    })?;
+    println!("imported {count} records");
+    Ok(())
+}
+
+#[test]
+fn test_archive_round_trip() {
+    use std::path::PathBuf;
+
+    let dir = PathBuf::from("target/test/archive");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // лог файл очереди: блок 0 - идентификатор лог файла, затем записи
+    let src = dir.join("src.binlog");
+    {
+        let mut log = LogFile::new(FileBuff::open_read_write(&src).unwrap()).unwrap();
+        LogQueueFileNumID { id: 1, previous: None }.write(&src, &mut log).unwrap();
+        for i in 0..3 {
+            let mut opts = BlockOptions::default();
+            opts.set("file.name", format!("data-{i}.txt").as_str()).unwrap();
+            opts.set("time", "2023-07-21T12:00:00+00:00").unwrap();
+            log.write_block(&opts, format!("payload {i}\n\x00\x01").as_bytes()).unwrap();
+        }
+        log.write_data(DataId::new(7), &BlockOptions::default(), b"typed").unwrap();
+    }
+
+    for format in [ArchiveFormat::Tar, ArchiveFormat::Zip, ArchiveFormat::Ndjson] {
+        let archive = dir.join(format!("export.{format:?}")).to_string_lossy().to_string();
+        export_log_file(&src, &archive, format).unwrap();
+
+        let dst = dir.join(format!("dst-{format:?}.binlog"));
+        import_log_file(&archive, format, &dst).unwrap();
+
+        let a = LogFile::new(FileBuff::open_read_only(&src).unwrap()).unwrap();
+        let b = LogFile::new(FileBuff::open_read_only(&dst).unwrap()).unwrap();
+        assert_eq!(a.count().unwrap(), b.count().unwrap() + 1);
+        for i in 0..b.count().unwrap() {
+            let ba = a.read_block(BlockId::new(i + 1)).unwrap();
+            let bb = b.read_block(BlockId::new(i)).unwrap();
+            assert_eq!(ba.data, bb.data);
+            assert_eq!(ba.head.data_type_id, bb.head.data_type_id);
+            assert_eq!(
+                std::collections::HashMap::<String,String>::from(ba.head.block_options),
+                std::collections::HashMap::<String,String>::from(bb.head.block_options)
+            );
+        }
+    }
+
+    // импорт в очередь сохраняет тип данных
+    let location = QueueLocation {
+        root: dir.join("queue").to_string_lossy().to_string(),
+        wildcard: "*.binlog".to_string(),
+        new_file: None,
+    };
+    let archive = dir.join("export.Ndjson").to_string_lossy().to_string();
+    import_queue(&archive, ArchiveFormat::Ndjson, &location, &Rotation::default()).unwrap();
+    let queue = location.open(false).unwrap().unwrap();
+    let (_, _, log) = queue.tail();
+    assert_eq!(log.count().unwrap(), 5);
+    assert_eq!(log.read_block(BlockId::new(4)).unwrap().head.data_type_id, DataId::new(7));
+    assert_eq!(log.read_block(BlockId::new(1)).unwrap().head.data_type_id, DataId::user_data());
+
+    assert_eq!(ArchiveFormat::resolve(None, "a/b.ZIP").unwrap(), ArchiveFormat::Zip);
+    assert_eq!(ArchiveFormat::resolve(None, "-").unwrap(), ArchiveFormat::Ndjson);
+    assert!(ArchiveFormat::resolve(None, "a.bin").is_err());
+}
@@ -0,0 +1,435 @@
+//! Интерактивный просмотр лог файла в терминале
+//!
+//! Работает только через чтение лог файла ([LogFile], [LogPointer]), сервис не нужен -
+//! можно смотреть скопированные файлы.
+//!
+//! Клавиши
+//! - `↑` `↓` / `k` `j` - предыдущий / следующий блок, `PgUp` `PgDn` - на страницу, `Home` `End` - первый / последний
+//! - `g` - переход к блоку по идентификатору
+//! - `1`..`9` - переход по обратной ссылке текущего блока
+//! - `/` - поиск по опциям (выражение как у `view ... where`), `n` `N` - следующий / предыдущий найденный
+//! - `x` - данные блока: hex / текст
+//! - `q` `Esc` - выход
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use logs::bbuff::absbuff::FileBuff;
+use logs::logfile::*;
+use logs::logfile::block::*;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::err::LogToolErr;
+
+/// Кол-во байтов в строке hex
+const HEX_LINE: usize = 16;
+
+/// Сколько данных блока читать для просмотра
+const PAYLOAD_LIMIT: usize = 64 * 1024;
+
+/// Отображение данных блока
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PayloadMode {
+    Hex,
+    Text,
+}
+
+/// Ввод в строке статуса
+#[derive(Debug, Clone, PartialEq)]
+enum Prompt {
+    /// Идентификатор блока
+    Jump(String),
+
+    /// Выражение поиска
+    Search(String),
+}
+
+/// Первые [PAYLOAD_LIMIT] байтов данных блока и полный размер, либо ошибка чтения
+type PayloadData = Result<(Vec<u8>, usize), String>;
+
+/// Состояние просмотра, без привязки к терминалу
+pub struct BrowseModel {
+    ptr: LogPointer<FileBuff>,
+
+    /// Кол-во блоков
+    count: u32,
+
+    /// Отображение данных
+    pub payload: PayloadMode,
+
+    /// Последний поиск
+    search: Option<BlockFilter>,
+
+    /// Сообщение в строке статуса
+    pub status: String,
+
+    prompt: Option<Prompt>,
+
+    /// Данные выбранного блока: идентификатор блока, первые [PAYLOAD_LIMIT] байтов, полный размер
+    payload_cache: RefCell<Option<(u32, PayloadData)>>,
+}
+
+impl BrowseModel {
+    /// Открытие лог файла, указатель на последний блок
+    pub fn open<P: AsRef<Path>>( log_file: P ) -> Result<Self, LogToolErr> {
+        let log = Arc::new(RwLock::new(LogFile::new(FileBuff::open_read_only(log_file)?)?));
+        let ptr = match log.pointer_to_end() {
+            Ok(ptr) => ptr,
+            Err(LogErr::LogIsEmpty) => return Err(LogToolErr::InvalidArg("log file is empty".to_string())),
+            Err(err) => return Err(err.into()),
+        };
+        let count = ptr.current_head().head.block_id.value() + 1;
+        Ok(Self {
+            ptr,
+            count,
+            payload: PayloadMode::Hex,
+            search: None,
+            status: format!("{count} blocks, press ? for keys"),
+            prompt: None,
+            payload_cache: RefCell::new(None),
+        })
+    }
+
+    /// Идентификатор текущего блока
+    pub fn current_id( &self ) -> u32 {
+        self.ptr.current_head().head.block_id.value()
+    }
+
+    /// Перемещение на `n` блоков вперед (`n > 0`) или назад, до границы лог файла
+    pub fn step( &mut self, n: i64 ) {
+        for _ in 0..n.unsigned_abs() {
+            let moved = if n > 0 { self.ptr.next() } else { self.ptr.previous() };
+            match moved {
+                Ok(ptr) => self.ptr = ptr,
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Переход к блоку
+    pub fn jump( &mut self, block_id: u32 ) {
+        if block_id >= self.count {
+            self.status = format!("block {block_id} not exists, last is {}", self.count - 1);
+            return;
+        }
+        match self.ptr.jump(BlockId::new(block_id)) {
+            Ok(ptr) => { self.ptr = ptr; self.status = format!("block {block_id}"); }
+            Err(err) => self.status = format!("jump to {block_id}: {err:?}"),
+        }
+    }
+
+    /// Переход по обратной ссылке текущего блока, `index` с 0
+    pub fn follow_back_ref( &mut self, index: usize ) {
+        match self.back_refs().get(index) {
+            Some((id, _)) => self.jump(*id),
+            None => self.status = format!("no back ref {}", index + 1),
+        }
+    }
+
+    /// Обратные ссылки текущего блока: идентификатор, смещение
+    pub fn back_refs( &self ) -> Vec<(u32, u64)> {
+        self.ptr.current_head().head.back_refs.refs.iter()
+            .map(|(id, off)| (id.value(), off.value()))
+            .collect()
+    }
+
+    /// Установка выражения поиска и поиск вперед
+    pub fn search_text( &mut self, text: &str ) {
+        match text.parse::<BlockFilter>() {
+            Ok(filter) => {
+                self.search = Some(filter);
+                self.search_next(true);
+            }
+            Err(err) => self.status = err.to_string(),
+        }
+    }
+
+    /// Следующий (`forward`) или предыдущий блок, подходящий под последний поиск
+    pub fn search_next( &mut self, forward: bool ) {
+        let filter = match &self.search {
+            Some(filter) => filter,
+            None => { self.status = "no search, press /".to_string(); return; }
+        };
+
+        let mut ptr = self.ptr.clone();
+        loop {
+            ptr = match if forward { ptr.next() } else { ptr.previous() } {
+                Ok(ptr) => ptr,
+                Err(_) => { self.status = "not found".to_string(); return; }
+            };
+            let h = ptr.current_head();
+            if filter.matches(&h.head.block_options, h.data_size.value() as u64) {
+                self.status = format!("found block {}", h.head.block_id.value());
+                self.ptr = ptr;
+                return;
+            }
+        }
+    }
+
+    /// Заголовки блоков вокруг текущего, не больше `height`; результат и позиция текущего
+    pub fn rows( &self, height: usize ) -> (Vec<BlockHeadRead>, usize) {
+        let mut before = Vec::new();
+        let mut ptr = self.ptr.clone();
+        while before.len() < height / 2 {
+            match ptr.previous() {
+                Ok(prev) => { before.push(prev.current_head().clone()); ptr = prev; }
+                Err(_) => break,
+            }
+        }
+        before.reverse();
+
+        let current = before.len();
+        let mut rows = before;
+        rows.push(self.ptr.current_head().clone());
+
+        let mut ptr = self.ptr.clone();
+        while rows.len() < height {
+            match ptr.next() {
+                Ok(next) => { rows.push(next.current_head().clone()); ptr = next; }
+                Err(_) => break,
+            }
+        }
+        (rows, current)
+    }
+
+    /// Данные текущего блока для просмотра
+    ///
+    /// Из лог файла читаются только первые [PAYLOAD_LIMIT] байтов один раз при выборе блока,
+    /// перерисовка использует кеш
+    pub fn payload_lines( &self ) -> Vec<String> {
+        let block_id = self.current_id();
+        let mut cache = self.payload_cache.borrow_mut();
+        if !matches!(&*cache, Some((id, _)) if *id == block_id) {
+            let size = self.ptr.current_head().data_size.value() as usize;
+            let data = self.ptr.current_data_prefix(PAYLOAD_LIMIT)
+                .map(|data| (data, size))
+                .map_err(|err| format!("can't read data {err:?}"));
+            *cache = Some((block_id, data));
+        }
+
+        let (shown, size) = match &*cache {
+            Some((_, Ok((shown, size)))) => (shown, *size),
+            Some((_, Err(err))) => return vec![err.clone()],
+            None => return vec![],
+        };
+        let mut lines = match self.payload {
+            PayloadMode::Hex => hex_lines(shown),
+            PayloadMode::Text => String::from_utf8_lossy(shown).lines().map(|l| l.to_string()).collect(),
+        };
+        if size > shown.len() {
+            lines.push(format!("... {} more bytes", size - shown.len()));
+        }
+        lines
+    }
+}
+
+/// Строки hex: смещение, байты, ASCII
+fn hex_lines( data: &[u8] ) -> Vec<String> {
+    data.chunks(HEX_LINE).enumerate().map(|(i, chunk)| {
+        let hex = chunk.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>().join(" ");
+        let ascii: String = chunk.iter().map(|b| if (0x20..0x7f).contains(b) { *b as char } else { '.' }).collect();
+        format!("{:08x}  {hex:<width$}  {ascii}", i * HEX_LINE, width = HEX_LINE * 3 - 1)
+    }).collect()
+}
+
+fn options_text( options: &BlockOptions ) -> String {
+    options.clone().into_iter().map(|(k,v)| format!("{}={}", k.value(), v.value())).collect::<Vec<_>>().join(" ")
+}
+
+const KEYS_HELP: &str = "↑↓ move  PgUp/PgDn page  Home/End  g jump  1-9 back ref  / search  n/N next/prev  x hex/text  q quit";
+
+fn draw( frame: &mut Frame, model: &BrowseModel ) {
+    let [main, status] = Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+    let [list_area, detail_area] = Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(main);
+    let [head_area, payload_area] = Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(detail_area);
+
+    // список блоков, обратные ссылки текущего блока отмечены ◀
+    let back_ids: Vec<u32> = model.back_refs().iter().map(|(id, _)| *id).collect();
+    let (rows, current) = model.rows(list_area.height.saturating_sub(2) as usize);
+    let items: Vec<ListItem> = rows.iter().enumerate().map(|(i, h)| {
+        let id = h.head.block_id.value();
+        let mark = if i == current {
+            ">".to_string()
+        } else {
+            back_ids.iter().position(|b| *b == id).map(|n| format!("◀{}", n + 1)).unwrap_or_default()
+        };
+        let line = format!("{mark:<3}{id:>8} {:>10} {:>8} {}", h.position.value(), h.data_size.value(), options_text(&h.head.block_options));
+        let style = if i == current {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else if !back_ids.contains(&id) {
+            Style::default()
+        } else {
+            Style::default().fg(Color::Yellow)
+        };
+        ListItem::new(line).style(style)
+    }).collect();
+    let title = format!(" blocks {}/{} ", model.current_id(), model.count - 1);
+    frame.render_widget(List::new(items).block(Block::default().borders(Borders::ALL).title(title)), list_area);
+
+    // заголовок текущего блока и обратные ссылки
+    let h = model.ptr.current_head();
+    let mut lines = vec![
+        Line::from(format!("block {} offset {}", h.head.block_id.value(), h.position.value())),
+        Line::from(format!("head {} data {} tail {}", h.head_size.value(), h.data_size.value(), h.tail_size.value())),
+        Line::from(""),
+    ];
+    for (k, v) in h.head.block_options.clone() {
+        lines.push(Line::from(vec![Span::styled(format!("{} ", k.value()), Style::default().fg(Color::Cyan)), Span::raw(v.value().to_string())]));
+    }
+    lines.push(Line::from(""));
+    for (n, (id, off)) in model.back_refs().iter().enumerate() {
+        let distance = model.current_id().saturating_sub(*id);
+        lines.push(Line::from(Span::styled(
+            format!("{} ◀ block {id} @ {off}  -{distance} {}", n + 1, "─".repeat((distance as f64).log2() as usize + 1)),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" head ")), head_area);
+
+    let mode = match model.payload { PayloadMode::Hex => "hex", PayloadMode::Text => "text" };
+    let payload: Vec<Line> = model.payload_lines().into_iter()
+        .take(payload_area.height as usize)
+        .map(Line::from)
+        .collect();
+    frame.render_widget(
+        Paragraph::new(payload).block(Block::default().borders(Borders::ALL).title(format!(" data ({mode}) "))),
+        payload_area
+    );
+
+    let status_line = match &model.prompt {
+        Some(Prompt::Jump(text)) => format!("block id: {text}"),
+        Some(Prompt::Search(text)) => format!("search: {text}"),
+        None => model.status.clone(),
+    };
+    frame.render_widget(Paragraph::new(status_line), status);
+}
+
+/// Обработка клавиши, результат - продолжать работу
+fn on_key( model: &mut BrowseModel, code: KeyCode, page: i64 ) -> bool {
+    if let Some(prompt) = model.prompt.as_mut() {
+        let text = match prompt { Prompt::Jump(t) | Prompt::Search(t) => t };
+        match code {
+            KeyCode::Esc => model.prompt = None,
+            KeyCode::Backspace => { text.pop(); },
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Enter => {
+                match model.prompt.take() {
+                    Some(Prompt::Jump(text)) => match text.trim().parse::<u32>() {
+                        Ok(id) => model.jump(id),
+                        Err(err) => model.status = format!("block id {text}: {err}"),
+                    },
+                    Some(Prompt::Search(text)) => model.search_text(&text),
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+        return true;
+    }
+
+    match code {
+        KeyCode::Char('q') | KeyCode::Esc => return false,
+        KeyCode::Down | KeyCode::Char('j') => model.step(1),
+        KeyCode::Up | KeyCode::Char('k') => model.step(-1),
+        KeyCode::PageDown => model.step(page),
+        KeyCode::PageUp => model.step(-page),
+        KeyCode::Home => model.jump(0),
+        KeyCode::End => model.jump(model.count - 1),
+        KeyCode::Char('g') => model.prompt = Some(Prompt::Jump(String::new())),
+        KeyCode::Char('/') => model.prompt = Some(Prompt::Search(String::new())),
+        KeyCode::Char('n') => model.search_next(true),
+        KeyCode::Char('N') => model.search_next(false),
+        KeyCode::Char('x') => {
+            model.payload = if model.payload == PayloadMode::Hex { PayloadMode::Text } else { PayloadMode::Hex };
+        }
+        KeyCode::Char('?') => model.status = KEYS_HELP.to_string(),
+        KeyCode::Char(c @ '1'..='9') => model.follow_back_ref(c as usize - '1' as usize),
+        _ => {}
+    }
+    true
+}
+
+fn run( terminal: &mut DefaultTerminal, model: &mut BrowseModel ) -> Result<(), LogToolErr> {
+    loop {
+        terminal.draw(|frame| draw(frame, model))?;
+        let page = terminal.size()?.height.saturating_sub(3).max(1) as i64;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !on_key(model, key.code, page) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Интерактивный просмотр лог файла
+pub fn browse_log_file<P: AsRef<Path>>( log_file: P ) -> Result<(), LogToolErr> {
+    let mut model = BrowseModel::open(log_file)?;
+    let mut terminal = ratatui::init();
+    let res = run(&mut terminal, &mut model);
+    ratatui::restore();
+    res
+}
+
+#[test]
+fn test_browse_model() {
+    use std::path::PathBuf;
+
+    let dir = PathBuf::from("target/test/browse");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let file = dir.join("log.binlog");
+    {
+        let mut log = LogFile::new(FileBuff::open_read_write(&file).unwrap()).unwrap();
+        for i in 0..40 {
+            let mut opts = BlockOptions::default();
+            opts.set("n", format!("{i}").as_str()).unwrap();
+            if i % 10 == 5 { opts.set("mark", "yes").unwrap(); }
+            log.write_block(&opts, format!("payload {i}").as_bytes()).unwrap();
+        }
+    }
+
+    let mut model = BrowseModel::open(&file).unwrap();
+    assert_eq!(model.current_id(), 39);
+    model.step(5);
+    assert_eq!(model.current_id(), 39);
+    model.step(-4);
+    assert_eq!(model.current_id(), 35);
+
+    model.jump(3);
+    assert_eq!(model.current_id(), 3);
+    model.jump(100);
+    assert_eq!(model.current_id(), 3);
+
+    model.search_text("mark=yes");
+    assert_eq!(model.current_id(), 5);
+    model.search_next(true);
+    assert_eq!(model.current_id(), 15);
+    model.search_next(false);
+    assert_eq!(model.current_id(), 5);
+    model.search_next(false);
+    assert_eq!((model.current_id(), model.status.as_str()), (5, "not found"));
+
+    model.jump(32);
+    let refs = model.back_refs();
+    assert!(!refs.is_empty());
+    model.follow_back_ref(0);
+    assert_eq!(model.current_id(), refs[0].0);
+
+    let (rows, current) = model.rows(10);
+    assert_eq!(rows[current].head.block_id.value(), model.current_id());
+    assert!(rows.len() <= 10);
+
+    model.jump(7);
+    assert_eq!(model.payload_lines()[0], format!("{:08x}  {:<47}  payload 7", 0, "70 61 79 6c 6f 61 64 20 37"));
+    model.payload = PayloadMode::Text;
+    assert_eq!(model.payload_lines(), vec!["payload 7".to_string()]);
+    model.jump(8);
+    assert_eq!(model.payload_lines(), vec!["payload 8".to_string()]);
+}
@@ -0,0 +1,295 @@
+//! Слежение за новыми блоками лог файла / очереди (аналог `tail -f`)
+//!
+//! Лог файл периодически проверяется: если размер изменился, то выводятся новые блоки.
+//! Для очереди дополнительно отслеживается появление новых лог файлов (переключение),
+//! новые лог файлы читаются по порядку цепочки.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use logs::bbuff::absbuff::FileBuff;
+use logs::logfile::*;
+use logs::logqueue::*;
+
+use crate::err::LogToolErr;
+use crate::actions::queue::{Queue, QueueLocation};
+use crate::actions::viewheaders::{block_view, BlockView, ViewOptions};
+
+/// Кол-во подряд неудачных попыток чтения, после которого в stderr выводится предупреждение
+///
+/// Чтение может не удаться, если запись блока еще не завершена - это не ошибка,
+/// попытка повторяется с увеличивающейся задержкой (но не больше [MAX_RETRY_DELAY])
+const WARN_READ_FAILS: u32 = 3;
+
+/// Наибольшая задержка повторного чтения после неудачи
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Параметры слежения
+#[derive(Debug, Clone)]
+pub struct FollowOptions {
+    /// Формат вывода, фильтр
+    pub view: ViewOptions,
+
+    /// Сколько последних блоков вывести перед слежением
+    pub last: usize,
+
+    /// Интервал проверки
+    pub interval: Duration,
+}
+
+impl Default for FollowOptions {
+    fn default() -> Self {
+        Self { view: ViewOptions::default(), last: 10, interval: Duration::from_secs(1) }
+    }
+}
+
+/// Блоки лог файла начиная с `from`, в порядке записи
+///
+/// Результат - блоки подходящие под фильтр (не больше `limit` последних) и кол-во блоков в лог файле
+fn blocks_since( file: &Path, from: u32, limit: Option<usize>, opts: &ViewOptions ) -> Result<(Vec<BlockView>, u32), LogToolErr> {
+    let log = Arc::new(RwLock::new(LogFile::new(FileBuff::open_read_only(file)?)?));
+    let mut ptr = match log.pointer_to_end() {
+        Ok(ptr) => ptr,
+        Err(LogErr::LogIsEmpty) => return Ok((vec![], 0)),
+        Err(err) => return Err(err.into()),
+    };
+
+    let count = ptr.current_head().head.block_id.value() + 1;
+    let mut views = Vec::new();
+    while ptr.current_head().head.block_id.value() >= from {
+        if limit.map(|limit| views.len() >= limit).unwrap_or(false) {
+            break;
+        }
+        if opts.matches(ptr.current_head()) {
+            views.push(block_view(&ptr, opts));
+        }
+        match ptr.previous() {
+            Ok(prev) => ptr = prev,
+            Err(_) => break,
+        }
+    }
+    views.reverse();
+    Ok((views, count))
+}
+
+fn print_views( views: &[BlockView], opts: &ViewOptions ) -> Result<(), LogToolErr> {
+    let mut out = std::io::stdout().lock();
+    for view in views {
+        writeln!(out, "{}", view.format(opts)?)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn print_header( opts: &ViewOptions ) -> Result<(), LogToolErr> {
+    if let Some(header) = BlockView::header(opts) {
+        let mut out = std::io::stdout().lock();
+        writeln!(out, "{header}")?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// Позиция чтения лог файла
+struct FileTail {
+    file: PathBuf,
+
+    /// Следующий блок
+    next: u32,
+
+    /// Размер файла при последнем чтении
+    size: u64,
+
+    /// Интервал проверки, от него считается задержка повтора
+    interval: Duration,
+
+    /// Неудачные попытки чтения подряд
+    fails: u32,
+
+    /// Время следующей попытки чтения после неудачи
+    retry_at: Option<Instant>,
+}
+
+impl FileTail {
+    fn new( file: PathBuf, next: u32, size: u64, interval: Duration ) -> Self {
+        Self { file, next, size, interval, fails: 0, retry_at: None }
+    }
+
+    /// Вывод новых блоков, если размер файла изменился
+    ///
+    /// Если новые блоки не читаются (запись блока не завершена), то чтение повторяется
+    /// при следующих проверках с задержкой `interval * 2^fails`
+    fn poll( &mut self, opts: &ViewOptions ) -> Result<(), LogToolErr> {
+        if self.retry_at.map(|at| Instant::now() < at).unwrap_or(false) {
+            return Ok(());
+        }
+
+        let size = std::fs::metadata(&self.file)?.len();
+        if size == self.size {
+            return Ok(());
+        }
+        if size < self.size {
+            eprintln!("{} truncated, read from start", self.file.display());
+            self.next = 0;
+        }
+
+        match blocks_since(&self.file, self.next, None, opts) {
+            Ok((views, count)) => {
+                print_views(&views, opts)?;
+                self.next = count;
+                self.size = size;
+                self.fails = 0;
+                self.retry_at = None;
+                Ok(())
+            }
+            Err(err) => {
+                self.fails += 1;
+                let delay = self.interval.saturating_mul(1 << self.fails.min(16)).min(MAX_RETRY_DELAY);
+                if self.fails == WARN_READ_FAILS {
+                    eprintln!("{} can't read new blocks, retrying: {err:?}", self.file.display());
+                }
+                self.retry_at = Some(Instant::now() + delay);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Слежение за лог файлом
+pub fn follow_log_file<P: AsRef<Path>>( log_file: P, opts: &FollowOptions ) -> Result<(), LogToolErr> {
+    let file = log_file.as_ref().to_path_buf();
+    let size = std::fs::metadata(&file)?.len();
+    let (views, next) = blocks_since(&file, 0, Some(opts.last), &opts.view)?;
+
+    print_header(&opts.view)?;
+    print_views(&views, &opts.view)?;
+
+    let mut tail = FileTail::new(file, next, size, opts.interval);
+    loop {
+        std::thread::sleep(opts.interval);
+        tail.poll(&opts.view)?;
+    }
+}
+
+/// Лог файлы очереди в порядке цепочки: идентификатор, файл
+fn queue_chain( location: &QueueLocation ) -> Result<Vec<(u128, PathBuf)>, LogToolErr> {
+    let queue = match location.open(false)? {
+        Some(queue) => queue,
+        None => return Ok(vec![]),
+    };
+    let q: &Queue = &queue;
+    Ok(q.files().into_iter().map(|(log_id, file, _)| (log_id.id, file)).collect())
+}
+
+/// Слежение за очередью
+///
+/// Перед слежением выводятся последние блоки актуального лог файла.
+/// При переключении очереди на новый лог файл в stderr выводится `switched to {log_id} {file}`,
+/// блоки с идентификаторами лог файлов (блок 0) не выводятся
+pub fn follow_queue( location: &QueueLocation, opts: &FollowOptions ) -> Result<(), LogToolErr> {
+    let find = location.find()?;
+    let mut known = FindFiles::<PathBuf,LogQueueFileNumID>::find_files(&find)?;
+    known.sort();
+
+    let chain = queue_chain(location)?;
+    let (mut log_id, file) = match chain.last() {
+        Some(last) => last.clone(),
+        None => return Err(LogToolErr::InvalidArg(format!("no log files in {}", location.root))),
+    };
+
+    let size = std::fs::metadata(&file)?.len();
+    let (views, next) = blocks_since(&file, 1, Some(opts.last), &opts.view)?;
+    print_header(&opts.view)?;
+    print_views(&views, &opts.view)?;
+
+    let mut tail = FileTail::new(file, next, size, opts.interval);
+    loop {
+        std::thread::sleep(opts.interval);
+        tail.poll(&opts.view)?;
+
+        let mut files = FindFiles::<PathBuf,LogQueueFileNumID>::find_files(&find)?;
+        files.sort();
+        if files == known {
+            continue;
+        }
+        known = files;
+
+        // дочитываем текущий лог файл до переключения, не дожидаясь задержки повтора
+        tail.retry_at = None;
+        tail.poll(&opts.view)?;
+        if tail.fails > 0 {
+            eprintln!("{} not read to the end before switch", tail.file.display());
+        }
+        let current = log_id;
+        for (next_id, next_file) in queue_chain(location)?.into_iter().filter(|(id, _)| *id > current) {
+            eprintln!("switched to {next_id} {}", next_file.display());
+            log_id = next_id;
+            tail = FileTail::new(next_file, 1, 0, opts.interval);
+            tail.poll(&opts.view)?;
+        }
+    }
+}
+
+#[test]
+fn test_blocks_since() {
+    use logs::logfile::block::BlockOptions;
+
+    let dir = PathBuf::from("target/test/follow");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let file = dir.join("log.binlog");
+    let mut log = LogFile::new(FileBuff::open_read_write(&file).unwrap()).unwrap();
+    for i in 0..5 {
+        let mut opts = BlockOptions::default();
+        opts.set("kind", if i % 2 == 0 { "even" } else { "odd" }).unwrap();
+        log.write_block(&opts, format!("rec {i}").as_bytes()).unwrap();
+    }
+
+    let opts = ViewOptions { text: true, ..ViewOptions::default() };
+    let (views, count) = blocks_since(&file, 0, Some(2), &opts).unwrap();
+    assert_eq!(count, 5);
+    assert_eq!(views.iter().map(|v| v.format(&opts).unwrap().contains("rec 3")).collect::<Vec<_>>(), vec![true, false]);
+
+    let (views, _) = blocks_since(&file, 3, None, &opts).unwrap();
+    assert_eq!(views.len(), 2);
+
+    let opts = ViewOptions { filter: Some("kind=even".parse().unwrap()), ..opts };
+    let (views, _) = blocks_since(&file, 0, None, &opts).unwrap();
+    assert_eq!(views.len(), 3);
+}
+
+#[test]
+fn test_poll_incomplete_block() {
+    use logs::logfile::block::BlockOptions;
+
+    let dir = PathBuf::from("target/test/follow_incomplete");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let full = dir.join("full.binlog");
+    let mut log = LogFile::new(FileBuff::open_read_write(&full).unwrap()).unwrap();
+    let mut sizes = Vec::new();
+    for i in 0..4 {
+        log.write_block(&BlockOptions::default(), format!("rec {i}").as_bytes()).unwrap();
+        sizes.push(std::fs::metadata(&full).unwrap().len());
+    }
+    let data = std::fs::read(&full).unwrap();
+
+    // последний блок записан наполовину
+    let file = dir.join("log.binlog");
+    std::fs::write(&file, &data[..(sizes[2] as usize + sizes[3] as usize) / 2]).unwrap();
+
+    let opts = ViewOptions::default();
+    let mut tail = FileTail::new(file.clone(), 0, 0, Duration::ZERO);
+    for _ in 0..WARN_READ_FAILS + 2 {
+        tail.poll(&opts).unwrap();
+    }
+    assert_eq!((tail.next, tail.fails), (0, WARN_READ_FAILS + 2));
+
+    std::fs::write(&file, &data).unwrap();
+    tail.poll(&opts).unwrap();
+    assert_eq!((tail.next, tail.fails), (4, 0));
+}
@@ -3,4 +3,9 @@ pub use append::*;
 
 pub mod extract;
 pub mod viewheaders;
-pub mod tag;
\ No newline at end of file
+pub mod tag;
+pub mod repair;
+pub mod queue;
+pub mod archive;
+pub mod follow;
+pub mod browse;
@@ -0,0 +1,190 @@
+//! Операции с очередью лог файлов
+//!
+//! Очередь задается корнем и шаблоном имени лог файлов, поиск выполняется рекурсивно
+
+use std::io::Write;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use chrono::Local;
+use logs::bbuff::absbuff::FileBuff;
+use logs::logfile::LogFile;
+use logs::logfile::block::*;
+use logs::logqueue::*;
+use logs::logqueue::find_logs::FsLogFind;
+
+use crate::err::LogToolErr;
+use crate::actions::tag::TagAction;
+use crate::actions::{ApplyContext, EntryData};
+
+/// Шаблон имени нового лог файла по умолчанию
+pub const DEFAULT_NEW_FILE: &str = "${root}/${time:local:yyyy-mm-ddThh-mi-ss}-${rnd:5}.binlog";
+
+pub(crate) type Queue = dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>>;
+
+/// Запись очереди: идентификатор лог файла, идентификатор блока
+pub type RecPos = (u128,u32);
+
+/// Расположение очереди
+#[derive(Debug, Clone)]
+pub struct QueueLocation {
+    /// Корневой каталог лог файлов
+    pub root: String,
+
+    /// Шаблон имени лог файла
+    pub wildcard: String,
+
+    /// Шаблон имени нового лог файла, по умолчанию [DEFAULT_NEW_FILE]
+    pub new_file: Option<String>,
+}
+
+impl QueueLocation {
+    pub(crate) fn find( &self ) -> Result<FsLogFind, LogToolErr> {
+        FsLogFind::new(&self.root, &self.wildcard, true).map_err(LogToolErr::InvalidArg)
+    }
+
+    /// Открытие очереди
+    ///
+    /// Если `create` не указан и лог файлов нет, то очередь не создается - результат `None`
+    pub(crate) fn open( &self, create: bool ) -> Result<Option<impl LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>>>, LogToolErr> {
+        let find = self.find()?;
+        if !create && FindFiles::<PathBuf,LogQueueFileNumID>::find_files(&find)?.is_empty() {
+            return Ok(None);
+        }
+
+        let template = self.new_file.clone().unwrap_or(DEFAULT_NEW_FILE.to_string());
+        let conf: LogQueueConf<LogQueueFileNumID, PathBuf, FileBuff, _, _, _, _> = LogQueueConf {
+            find_files: find,
+            open_log_file: LogQueueFileNumIDOpen,
+            validate: QueueValidator::new(ValidateLevel::Quick),
+            new_file: path_template(&self.root, &template)?,
+            _p: PhantomData,
+        };
+        Ok(Some(conf.open()?))
+    }
+}
+
+/// Идентификатор записи в формате `{log_id}/{block_id}`
+pub fn parse_rec_id( value: &str ) -> Result<RecPos, String> {
+    let (log_id, block_id) = value.trim().split_once('/')
+        .ok_or_else(|| format!("expect {{log_id}}/{{block_id}}, found {value}"))?;
+    let log_id = log_id.parse::<u128>().map_err(|e| format!("log id {log_id}: {e}"))?;
+    let block_id = block_id.parse::<u32>().map_err(|e| format!("block id {block_id}: {e}"))?;
+    Ok((log_id, block_id))
+}
+
+/// Диапазон записей `{from}-{to}`, `to` включительно, без `to` - до конца очереди
+pub fn parse_rec_id_range( value: &str ) -> Result<(RecPos,Option<RecPos>), String> {
+    match value.split_once('-') {
+        None => Ok((parse_rec_id(value)?, None)),
+        Some((from, to)) if to.trim().is_empty() => Ok((parse_rec_id(from)?, None)),
+        Some((from, to)) => Ok((parse_rec_id(from)?, Some(parse_rec_id(to)?))),
+    }
+}
+
+/// Список лог файлов в порядке цепочки: идентификатор, предыдущий, кол-во блоков, размер, имя файла
+pub fn list_files( location: &QueueLocation ) -> Result<(), LogToolErr> {
+    let queue = match location.open(false)? {
+        Some(queue) => queue,
+        None => { println!("no log files"); return Ok(()); }
+    };
+
+    for (log_id, file, log) in queue.files() {
+        println!(
+            "{id:>6} {prev:>6} {blocks:>8} {bytes:>12} {file}",
+            id = log_id.id,
+            prev = log_id.previous.map(|p| p.to_string()).unwrap_or("-".to_string()),
+            blocks = log.count()?,
+            bytes = log.bytes_count()?,
+            file = file.display()
+        );
+    }
+    Ok(())
+}
+
+/// Последняя запись очереди `{log_id}/{block_id}` и актуальный лог файл
+pub fn show_tail( location: &QueueLocation ) -> Result<(), LogToolErr> {
+    let queue = match location.open(false)? {
+        Some(queue) => queue,
+        None => { println!("no log files"); return Ok(()); }
+    };
+
+    let q: &Queue = &queue;
+    let (_, file, _) = q.tail();
+    match q.last_record()? {
+        Some(rid) => println!("{}/{} {}", rid.log_file_id.id, rid.block_id.value(), file.display()),
+        None => println!("empty {}", file.display()),
+    }
+    Ok(())
+}
+
+/// Добавление записи в очередь
+///
+/// Перед записью актуальный лог файл переключается, если выполнено одно из условий `rotation`.
+/// Выводится идентификатор добавленной записи
+pub fn append_to_queue<D>(
+    location: &QueueLocation,
+    entry: D,
+    rotation: &Rotation,
+    tags: &Vec<TagAction>,
+) -> Result<(), LogToolErr>
+where
+    D: EntryData + ApplyContext
+{
+    let mut queue = location.open(true)?.unwrap();
+    if let Some((file, log_id)) = rotate_if_due(&mut queue, rotation, Local::now().fixed_offset())? {
+        println!("switched to {} {}", log_id.id, file.display());
+    }
+
+    let mut options = BlockOptions::default();
+    entry.apply(&mut options, tags)?;
+    let record = PreparedRecord { data: entry.entry_data()?, options };
+
+    let q: &Queue = &queue;
+    let rid = q.write(&record)?;
+    println!("{}/{}", rid.log_file_id.id, rid.block_id.value());
+    Ok(())
+}
+
+/// Выгрузка данных записей `from..=to` в stdout, записи могут быть в разных лог файлах
+///
+/// Блоки с идентификаторами лог файлов (блок 0) пропускаются
+pub fn extract_records( location: &QueueLocation, from: RecPos, to: Option<RecPos> ) -> Result<(), LogToolErr> {
+    let queue = match location.open(false)? {
+        Some(queue) => queue,
+        None => return Ok(()),
+    };
+
+    let mut out = std::io::stdout().lock();
+    for (log_id, _, log) in queue.files() {
+        if log_id.id < from.0 { continue; }
+        if to.map(|to| log_id.id > to.0).unwrap_or(false) { break; }
+
+        let first = if log_id.id == from.0 { from.1.max(1) } else { 1 };
+        let count = log.count()?;
+        for b in first..count {
+            if to.map(|to| (log_id.id, b) > to).unwrap_or(false) { break; }
+            let block = log.read_block(BlockId::new(b))?;
+            out.write_all(&block.data)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Переключение актуального лог файла
+pub fn switch_queue( location: &QueueLocation ) -> Result<(), LogToolErr> {
+    let mut queue = location.open(true)?.unwrap();
+    let (file, log_id) = queue.switch()?;
+    println!("{} {}", log_id.id, file.display());
+    Ok(())
+}
+
+#[test]
+fn test_parse_rec_id_range() {
+    assert_eq!(parse_rec_id("1/20"), Ok((1, 20)));
+    assert!(parse_rec_id("1").is_err());
+    assert_eq!(parse_rec_id_range("0/1-2/5"), Ok(((0, 1), Some((2, 5)))));
+    assert_eq!(parse_rec_id_range("3/1-"), Ok(((3, 1), None)));
+    assert_eq!(parse_rec_id_range("3/1"), Ok(((3, 1), None)));
+}
@@ -0,0 +1,61 @@
+use std::path::PathBuf;
+
+use chrono::Local;
+use logs::logqueue::*;
+use logs::logqueue::find_logs::FsLogFind;
+
+use crate::err::LogToolErr;
+
+/// Восстановление очереди
+///
+/// Выводит найденные проблемы и план восстановления, затем выполняет план (кроме `dry_run`)
+/// и повторно проверяет очередь
+///
+/// Параметры
+/// - `root` - корень очереди
+/// - `wildcard` - шаблон имени лог файлов
+/// - `level` - уровень проверки
+/// - `dry_run` - только показать план
+/// - `backup_dir`, `quarantine_dir` - каталоги резервных копий и карантина,
+///   по умолчанию `{root}.repair/{время}/backup` и `{root}.repair/{время}/quarantine`
+pub fn repair_queue(
+    root: &str,
+    wildcard: &str,
+    level: ValidateLevel,
+    dry_run: bool,
+    backup_dir: Option<PathBuf>,
+    quarantine_dir: Option<PathBuf>,
+) -> Result<(), LogToolErr> {
+    let find = FsLogFind::new(root, wildcard, true).map_err(LogToolErr::InvalidArg)?;
+
+    let plan = repair_plan(&find, level)?;
+    print!("{plan}");
+    if plan.actions.is_empty() {
+        return Ok(());
+    }
+
+    let defaults = RepairOptions::beside(&PathBuf::from(root), &Local::now().format("%Y%m%dT%H%M%S").to_string());
+    let options = RepairOptions {
+        dry_run,
+        backup_dir: backup_dir.unwrap_or(defaults.backup_dir),
+        quarantine_dir: quarantine_dir.unwrap_or(defaults.quarantine_dir),
+    };
+    if dry_run {
+        println!("dry run, nothing changed");
+        return Ok(());
+    }
+
+    let done = plan.apply(&options)?;
+    println!("applied {} actions, backup in {}", done.applied, options.backup_dir.display());
+    for file in &done.backup {
+        println!("backup {}", file.display());
+    }
+
+    let after = repair_plan(&find, level)?;
+    if after.report.is_ok() {
+        println!("queue is valid, chain {:?}", after.report.chain);
+    } else {
+        print!("queue still has problems\n{}", after.report);
+    }
+    Ok(())
+}
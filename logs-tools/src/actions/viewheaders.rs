@@ -1,49 +1,238 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::str::FromStr;
 use std::{path::Path, sync::{Arc, RwLock}};
 
 use logs::{bbuff::absbuff::FileBuff};
 use logs::logfile::*;
+use logs::logfile::block::BlockHeadRead;
+use serde::Serialize;
 use sha2::{Digest, Sha256};
 use crate::err::LogToolErr;
 
-/// Просмотр заголовков
-pub fn view_logfile<P: AsRef<Path>>(log_file: P, sha256: bool) -> Result<(), LogToolErr> {
-    let buff = FileBuff::open_read_only(log_file)?;
-    let log = LogFile::new(buff)?;
-    let log = Arc::new(RwLock::new(log));
+/// Формат вывода заголовков
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ViewFormat {
+    /// Таблица с выравниванием колонок
+    #[default]
+    Table,
 
-    let mut ptr = log.pointer_to_end()?;
-    loop {
-        let h = ptr.current_head();
-        print!(
-            "{b_id:0>6} {d_size:0>10}",
-            b_id = h.head.block_id.value(),
-            d_size = h.data_size.value()
-        );
-
-        if sha256 {
-            match ptr.current_data() {
-                Ok(data) => {
-                    let mut hasher = Sha256::new();
-                    hasher.update(*data);
-                    let hash = hasher.finalize();
-                    let hash = hex::encode(hash);
-                    print!(" {hash}");
-                }
-                Err(err) => {
-                    print!("can't read data {err:?}")
-                }
+    /// Строка json на блок
+    Json,
+
+    /// CSV со строкой заголовков
+    Csv,
+}
+
+impl FromStr for ViewFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Self::Table),
+            "json" | "jsonl" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(format!("unknown format {s}, expect table, json or csv"))
+        }
+    }
+}
+
+/// Параметры просмотра
+#[derive(Debug, Clone, Default)]
+pub struct ViewOptions {
+    /// Формат вывода
+    pub format: ViewFormat,
+
+    /// Вычислять sha256 данных блока
+    pub sha256: bool,
+
+    /// Кол-во первых байтов данных для предпросмотра
+    pub preview: Option<usize>,
+
+    /// Выводить только блоки, подходящие под фильтр
+    pub filter: Option<BlockFilter>,
+
+    /// Выводить данные блока как текст, если это UTF-8
+    pub text: bool,
+}
+
+impl ViewOptions {
+    /// Блок подходит под фильтр
+    pub fn matches( &self, head: &BlockHeadRead ) -> bool {
+        self.filter.as_ref()
+            .map(|f| f.matches(&head.head.block_options, head.data_size.value() as u64))
+            .unwrap_or(true)
+    }
+}
+
+/// Сведения о блоке
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct BlockView {
+    block_id: u32,
+    offset: u64,
+    head_size: u32,
+    data_size: u32,
+    tail_size: u16,
+    block_size: u64,
+    options: BTreeMap<String,String>,
+
+    #[serde(skip_serializing_if="Option::is_none")]
+    sha256: Option<String>,
+
+    #[serde(skip_serializing_if="Option::is_none")]
+    preview: Option<String>,
+
+    /// Данные блока как текст
+    #[serde(skip_serializing_if="Option::is_none")]
+    text: Option<String>,
+
+    /// Ошибка чтения данных блока
+    #[serde(skip_serializing_if="Option::is_none")]
+    error: Option<String>,
+}
+
+/// Предпросмотр данных: печатные ASCII символы как есть, остальные - `\xNN`
+fn preview_of( data: &[u8], limit: usize ) -> String {
+    let mut text = String::new();
+    for b in data.iter().take(limit) {
+        match *b {
+            b'\\' => text.push_str("\\\\"),
+            0x20..=0x7e => text.push(*b as char),
+            _ => text.push_str(&format!("\\x{b:02x}")),
+        }
+    }
+    if data.len() > limit {
+        text.push_str("...");
+    }
+    text
+}
+
+/// Значение поля CSV, при необходимости в кавычках
+fn csv_field( value: &str ) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+const CSV_HEADER: &str = "block_id,offset,head_size,data_size,tail_size,block_size,options,sha256,preview,error,text";
+
+impl BlockView {
+    fn options_text( &self ) -> String {
+        self.options.iter().map(|(k,v)| format!("{k}={v}")).collect::<Vec<_>>().join(" ")
+    }
+
+    fn table_header( opts: &ViewOptions ) -> String {
+        let mut line = format!("{:>6} {:>10} {:>6} {:>10} {:>4}", "block", "offset", "head", "data", "tail");
+        if opts.sha256 { line.push_str(&format!(" {:<64}", "sha256")); }
+        if opts.preview.is_some() { line.push_str(" preview"); }
+        line.push_str(" options");
+        line
+    }
+
+    fn table_row( &self, opts: &ViewOptions ) -> String {
+        let mut line = format!("{:>6} {:>10} {:>6} {:>10} {:>4}", self.block_id, self.offset, self.head_size, self.data_size, self.tail_size);
+        if opts.sha256 { line.push_str(&format!(" {:<64}", self.sha256.clone().unwrap_or("-".to_string()))); }
+        if opts.preview.is_some() { line.push_str(&format!(" \"{}\"", self.preview.clone().unwrap_or_default())); }
+        line.push(' ');
+        line.push_str(&self.options_text());
+        if let Some(err) = &self.error { line.push_str(&format!(" error: {err}")); }
+        if let Some(text) = &self.text {
+            for text_line in text.lines() {
+                line.push_str(&format!("\n    {text_line}"));
             }
         }
+        line
+    }
+
+    fn csv_row( &self ) -> String {
+        [
+            self.block_id.to_string(),
+            self.offset.to_string(),
+            self.head_size.to_string(),
+            self.data_size.to_string(),
+            self.tail_size.to_string(),
+            self.block_size.to_string(),
+            csv_field(&self.options_text()),
+            self.sha256.clone().unwrap_or_default(),
+            csv_field(&self.preview.clone().unwrap_or_default()),
+            csv_field(&self.error.clone().unwrap_or_default()),
+            csv_field(&self.text.clone().unwrap_or_default()),
+        ].join(",")
+    }
+
+    /// Строка в формате вывода
+    pub(crate) fn format( &self, opts: &ViewOptions ) -> Result<String, LogToolErr> {
+        match opts.format {
+            ViewFormat::Table => Ok(self.table_row(opts)),
+            ViewFormat::Csv => Ok(self.csv_row()),
+            ViewFormat::Json => serde_json::to_string(self)
+                .map_err(|err| LogToolErr::Json(err.to_string())),
+        }
+    }
+
+    /// Заголовок вывода, для json - нет
+    pub(crate) fn header( opts: &ViewOptions ) -> Option<String> {
+        match opts.format {
+            ViewFormat::Table => Some(Self::table_header(opts)),
+            ViewFormat::Csv => Some(CSV_HEADER.to_string()),
+            ViewFormat::Json => None,
+        }
+    }
+}
 
-        //print!("{:?}", h);
+pub(crate) fn block_view<B: FlatBuff>( ptr: &LogPointer<B>, opts: &ViewOptions ) -> BlockView {
+    let h = ptr.current_head();
+    let mut view = BlockView {
+        block_id: h.head.block_id.value(),
+        offset: h.position.value(),
+        head_size: h.head_size.value(),
+        data_size: h.data_size.value(),
+        tail_size: h.tail_size.value(),
+        block_size: h.block_size(),
+        options: h.head.block_options.clone().into_iter().map(|(k,v)| (k.to_string(), v.to_string())).collect(),
+        sha256: None,
+        preview: None,
+        text: None,
+        error: None,
+    };
 
-        if !h.head.block_options.is_empty() {
-            for (key, value) in h.head.block_options.clone() {
-                print!(" {key}={value}")
+    if opts.sha256 || opts.preview.is_some() || opts.text {
+        match ptr.current_data() {
+            Ok(data) => {
+                if opts.sha256 {
+                    view.sha256 = Some(hex::encode(Sha256::digest(&data[..])));
+                }
+                view.preview = opts.preview.map(|limit| preview_of(&data, limit));
+                if opts.text {
+                    view.text = std::str::from_utf8(&data).ok().map(|text| text.to_string());
+                }
             }
+            Err(err) => view.error = Some(format!("can't read data {err:?}"))
         }
+    }
+    view
+}
 
-        println!();
+/// Просмотр заголовков, от последнего блока к первому
+///
+/// Если указан фильтр, то выводятся только подходящие блоки
+pub fn view_logfile<P: AsRef<Path>>(log_file: P, opts: &ViewOptions) -> Result<(), LogToolErr> {
+    let buff = FileBuff::open_read_only(log_file)?;
+    let log = LogFile::new(buff)?;
+    let log = Arc::new(RwLock::new(log));
+
+    let mut out = std::io::stdout().lock();
+    if let Some(header) = BlockView::header(opts) {
+        writeln!(out, "{header}")?;
+    }
+
+    let mut ptr = log.pointer_to_end()?;
+    loop {
+        if opts.matches(ptr.current_head()) {
+            writeln!(out, "{}", block_view(&ptr, opts).format(opts)?)?;
+        }
 
         match ptr.previous() {
             Ok(next_ptr) => ptr = next_ptr,
@@ -55,3 +244,13 @@ pub fn view_logfile<P: AsRef<Path>>(log_file: P, sha256: bool) -> Result<(), Log
 
     Ok(())
 }
+
+#[test]
+fn test_view_fields() {
+    assert_eq!(preview_of(b"ab\\\n\x01", 10), "ab\\\\\\x0a\\x01");
+    assert_eq!(preview_of(b"abcdef", 3), "abc...");
+    assert_eq!(csv_field("a=1 b=2"), "a=1 b=2");
+    assert_eq!(csv_field("a=\"x\",y"), "\"a=\"\"x\"\",y\"");
+    assert_eq!("jsonl".parse::<ViewFormat>(), Ok(ViewFormat::Json));
+    assert!("xml".parse::<ViewFormat>().is_err());
+}
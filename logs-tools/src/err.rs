@@ -1,7 +1,9 @@
+use std::path::PathBuf;
 use std::sync::{PoisonError, RwLockReadGuard};
 
 use logs::{bbuff::absbuff::ABuffError, logfile::LogErr};
 use logs::logfile::block::*;
+use logs::logqueue::{LogQueueFileNumID, LoqErr, RepairErr};
 
 use crate::actions::tag::TagAction;
 
@@ -22,7 +24,23 @@ pub enum LogToolErr {
         message: String,
         tag: TagAction,
     },
-    NotImplemented(String)
+    NotImplemented(String),
+    InvalidArg(String),
+    Json(String),
+    Repair(RepairErr),
+    Queue(Box<LoqErr<PathBuf,LogQueueFileNumID>>),
+}
+
+impl From<LoqErr<PathBuf,LogQueueFileNumID>> for LogToolErr {
+    fn from(value: LoqErr<PathBuf,LogQueueFileNumID>) -> Self {
+        Self::Queue(Box::new(value))
+    }
+}
+
+impl From<RepairErr> for LogToolErr {
+    fn from(value: RepairErr) -> Self {
+        Self::Repair(value)
+    }
 }
 
 impl From<BlockErr> for LogToolErr {
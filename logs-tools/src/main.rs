@@ -3,6 +3,8 @@
 //! - просмотр лог файла
 //! - добавление файла в лог
 //! - выгрузка файла из лога
+//! - операции с очередью лог файлов
+//! - восстановление очереди лог файлов
 
 mod bytesize;
 mod err;
@@ -10,7 +12,10 @@ use actions::tag::TagAction;
 use bytesize::ByteSize;
 
 use err::LogToolErr;
+use logs::logfile::BlockFilter;
 use logs::logfile::block::*;
+use logs::logqueue::{Rotation, ValidateLevel};
+use logs::perf::Tracker;
 
 use parse::*;
 use range::{MultipleParse, Range};
@@ -21,6 +26,11 @@ use std::{
 
 mod actions;
 use actions::*;
+use actions::queue::QueueLocation;
+use actions::viewheaders::{ViewFormat, ViewOptions};
+use actions::archive::{self, ArchiveFormat};
+use actions::follow::{self, FollowOptions};
+use actions::browse;
 mod range;
 mod buildinfo;
 
@@ -31,7 +41,7 @@ fn main() {
         match action.execute() {
             Ok(_) => {}
             Err(err) => {
-                println!(
+                eprintln!(
                     "execute {act:?} failed with {err:?}",
                     act = action,
                     err = err
@@ -60,6 +70,10 @@ fn main() {
 /// 
 /// ```
 /// option ::= verbose | compute_sha256_entry | block_buffer_size | tag
+///          | dry_run | validate_level | backup_dir | quarantine_dir
+///          | new_file | max_file_size | max_file_blocks
+///          | view_format | preview | archive_format
+///          | text | follow_last | follow_interval
 /// 
 /// verbose ::= '-v' | '+v'
 /// compute_sha256_entry ::= '-sha256' | '+sha256'
@@ -67,6 +81,19 @@ fn main() {
 /// tag ::= 'tag' tag_action
 /// tag_action ::= 'clear' | 'default' | tag_add
 /// tag_add ::= ( 'add' | '+' ) tag_key tag_value
+/// dry_run ::= '-dry_run' | '+dry_run'
+/// validate_level ::= '-validate' ( 'quick' | 'headers' | 'full' )
+/// backup_dir ::= '-backup_dir' dir_name
+/// quarantine_dir ::= '-quarantine_dir' dir_name
+/// new_file ::= '-new_file' new_file_template
+/// max_file_size ::= '-max_file_size' ByteSize
+/// max_file_blocks ::= '-max_file_blocks' dec_number
+/// view_format ::= '-format' ( 'table' | 'json' | 'csv' )
+/// preview ::= '-preview' dec_number
+/// archive_format ::= '-archive' ( 'tar' | 'zip' | 'ndjson' )
+/// text ::= '-text' | '+text'
+/// follow_last ::= '-last' dec_number
+/// follow_interval ::= '-interval' duration
 /// 
 /// ByteSize ::= dec_number {white_space} [size_suffix]
 /// size_suffix ::= kb | mb | gb
@@ -75,13 +102,20 @@ fn main() {
 /// gb ::= ( 'G' | 'g' ) b
 /// b = 'B' | 'b'
 /// 
-/// command ::= append_cmd | view_cmd | extract_cmd
+/// command ::= append_cmd | view_cmd | extract_cmd | queue_cmd | repair_cmd
+///         | export_cmd | import_cmd | follow_cmd | browse_cmd
 /// ```
 /// 
 /// Комманды
 /// - append_cmd - добавляет запись в лог
 /// - view_cmd - просмотр заголовков записей в логе
 /// - extract_cmd - извлечение записи из лога
+/// - queue_cmd - операции с очередью лог файлов
+/// - repair_cmd - восстановление очереди лог файлов
+/// - export_cmd - экспорт блоков лог файла в архив
+/// - import_cmd - импорт записей из архива в лог файл
+/// - follow_cmd - слежение за новыми блоками лог файла
+/// - browse_cmd - интерактивный просмотр лог файла в терминале
 /// 
 /// ```
 /// append_cmd ::= ( 'a' | 'append' ) log_file_name append_what
@@ -93,11 +127,81 @@ fn main() {
 /// - log_file_name - имя лог файла
 /// 
 /// ```
-/// view_cmd ::= ( 'v' | 'view' ) log_file_name
+/// view_cmd ::= ( 'v' | 'view' ) log_file_name [ 'where' filter ]
+/// ```
+/// 
+/// Просмотр заголовков выводит для каждого блока: идентификатор, смещение, размеры заголовка, данных и хвоста, опции,
+/// с `+sha256` - контрольную сумму данных, с `-preview` - первые байты данных (0 - отключить).
+/// Формат вывода `-format`
+/// - table - таблица, по умолчанию
+/// - json - строка json на блок
+/// - csv - CSV со строкой заголовков
+/// 
+/// С `+text` выводятся данные блока как текст, если это UTF-8.
+/// 
+/// Слежение `follow` выводит последние `-last` блоков (по умолчанию 10), затем новые блоки по мере записи,
+/// лог файл проверяется с интервалом `-interval` (по умолчанию 1 sec); опции вывода и фильтр - как у `view`.
+/// Не дописанный блок не прерывает слежение - чтение повторяется с увеличивающейся задержкой.
+/// 
+/// Просмотр `browse` - список блоков, заголовок и обратные ссылки текущего блока, данные (hex / текст),
+/// переход к блоку, поиск по опциям; клавиши см. [actions::browse].
+/// 
+/// `filter` - выражение над метаданными блока, выводятся только подходящие блоки,
+/// синтаксис см. [logs::logfile::BlockFilter]
+/// 
+/// ```text
+/// filter ::= and_filter { 'or' and_filter }
+/// and_filter ::= condition { 'and' condition }
+/// condition ::= 'not' condition | '(' filter ')'
+///             | option_key ( '=' | '!=' ) wildcard
+///             | 'size' ( '<' | '<=' | '>' | '>=' | '=' | '!=' ) ByteSize
+///             | 'time' ( '<' | '<=' | '>' | '>=' ) ( rfc3339 | date | 'now' | 'now-' duration )
+/// ```
+/// 
+/// ```
 /// 
 /// extract_cmd ::= ( 'e' | 'extract' ) log_file_name extract_selection
+/// 
+/// queue_cmd ::= ( 'q' | 'queue' ) queue_root log_wildcard queue_action
+/// queue_action ::= 'files' | 'tail' | 'switch' | queue_append | queue_extract
+///                | 'export' archive_file | 'import' archive_file
+///                | 'follow' [ 'where' filter ]
+/// queue_append ::= ( 'a' | 'append' ) append_what
+/// queue_extract ::= ( 'e' | 'extract' ) rec_id_range
+/// rec_id_range ::= rec_id [ '-' [ rec_id ] ]
+/// rec_id ::= dec_number '/' dec_number
+/// 
+/// repair_cmd ::= 'repair' queue_root log_wildcard
+/// 
+/// export_cmd ::= 'export' log_file_name archive_file
+/// import_cmd ::= 'import' archive_file log_file_name
+/// follow_cmd ::= 'follow' log_file_name [ 'where' filter ]
+/// browse_cmd ::= 'browse' log_file_name
 /// ```
 /// 
+/// - queue_root - каталог очереди, поиск лог файлов выполняется рекурсивно
+/// - log_wildcard - шаблон имени лог файлов, например `*.binlog`
+/// - new_file_template - шаблон имени нового лог файла, по умолчанию `${root}/${time:local:yyyy-mm-ddThh-mi-ss}-${rnd:5}.binlog`
+/// - archive_file - файл архива, `-` - stdout/stdin; формат по `-archive` или по расширению (`.tar`, `.zip`, `.ndjson`)
+/// 
+/// Действия с очередью
+/// - files - список лог файлов в порядке цепочки: идентификатор, предыдущий, кол-во блоков, размер, имя
+/// - tail - идентификатор последней записи `{log_id}/{block_id}`
+/// - switch - переключение актуального лог файла
+/// - append - добавление записи, перед записью лог файл переключается
+///   при достижении `-max_file_size` или `-max_file_blocks`
+/// - extract - выгрузка данных записей в stdout, без второго rec_id - до конца очереди
+/// - export - экспорт записей очереди в архив, блоки с идентификаторами лог файлов не выгружаются
+/// - import - импорт записей из архива, лог файл переключается как при append
+/// - follow - слежение за новыми записями, переключение лог файла выводится в stderr
+/// 
+/// Архив
+/// - tar, zip - файл на блок `blocks/{номер:08}.bin` и `manifest.json` с опциями, типом данных и sha256 блоков,
+///   tar читается только из файла
+/// - ndjson - строка json на блок: `source`, `options`, `data_type`, `data` (base64)
+/// 
+/// Опции блоков при импорте сохраняются как есть
+/// 
 /// - extract_selection - Указывает какие записи необходимо получить
 /// 
 /// ```
@@ -152,6 +256,18 @@ fn parse_args(args: &Vec<String>) -> Box<Vec<Action>> {
     let mut verbose: bool = false;
     let mut tags: Vec<TagAction> = vec![];
     let mut custom_tag_name: Option<String16> = None;
+    let mut dry_run = false;
+    let mut validate_level = ValidateLevel::default();
+    let mut backup_dir: Option<PathBuf> = None;
+    let mut quarantine_dir: Option<PathBuf> = None;
+    let mut new_file: Option<String> = None;
+    let mut rotation = Rotation::default();
+    let mut queue_location: Option<QueueLocation> = None;
+    let mut view_format = ViewFormat::default();
+    let mut preview: Option<usize> = None;
+    let mut archive_format: Option<ArchiveFormat> = None;
+    let mut text = false;
+    let mut follow_options = FollowOptions::default();
 
     loop {
         let arg = itr.next();
@@ -166,6 +282,8 @@ fn parse_args(args: &Vec<String>) -> Box<Vec<Action>> {
                     state = "append"
                 } else if arg == "v" || arg == "view" {
                     state = "view"
+                } else if arg == "where" && actions.last_mut().and_then(Action::view_options).is_some() {
+                    state = "where"
                 } else if arg == "+sha256" {
                     sha256 = true
                 } else if arg == "-sha256" {
@@ -180,6 +298,48 @@ fn parse_args(args: &Vec<String>) -> Box<Vec<Action>> {
                     state = "tag"
                 } else if arg == "e" || arg == "extract" {
                     state = "extract"
+                } else if arg == "q" || arg == "queue" {
+                    state = "queue"
+                } else if arg == "-format" {
+                    state = "-format"
+                } else if arg == "-preview" {
+                    state = "-preview"
+                } else if arg == "+text" {
+                    text = true
+                } else if arg == "-text" {
+                    text = false
+                } else if arg == "-last" {
+                    state = "-last"
+                } else if arg == "-interval" {
+                    state = "-interval"
+                } else if arg == "follow" {
+                    state = "follow"
+                } else if arg == "browse" {
+                    state = "browse"
+                } else if arg == "-archive" {
+                    state = "-archive"
+                } else if arg == "export" {
+                    state = "export"
+                } else if arg == "import" {
+                    state = "import"
+                } else if arg == "-new_file" {
+                    state = "-new_file"
+                } else if arg == "-max_file_size" {
+                    state = "-max_file_size"
+                } else if arg == "-max_file_blocks" {
+                    state = "-max_file_blocks"
+                } else if arg == "repair" {
+                    state = "repair"
+                } else if arg == "+dry_run" {
+                    dry_run = true
+                } else if arg == "-dry_run" {
+                    dry_run = false
+                } else if arg == "-validate" {
+                    state = "-validate"
+                } else if arg == "-backup_dir" {
+                    state = "-backup_dir"
+                } else if arg == "-quarantine_dir" {
+                    state = "-quarantine_dir"
                 } else {
                     println!("undefined arg {arg}")
                 }
@@ -188,6 +348,177 @@ fn parse_args(args: &Vec<String>) -> Box<Vec<Action>> {
                 state = "state";
                 block_buff_size = Some(ByteSize::parse(arg).unwrap())
             }
+            "-validate" => {
+                state = "state";
+                match arg.parse() {
+                    Ok(level) => validate_level = level,
+                    Err(err) => println!("{err}")
+                }
+            }
+            "-backup_dir" => {
+                state = "state";
+                backup_dir = Some(PathBuf::from(arg))
+            }
+            "-quarantine_dir" => {
+                state = "state";
+                quarantine_dir = Some(PathBuf::from(arg))
+            }
+            "-format" => {
+                state = "state";
+                match arg.parse() {
+                    Ok(format) => view_format = format,
+                    Err(err) => println!("{err}")
+                }
+            }
+            "-archive" => {
+                state = "state";
+                match arg.parse::<ArchiveFormat>() {
+                    Ok(format) => archive_format = Some(format),
+                    Err(err) => println!("{err}")
+                }
+            }
+            "export" => {
+                log_file_name = Box::new(Some(arg.clone()));
+                state = "export_to"
+            }
+            "export_to" => {
+                state = "state";
+                actions.push(Action::Export {
+                    log_file: log_file_name.clone().unwrap(),
+                    archive: arg.clone(),
+                    format: archive_format,
+                });
+            }
+            "import" => {
+                log_file_name = Box::new(Some(arg.clone()));
+                state = "import_to"
+            }
+            "import_to" => {
+                state = "state";
+                actions.push(Action::Import {
+                    archive: log_file_name.clone().unwrap(),
+                    format: archive_format,
+                    log_file: arg.clone(),
+                });
+            }
+            "-preview" => {
+                state = "state";
+                match arg.parse::<usize>() {
+                    Ok(0) => preview = None,
+                    Ok(n) => preview = Some(n),
+                    Err(err) => println!("preview {arg}: {err}")
+                }
+            }
+            "-new_file" => {
+                state = "state";
+                new_file = Some(arg.clone())
+            }
+            "-max_file_size" => {
+                state = "state";
+                rotation.max_bytes = Some(ByteSize::parse(arg).unwrap().0 as u64)
+            }
+            "-max_file_blocks" => {
+                state = "state";
+                match arg.parse() {
+                    Ok(n) => rotation.max_blocks = Some(n),
+                    Err(err) => println!("max_file_blocks {arg}: {err}")
+                }
+            }
+            "queue" => {
+                log_file_name = Box::new(Some(arg.clone()));
+                state = "queue_wildcard"
+            }
+            "queue_wildcard" => {
+                queue_location = Some(QueueLocation {
+                    root: log_file_name.clone().unwrap(),
+                    wildcard: arg.clone(),
+                    new_file: new_file.clone(),
+                });
+                state = "queue_action"
+            }
+            "queue_action" => {
+                state = "state";
+                let location = queue_location.clone().unwrap();
+                match &arg[..] {
+                    "files" => actions.push(Action::Queue { location, action: QueueAction::Files }),
+                    "tail" => actions.push(Action::Queue { location, action: QueueAction::Tail }),
+                    "switch" => actions.push(Action::Queue { location, action: QueueAction::Switch }),
+                    "a" | "append" => state = "queue_append_what",
+                    "e" | "extract" => state = "queue_extract",
+                    "export" => state = "queue_export",
+                    "import" => state = "queue_import",
+                    "follow" => actions.push(Action::Queue {
+                        location,
+                        action: QueueAction::Follow {
+                            options: FollowOptions {
+                                view: ViewOptions { format: view_format, sha256, preview, filter: None, text },
+                                ..follow_options.clone()
+                            }
+                        }
+                    }),
+                    _ => println!("undefined queue action {arg}, expect files, tail, switch, append, extract, export, import or follow")
+                }
+            }
+            "queue_append_what" => {
+                state = "state";
+                let entry = match &arg[..] {
+                    "file" => { state = "queue_append_file"; None },
+                    "stdin" => Some(EntryDataSource::Stdin),
+                    _ => { println!("undefined input arg {arg}, expect file <file_name> or stdin"); None }
+                };
+                if let Some(entry) = entry {
+                    actions.push(Action::Queue {
+                        location: queue_location.clone().unwrap(),
+                        action: QueueAction::Append { entry, rotation: rotation.clone(), tags: tags.clone() }
+                    });
+                }
+            }
+            "queue_append_file" => {
+                state = "state";
+                actions.push(Action::Queue {
+                    location: queue_location.clone().unwrap(),
+                    action: QueueAction::Append { entry: EntryDataSource::File(arg.clone()), rotation: rotation.clone(), tags: tags.clone() }
+                });
+            }
+            "queue_extract" => {
+                state = "state";
+                match queue::parse_rec_id_range(arg) {
+                    Ok((from, to)) => actions.push(Action::Queue {
+                        location: queue_location.clone().unwrap(),
+                        action: QueueAction::Extract { from, to }
+                    }),
+                    Err(err) => println!("range {arg} not parsed: {err}")
+                }
+            }
+            "queue_export" => {
+                state = "state";
+                actions.push(Action::Queue {
+                    location: queue_location.clone().unwrap(),
+                    action: QueueAction::Export { archive: arg.clone(), format: archive_format }
+                });
+            }
+            "queue_import" => {
+                state = "state";
+                actions.push(Action::Queue {
+                    location: queue_location.clone().unwrap(),
+                    action: QueueAction::Import { archive: arg.clone(), format: archive_format, rotation: rotation.clone() }
+                });
+            }
+            "repair" => {
+                log_file_name = Box::new(Some(arg.clone()));
+                state = "repair_wildcard"
+            }
+            "repair_wildcard" => {
+                state = "state";
+                actions.push(Action::Repair {
+                    root: log_file_name.clone().unwrap(),
+                    wildcard: arg.clone(),
+                    level: validate_level,
+                    dry_run,
+                    backup_dir: backup_dir.clone(),
+                    quarantine_dir: quarantine_dir.clone(),
+                });
+            }
             "append" => {
                 log_file_name = Box::new(Some(arg.clone()));
                 state = "append_what"
@@ -227,9 +558,52 @@ fn parse_args(args: &Vec<String>) -> Box<Vec<Action>> {
                 state = "state";
                 actions.push(Action::ViewHeads {
                     log_file: arg.clone(),
-                    sha256: sha256,
+                    options: ViewOptions { format: view_format, sha256, preview, filter: None, text },
+                });
+            },
+            "follow" => {
+                state = "state";
+                actions.push(Action::Follow {
+                    log_file: arg.clone(),
+                    options: FollowOptions {
+                        view: ViewOptions { format: view_format, sha256, preview, filter: None, text },
+                        ..follow_options.clone()
+                    },
                 });
             },
+            "browse" => {
+                state = "state";
+                actions.push(Action::Browse { log_file: arg.clone() });
+            },
+            "-last" => {
+                state = "state";
+                match arg.parse::<usize>() {
+                    Ok(n) => follow_options.last = n,
+                    Err(err) => println!("last {arg}: {err}")
+                }
+            }
+            "-interval" => {
+                state = "state";
+                match DurationParser.parse(arg) {
+                    Some((interval, _)) => follow_options.interval = interval,
+                    None => println!("interval {arg} not parsed, expect duration like 500ms or 2sec")
+                }
+            }
+            "where" => {
+                state = "state";
+                match arg.parse::<BlockFilter>() {
+                    Ok(filter) => {
+                        if let Some(options) = actions.last_mut().and_then(Action::view_options) {
+                            options.filter = Some(filter);
+                        }
+                    },
+                    Err(err) => {
+                        // без фильтра вывод был бы неверным
+                        actions.pop();
+                        println!("where {arg}: {err}")
+                    }
+                }
+            },
             "tag" => {
                 state = "state";
                 match &arg[..] {
@@ -325,6 +699,52 @@ enum EntryDataSource {
     File(String)
 }
 
+/// Действие с очередью
+#[derive(Debug, Clone)]
+enum QueueAction {
+    /// Список лог файлов
+    Files,
+
+    /// Последняя запись
+    Tail,
+
+    /// Переключение лог файла
+    Switch,
+
+    /// Добавление записи
+    Append {
+        /// Добавляемый файл/данные
+        entry: EntryDataSource,
+
+        /// Условия переключения лог файла перед записью
+        rotation: Rotation,
+
+        /// Теги
+        tags: Vec<TagAction>,
+    },
+
+    /// Выгрузка записей, `to` включительно
+    Extract { from: queue::RecPos, to: Option<queue::RecPos> },
+
+    /// Экспорт записей в архив
+    Export { archive: String, format: Option<ArchiveFormat> },
+
+    /// Слежение за новыми записями
+    Follow { options: FollowOptions },
+
+    /// Импорт записей из архива
+    Import {
+        /// Архив
+        archive: String,
+
+        /// Формат архива, по умолчанию по расширению
+        format: Option<ArchiveFormat>,
+
+        /// Условия переключения лог файла перед записью
+        rotation: Rotation,
+    },
+}
+
 /// Операции с лог файлом
 #[derive(Debug, Clone)]
 enum Action {
@@ -347,7 +767,7 @@ enum Action {
     },
     
     /// Просмотр заголовков лог файла
-    ViewHeads { log_file: String, sha256: bool },
+    ViewHeads { log_file: String, options: ViewOptions },
 
     /// Извлечение записи из лога
     Extract {
@@ -358,6 +778,66 @@ enum Action {
         selection: ExtractSelection
     },
 
+    /// Слежение за новыми блоками лог файла
+    Follow { log_file: String, options: FollowOptions },
+
+    /// Интерактивный просмотр лог файла
+    Browse { log_file: String },
+
+    /// Экспорт блоков лог файла в архив
+    Export {
+        /// Лог файл
+        log_file: String,
+
+        /// Архив, `-` - stdout
+        archive: String,
+
+        /// Формат архива, по умолчанию по расширению
+        format: Option<ArchiveFormat>,
+    },
+
+    /// Импорт записей из архива в лог файл
+    Import {
+        /// Архив, `-` - stdin
+        archive: String,
+
+        /// Формат архива, по умолчанию по расширению
+        format: Option<ArchiveFormat>,
+
+        /// Лог файл
+        log_file: String,
+    },
+
+    /// Операции с очередью лог файлов
+    Queue {
+        /// Расположение очереди
+        location: QueueLocation,
+
+        /// Действие
+        action: QueueAction,
+    },
+
+    /// Восстановление очереди лог файлов
+    Repair {
+        /// Каталог очереди
+        root: String,
+
+        /// Шаблон имени лог файлов
+        wildcard: String,
+
+        /// Уровень проверки
+        level: ValidateLevel,
+
+        /// Только показать план восстановления
+        dry_run: bool,
+
+        /// Каталог резервных копий
+        backup_dir: Option<PathBuf>,
+
+        /// Каталог карантина
+        quarantine_dir: Option<PathBuf>,
+    },
+
     /// Взаимодействие с очередью
     #[allow(dead_code)]
     QAction {
@@ -366,6 +846,16 @@ enum Action {
 }
 
 impl Action {
+    /// Параметры вывода блоков, для действий с `where`
+    fn view_options( &mut self ) -> Option<&mut ViewOptions> {
+        match self {
+            Action::ViewHeads { options, .. } => Some(options),
+            Action::Follow { options, .. } => Some(&mut options.view),
+            Action::Queue { action: QueueAction::Follow { options }, .. } => Some(&mut options.view),
+            _ => None,
+        }
+    }
+
     #[allow(unused_variables)]
     fn execute(&self) -> Result<(), LogToolErr> {
         match self {
@@ -408,11 +898,11 @@ impl Action {
 
                 Ok(())
             }
-            Action::ViewHeads { log_file, sha256 } => {
+            Action::ViewHeads { log_file, options } => {
                 let mut p0 = PathBuf::new();
                 p0.push(log_file);
 
-                viewheaders::view_logfile(p0, *sha256)
+                viewheaders::view_logfile(p0, options)
             }
             Action::Extract { 
                 log_file,
@@ -435,6 +925,43 @@ impl Action {
                     }
                 }
             },
+            Action::Follow { log_file, options } => follow::follow_log_file(log_file, options),
+            Action::Browse { log_file } => browse::browse_log_file(log_file),
+            Action::Export { log_file, archive, format } => {
+                archive::export_log_file(log_file, archive, ArchiveFormat::resolve(*format, archive)?)
+            },
+            Action::Import { archive, format, log_file } => {
+                archive::import_log_file(archive, ArchiveFormat::resolve(*format, archive)?, log_file)
+            },
+            Action::Queue { location, action } => {
+                match action {
+                    QueueAction::Files => queue::list_files(location),
+                    QueueAction::Tail => queue::show_tail(location),
+                    QueueAction::Switch => queue::switch_queue(location),
+                    QueueAction::Append { entry, rotation, tags } => {
+                        match entry {
+                            EntryDataSource::File(file_name) => {
+                                let entry = EntryFile::read_file(PathBuf::from(file_name), &Tracker::new())?;
+                                queue::append_to_queue(location, entry, rotation, tags)
+                            },
+                            EntryDataSource::Stdin => {
+                                queue::append_to_queue(location, EntryStdin::read_stdin()?, rotation, tags)
+                            }
+                        }
+                    },
+                    QueueAction::Extract { from, to } => queue::extract_records(location, *from, *to),
+                    QueueAction::Follow { options } => follow::follow_queue(location, options),
+                    QueueAction::Export { archive, format } => {
+                        archive::export_queue(location, archive, ArchiveFormat::resolve(*format, archive)?)
+                    },
+                    QueueAction::Import { archive, format, rotation } => {
+                        archive::import_queue(archive, ArchiveFormat::resolve(*format, archive)?, location, rotation)
+                    },
+                }
+            },
+            Action::Repair { root, wildcard, level, dry_run, backup_dir, quarantine_dir } => {
+                repair::repair_queue(root, wildcard, *level, *dry_run, backup_dir.clone(), quarantine_dir.clone())
+            },
             Action::QAction { base_url } => {
                 Err(LogToolErr::NotImplemented("queue operation not implemented".to_string()))
             }
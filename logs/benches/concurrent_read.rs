@@ -0,0 +1,173 @@
+//! Пропускная способность чтения очереди во время записи
+//!
+//! Сравнивается блокировка всей очереди одним `Mutex` и [SharedLogQueue]
+//! (параллельные читатели, один писатель).
+//!
+//! Запуск: `cargo bench -p logs --bench concurrent_read`
+//!
+//! Параметры (переменные окружения)
+//! - `BENCH_READERS` - кол-во потоков читателей, по умолчанию `1,2,4,8`
+//! - `BENCH_SECONDS` - продолжительность каждого замера, по умолчанию `2`
+
+use std::fs::{create_dir_all, remove_dir_all};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use logs::bbuff::absbuff::FileBuff;
+use logs::logfile::LogFile;
+use logs::logfile::block::{BlockId, BlockOptions};
+use logs::logqueue::find_logs::FsLogFind;
+use logs::logqueue::*;
+
+type Queue = dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>>;
+
+/// Кол-во записей до переключения лог файла
+const RECORDS_PER_FILE: u64 = 5000;
+
+/// Способ доступа к очереди
+trait Bench: Clone + Send + 'static {
+    fn name( &self ) -> &'static str;
+
+    /// Чтение записи `n` от конца очереди, результат - размер данных
+    fn read( &self, n: u32 ) -> usize;
+
+    /// Добавление записи, каждые [RECORDS_PER_FILE] записей - переключение лог файла
+    fn write( &self, i: u64, data: &[u8] );
+}
+
+fn read_from_tail( q: &Queue, n: u32 ) -> usize {
+    let (_, _, log) = q.tail();
+    let count = log.count().unwrap();
+    if count < 2 {
+        return 0;
+    }
+    let block_id = count - 1 - (n % (count - 1));
+    log.read_block(BlockId::new(block_id)).unwrap().data.len()
+}
+
+fn record( data: &[u8] ) -> PreparedRecord {
+    PreparedRecord { data: data.to_vec(), options: BlockOptions::default() }
+}
+
+/// Вся очередь под одной блокировкой
+#[derive(Clone)]
+struct MutexQueue( Arc<Mutex<dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> + Send>> );
+
+impl Bench for MutexQueue {
+    fn name( &self ) -> &'static str { "mutex" }
+
+    fn read( &self, n: u32 ) -> usize {
+        let q = self.0.lock().unwrap();
+        read_from_tail(&*q, n)
+    }
+
+    fn write( &self, i: u64, data: &[u8] ) {
+        let mut q = self.0.lock().unwrap();
+        let q: &mut Queue = &mut *q;
+        if i % RECORDS_PER_FILE == RECORDS_PER_FILE - 1 {
+            q.switch().unwrap();
+        } else {
+            q.write(&record(data)).unwrap();
+        }
+    }
+}
+
+/// Один писатель, параллельные читатели
+#[derive(Clone)]
+struct Shared( SharedLogQueue<'static,LogQueueFileNumID,PathBuf,FileBuff> );
+
+impl Bench for Shared {
+    fn name( &self ) -> &'static str { "shared" }
+
+    fn read( &self, n: u32 ) -> usize {
+        let q = self.0.read().unwrap();
+        read_from_tail(&*q, n)
+    }
+
+    fn write( &self, i: u64, data: &[u8] ) {
+        if i % RECORDS_PER_FILE == RECORDS_PER_FILE - 1 {
+            self.0.exclusive().unwrap().switch().unwrap();
+        } else {
+            self.0.writer().unwrap().write(&record(data)).unwrap();
+        }
+    }
+}
+
+fn open_queue( name: &str ) -> impl LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> + Send + Sync {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("concurrent_read").join(name);
+    if root.exists() { remove_dir_all(&root).unwrap(); }
+    create_dir_all(&root).unwrap();
+
+    let conf: LogQueueConf<LogQueueFileNumID, PathBuf, FileBuff, _, _, _, _> = LogQueueConf {
+        find_files: FsLogFind::new(root.to_str().unwrap(), "*.binlog", true).unwrap(),
+        open_log_file: LogQueueFileNumIDOpen,
+        validate: ValidateStub,
+        new_file: path_template(root.to_str().unwrap(), "${root}/${time:local:yyyy-mm-ddThh-mi-ss}-${rnd:5}.binlog").unwrap(),
+        _p: PhantomData,
+    };
+    conf.open().unwrap()
+}
+
+/// Замер: `readers` потоков читают, один поток пишет
+///
+/// Результат - (чтений в секунду, записей в секунду)
+fn measure<B: Bench>( bench: B, readers: usize, duration: Duration ) -> (f64, f64) {
+    let data = vec![7u8; 256];
+    for i in 0..1000 {
+        bench.write(i, &data);
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let reads = Arc::new(AtomicU64::new(0));
+
+    let threads: Vec<_> = (0..readers).map(|r| {
+        let bench = bench.clone();
+        let stop = stop.clone();
+        let reads = reads.clone();
+        thread::spawn(move || {
+            let mut n = r as u32;
+            let mut count = 0u64;
+            while !stop.load(Ordering::Relaxed) {
+                bench.read(n);
+                n = n.wrapping_mul(1103515245).wrapping_add(12345);
+                count += 1;
+            }
+            reads.fetch_add(count, Ordering::Relaxed);
+        })
+    }).collect();
+
+    let started = Instant::now();
+    let mut writes = 0u64;
+    while started.elapsed() < duration {
+        bench.write(1000 + writes, &data);
+        writes += 1;
+    }
+    stop.store(true, Ordering::Relaxed);
+    for t in threads { t.join().unwrap(); }
+
+    let secs = started.elapsed().as_secs_f64();
+    (reads.load(Ordering::Relaxed) as f64 / secs, writes as f64 / secs)
+}
+
+fn main() {
+    let readers: Vec<usize> = std::env::var("BENCH_READERS").unwrap_or("1,2,4,8".to_string())
+        .split(',').map(|s| s.trim().parse().unwrap()).collect();
+    let duration = Duration::from_secs_f64(
+        std::env::var("BENCH_SECONDS").ok().and_then(|s| s.parse().ok()).unwrap_or(2.0)
+    );
+
+    println!("{:<8} {:>8} {:>14} {:>14}", "queue", "readers", "reads/s", "writes/s");
+    for &n in &readers {
+        let mutex = MutexQueue(Arc::new(Mutex::new(open_queue(&format!("mutex-{n}")))));
+        let (r, w) = measure(mutex.clone(), n, duration);
+        println!("{:<8} {:>8} {:>14.0} {:>14.0}", mutex.name(), n, r, w);
+
+        let shared = Shared(SharedLogQueue::new(open_queue(&format!("shared-{n}"))));
+        let (r, w) = measure(shared.clone(), n, duration);
+        println!("{:<8} {:>8} {:>14.0} {:>14.0}", shared.name(), n, r, w);
+    }
+}
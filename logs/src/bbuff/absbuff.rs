@@ -293,21 +293,35 @@ impl WriteBytesTo for FileBuff {
 }
 
 impl ReadBytesFrom for FileBuff {
+    /// Чтение выполняется без перемещения позиции файла,
+    /// поэтому достаточно блокировки на чтение - читатели не блокируют друг друга
     fn read_from(&self, pos: u64, data_consumer: &mut [u8]) -> Result<u64, ABuffError> {
-        let mut file = self.file.write()?;
+        let file = self.file.read()?;
 
         let file_len = file.metadata()?.len();
         if pos > file_len {
             return Ok(0);
         }
 
-        file.seek(SeekFrom::Start(pos as u64))?;
-
-        let reads = file.read(data_consumer)?;
+        let reads = read_at(&file, pos, data_consumer)?;
         Ok(reads as u64)
     }
 }
 
+/// Чтение из указанной позиции файла
+#[cfg(unix)]
+fn read_at(file: &File, pos: u64, data_consumer: &mut [u8]) -> std::io::Result<usize> {
+    use std::os::unix::fs::FileExt;
+    file.read_at(data_consumer, pos)
+}
+
+/// Чтение из указанной позиции файла
+#[cfg(windows)]
+fn read_at(file: &File, pos: u64, data_consumer: &mut [u8]) -> std::io::Result<usize> {
+    use std::os::windows::fs::FileExt;
+    file.seek_read(data_consumer, pos)
+}
+
 impl BytesCount for FileBuff {
     fn bytes_count(&self) -> Result<u64, ABuffError> {
         let file = self.file.read()?;
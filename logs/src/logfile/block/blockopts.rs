@@ -7,10 +7,16 @@ use super::{BlockErr, String16, String32};
 /// Опции блока
 ///
 /// Представляет из себя пары ключ/значение
+///
+/// Порядок ключей сохраняется - порядок добавления или порядок в прочитанном блоке,
+/// в этом же порядке опции записываются
 #[derive(Clone, Debug)]
 pub struct BlockOptions {
     // Значения - пары ключ/значение
     pub values: Box<HashMap<String16, String32>>,
+
+    // Порядок ключей
+    order: Vec<String16>,
 }
 
 impl BlockOptions {
@@ -25,10 +31,16 @@ impl BlockOptions {
     ) -> Result<(), BlockErr> {
         let key: String16 = key.try_into()?;
         let value: String32 = value.try_into()?;
-        self.values.insert(key, value);
+        self.put(key, value);
         Ok(())
     }
 
+    fn put(&mut self, key: String16, value: String32) {
+        if self.values.insert(key.clone(), value).is_none() {
+            self.order.push(key);
+        }
+    }
+
     pub fn get<K: TryInto<String16, Error = BlockErr>>(&self, key: K) -> Option<String32> {
         let key_result = TryInto::<String16>::try_into(key);
         match key_result {
@@ -38,12 +50,13 @@ impl BlockOptions {
     }
 
     pub fn keys(&self) -> Vec<String16> {
-        let res: Vec<String16> = self.values.keys().into_iter().map(|v| v.clone()).collect();
-        res
+        self.order.clone()
     }
 
     pub fn entries(&self) -> Vec<(String16, String32)> {
-        let res: Vec<(String16, String32)> = self.values.clone().into_iter().collect();
+        let res: Vec<(String16, String32)> = self.order.iter()
+            .filter_map(|k| self.values.get(k).map(|v| (k.clone(), v.clone())))
+            .collect();
         res
     }
 
@@ -53,13 +66,17 @@ impl BlockOptions {
     ) -> Option<(String16, String32)> {
         let key_result = TryInto::<String16>::try_into(key);
         match key_result {
-            Ok(key) => self.values.remove(&key).map(|v| (key, v)),
+            Ok(key) => {
+                self.order.retain(|k| *k != key);
+                self.values.remove(&key).map(|v| (key, v))
+            },
             Err(_) => None,
         }
     }
 
     pub fn clear(&mut self) {
-        self.values.clear()
+        self.values.clear();
+        self.order.clear();
     }
 }
 
@@ -100,6 +117,7 @@ impl Default for BlockOptions {
     fn default() -> Self {
         BlockOptions {
             values: Box::new(HashMap::<String16, String32>::new()),
+            order: Vec::new(),
         }
     }
 }
@@ -107,7 +125,7 @@ impl Default for BlockOptions {
 impl ByteWriter<&BlockOptions> for ByteBuff {
     fn write(&mut self, options: &BlockOptions) {
         self.write(options.values.len());
-        for (key, value) in options.values.clone().into_iter() {
+        for (key, value) in options.entries() {
             self.write(key);
             self.write(value);
         }
@@ -127,7 +145,7 @@ impl ByteReader<BlockOptions> for ByteBuff {
             let mut value = String32::default();
             self.read(&mut key)?;
             self.read(&mut value)?;
-            target.values.insert(key, value);
+            target.put(key, value);
         }
 
         Ok(())
@@ -158,6 +176,27 @@ fn test_store_restore() {
     }
 }
 
+#[test]
+fn test_store_ordered() {
+    let mut opts = BlockOptions::default();
+    for n in 0..20 {
+        opts.set(format!("key{}", 19 - n), n.to_string()).unwrap();
+    }
+    opts.delete("key5");
+
+    // прочитанные опции записываются в том же порядке
+    let mut bbuf1 = ByteBuff::new();
+    bbuf1.write(&opts);
+    let mut restored = BlockOptions::default();
+    bbuf1.position = 0;
+    bbuf1.read(&mut restored).unwrap();
+    assert_eq!(restored.keys(), opts.keys());
+
+    let mut bbuf2 = ByteBuff::new();
+    bbuf2.write(&restored);
+    assert_eq!(bbuf1.buff, bbuf2.buff);
+}
+
 impl From<BlockOptions> for HashMap<String,String> {
     fn from(value: BlockOptions) -> Self {
         let mut map = HashMap::<String,String>::new();
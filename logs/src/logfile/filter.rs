@@ -0,0 +1,414 @@
+//! Фильтр блоков по метаданным
+//!
+//! Выражение проверяет опции блока, время записи (опция [BLOCK_TIME_KEY]) и размер данных
+//!
+//! Синтаксис
+//!
+//! ```text
+//! filter     ::= or_expr
+//! or_expr    ::= and_expr { 'or' and_expr }
+//! and_expr   ::= not_expr { 'and' not_expr }
+//! not_expr   ::= 'not' not_expr | '(' or_expr ')' | condition
+//! condition  ::= size_cond | time_cond | option_cond
+//!
+//! size_cond  ::= 'size' cmp_op byte_size
+//! byte_size  ::= dec_number [ 'b' | 'k' | 'kb' | 'm' | 'mb' | 'g' | 'gb' ]
+//!
+//! time_cond  ::= 'time' ( '<' | '<=' | '>' | '>=' ) time_value
+//! time_value ::= rfc3339 | date | 'now' | 'now-' duration
+//!
+//! option_cond ::= key ( '=' | '!=' ) value
+//!
+//! cmp_op     ::= '<' | '<=' | '>' | '>=' | '=' | '!='
+//! value      ::= word | '"' { char } '"' | "'" { char } "'"
+//! ```
+//!
+//! - `size` - размер данных блока, суффиксы кратны 1024
+//! - `time` - время записи, блоки без опции `time` не проходят условие;
+//!   `date` (`2023-07-21`) - начало дня UTC, `duration` - см. [parse::DurationParser] (`now-10min`)
+//! - `key=value` - значение опции совпадает с шаблоном [parse::Wildcard] (`*`, `?`),
+//!   блоки без опции не проходят условие; `key!=value` - отрицание `key=value`
+//! - ключевые слова `and`, `or`, `not` без учета регистра
+//!
+//! Пример
+//!
+//! ```text
+//! mime=text/* and size>1kb and time>=now-1hour
+//! not (source='batch import' or size=0)
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use parse::{DurationParser, Parser, Wildcard, WildcardParser};
+
+use super::block::BlockOptions;
+use super::{parse_block_time, BLOCK_TIME_KEY};
+
+/// Операция сравнения
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CmpOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl CmpOp {
+    fn test<T: PartialOrd>( &self, a: T, b: T ) -> bool {
+        match self {
+            Self::Lt => a < b,
+            Self::Le => a <= b,
+            Self::Gt => a > b,
+            Self::Ge => a >= b,
+            Self::Eq => a == b,
+            Self::Ne => a != b,
+        }
+    }
+}
+
+/// Время в условии
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterTime {
+    /// Конкретное время
+    At(DateTime<FixedOffset>),
+
+    /// Текущее время минус продолжительность
+    Ago(Duration),
+}
+
+impl FilterTime {
+    fn resolve( &self, now: DateTime<FixedOffset> ) -> DateTime<FixedOffset> {
+        match self {
+            Self::At(t) => *t,
+            Self::Ago(d) => now - chrono::Duration::from_std(*d).unwrap_or(chrono::Duration::zero()),
+        }
+    }
+}
+
+/// Фильтр блоков
+#[derive(Debug, Clone)]
+pub enum BlockFilter {
+    And(Box<BlockFilter>, Box<BlockFilter>),
+    Or(Box<BlockFilter>, Box<BlockFilter>),
+    Not(Box<BlockFilter>),
+
+    /// Значение опции совпадает с шаблоном
+    Option { key: String, pattern: Wildcard },
+
+    /// Размер данных
+    Size { op: CmpOp, bytes: u64 },
+
+    /// Время записи
+    Time { op: CmpOp, time: FilterTime },
+}
+
+/// Ошибка разбора фильтра
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterErr {
+    /// Позиция (в символах) в исходном тексте
+    pub position: usize,
+
+    /// Описание
+    pub message: String,
+}
+
+impl fmt::Display for FilterErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "filter error at {}: {}", self.position, self.message)
+    }
+}
+
+impl BlockFilter {
+    /// Проверка блока, текущее время - системное
+    ///
+    /// Аргументы
+    /// - `options` - опции блока
+    /// - `data_size` - размер данных блока
+    pub fn matches( &self, options: &BlockOptions, data_size: u64 ) -> bool {
+        self.matches_at(options, data_size, Utc::now().fixed_offset())
+    }
+
+    /// Проверка блока относительно указанного текущего времени
+    pub fn matches_at( &self, options: &BlockOptions, data_size: u64, now: DateTime<FixedOffset> ) -> bool {
+        match self {
+            Self::And(a, b) => a.matches_at(options, data_size, now) && b.matches_at(options, data_size, now),
+            Self::Or(a, b) => a.matches_at(options, data_size, now) || b.matches_at(options, data_size, now),
+            Self::Not(a) => !a.matches_at(options, data_size, now),
+            Self::Option { key, pattern } => options.get(key.as_str())
+                .map(|value| pattern.test(value.value()))
+                .unwrap_or(false),
+            Self::Size { op, bytes } => op.test(data_size, *bytes),
+            Self::Time { op, time } => options.get(BLOCK_TIME_KEY)
+                .and_then(|value| parse_block_time(value.value()))
+                .map(|t| op.test(t, time.resolve(now)))
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl FromStr for BlockFilter {
+    type Err = FilterErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut p = FilterParser { chars: s.chars().collect(), pos: 0 };
+        let filter = p.or_expr()?;
+        p.skip_ws();
+        if p.pos < p.chars.len() {
+            return Err(p.err(format!("unexpected {:?}", p.rest())));
+        }
+        Ok(filter)
+    }
+}
+
+/// Разбор выражения фильтра, рекурсивный спуск
+struct FilterParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl FilterParser {
+    fn err( &self, message: String ) -> FilterErr {
+        FilterErr { position: self.pos, message }
+    }
+
+    fn rest( &self ) -> String {
+        self.chars[self.pos..].iter().collect()
+    }
+
+    fn skip_ws( &mut self ) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek( &self ) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    /// Ключевое слово, за которым следует пробел, скобка или конец
+    fn keyword( &mut self, word: &str ) -> bool {
+        self.skip_ws();
+        let end = self.pos + word.chars().count();
+        if end > self.chars.len() { return false; }
+        let text: String = self.chars[self.pos..end].iter().collect();
+        if !text.eq_ignore_ascii_case(word) { return false; }
+        match self.chars.get(end) {
+            None => {},
+            Some(c) if c.is_whitespace() || *c == '(' || *c == ')' => {},
+            _ => return false,
+        }
+        self.pos = end;
+        true
+    }
+
+    fn symbol( &mut self, sym: char ) -> bool {
+        self.skip_ws();
+        if self.peek() == Some(sym) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or_expr( &mut self ) -> Result<BlockFilter, FilterErr> {
+        let mut left = self.and_expr()?;
+        while self.keyword("or") {
+            let right = self.and_expr()?;
+            left = BlockFilter::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn and_expr( &mut self ) -> Result<BlockFilter, FilterErr> {
+        let mut left = self.not_expr()?;
+        while self.keyword("and") {
+            let right = self.not_expr()?;
+            left = BlockFilter::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn not_expr( &mut self ) -> Result<BlockFilter, FilterErr> {
+        if self.keyword("not") {
+            return Ok(BlockFilter::Not(Box::new(self.not_expr()?)));
+        }
+        if self.symbol('(') {
+            let expr = self.or_expr()?;
+            if !self.symbol(')') {
+                return Err(self.err("expect )".to_string()));
+            }
+            return Ok(expr);
+        }
+        self.condition()
+    }
+
+    fn key( &mut self ) -> Result<String, FilterErr> {
+        self.skip_ws();
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '-' || c == '.' || c == ':' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if start == self.pos {
+            return Err(self.err(format!("expect option key, size or time, found {:?}", self.rest())));
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn cmp_op( &mut self ) -> Result<CmpOp, FilterErr> {
+        self.skip_ws();
+        let two: String = self.chars[self.pos..(self.pos + 2).min(self.chars.len())].iter().collect();
+        let (op, len) = match (two.as_str(), self.peek()) {
+            ("<=", _) => (CmpOp::Le, 2),
+            (">=", _) => (CmpOp::Ge, 2),
+            ("!=", _) => (CmpOp::Ne, 2),
+            (_, Some('<')) => (CmpOp::Lt, 1),
+            (_, Some('>')) => (CmpOp::Gt, 1),
+            (_, Some('=')) => (CmpOp::Eq, 1),
+            _ => return Err(self.err(format!("expect comparison, found {:?}", self.rest()))),
+        };
+        self.pos += len;
+        Ok(op)
+    }
+
+    /// Значение: слово до пробела или скобки, либо строка в кавычках
+    fn value( &mut self ) -> Result<String, FilterErr> {
+        self.skip_ws();
+        match self.peek() {
+            Some(q) if q == '"' || q == '\'' => {
+                let start = self.pos;
+                self.pos += 1;
+                let mut text = String::new();
+                loop {
+                    match self.peek() {
+                        None => return Err(FilterErr { position: start, message: "unterminated string".to_string() }),
+                        Some(c) if c == q => { self.pos += 1; break; },
+                        Some(c) => { text.push(c); self.pos += 1; }
+                    }
+                }
+                Ok(text)
+            },
+            _ => {
+                let start = self.pos;
+                while let Some(c) = self.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' { break; }
+                    self.pos += 1;
+                }
+                if start == self.pos {
+                    return Err(self.err("expect value".to_string()));
+                }
+                Ok(self.chars[start..self.pos].iter().collect())
+            }
+        }
+    }
+
+    fn condition( &mut self ) -> Result<BlockFilter, FilterErr> {
+        let key = self.key()?;
+        let op_pos = self.pos;
+        let op = self.cmp_op()?;
+        let value_pos = self.pos;
+        let value = self.value()?;
+        let value_err = |message: String| FilterErr { position: value_pos, message };
+
+        if key.eq_ignore_ascii_case("size") {
+            let bytes = parse_size(&value).map_err(value_err)?;
+            return Ok(BlockFilter::Size { op, bytes });
+        }
+
+        if key == BLOCK_TIME_KEY && !matches!(op, CmpOp::Eq | CmpOp::Ne) {
+            let time = parse_time(&value).map_err(value_err)?;
+            return Ok(BlockFilter::Time { op, time });
+        }
+
+        let pattern = match WildcardParser::new().parse(&value) {
+            Some((pattern, _)) => pattern,
+            None => return Err(value_err(format!("can't parse pattern {value}"))),
+        };
+        let cond = BlockFilter::Option { key, pattern };
+        match op {
+            CmpOp::Eq => Ok(cond),
+            CmpOp::Ne => Ok(BlockFilter::Not(Box::new(cond))),
+            _ => Err(FilterErr { position: op_pos, message: "option supports only = and !=".to_string() }),
+        }
+    }
+}
+
+/// Размер: число и необязательный суффикс `b`, `k(b)`, `m(b)`, `g(b)`
+fn parse_size( text: &str ) -> Result<u64, String> {
+    let lower = text.to_ascii_lowercase();
+    let digits = lower.chars().take_while(|c| c.is_ascii_digit()).count();
+    let (num, suffix) = lower.split_at(digits);
+    let num = num.parse::<u64>().map_err(|_| format!("can't parse size {text}"))?;
+    let mult = match suffix {
+        "" | "b" => 1,
+        "k" | "kb" => 1024,
+        "m" | "mb" => 1024 * 1024,
+        "g" | "gb" => 1024 * 1024 * 1024,
+        _ => return Err(format!("unknown size suffix {suffix}, expect b, kb, mb or gb")),
+    };
+    num.checked_mul(mult).ok_or_else(|| format!("size {text} too large"))
+}
+
+/// Время: RFC 3339, дата (начало дня UTC), `now`, `now-{duration}`
+fn parse_time( text: &str ) -> Result<FilterTime, String> {
+    if text.eq_ignore_ascii_case("now") {
+        return Ok(FilterTime::Ago(Duration::ZERO));
+    }
+    if let Some(ago) = text.strip_prefix("now-") {
+        return match DurationParser.parse(ago) {
+            Some((d, cc)) if cc.0 == ago.chars().count() => Ok(FilterTime::Ago(d)),
+            _ => Err(format!("can't parse duration {ago}")),
+        };
+    }
+    if let Ok(t) = DateTime::parse_from_rfc3339(text) {
+        return Ok(FilterTime::At(t));
+    }
+    if let Ok(d) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Ok(FilterTime::At(d.and_hms_opt(0, 0, 0).unwrap().and_utc().fixed_offset()));
+    }
+    Err(format!("can't parse time {text}, expect rfc3339, yyyy-mm-dd, now or now-duration"))
+}
+
+#[test]
+fn test_block_filter() {
+    let mut opts = BlockOptions::default();
+    opts.set("mime", "text/plain").unwrap();
+    opts.set("source", "batch import").unwrap();
+    opts.set(BLOCK_TIME_KEY, "2023-07-21T12:00:00+00:00").unwrap();
+    let now = DateTime::parse_from_rfc3339("2023-07-21T12:30:00+00:00").unwrap();
+
+    let test = |expr: &str, size: u64| expr.parse::<BlockFilter>().unwrap().matches_at(&opts, size, now);
+
+    assert!(test("mime=text/*", 10));
+    assert!(!test("mime=image/*", 10));
+    assert!(test("mime!=image/*", 10));
+    assert!(!test("missing=*", 10));
+    assert!(test("source='batch import'", 10));
+    assert!(test("size>1kb", 2000));
+    assert!(!test("size>1kb", 1024));
+    assert!(test("size<=1k and mime=text/*", 1024));
+    assert!(test("time>=2023-07-21", 0));
+    assert!(test("time>now-1hour and time<now", 0));
+    assert!(!test("time>now-10min", 0));
+    assert!(test("time=2023-07-21T*", 0));
+    assert!(test("not (size=0 or mime=image/*)", 5));
+    assert!(test("size=0 OR mime=text/* AND size>1", 5));
+
+    let err = "mime=text/* and".parse::<BlockFilter>().err().unwrap();
+    assert_eq!(err.position, 15);
+    assert!("size>lots".parse::<BlockFilter>().is_err());
+    assert!("size>17179869184gb".parse::<BlockFilter>().is_err());
+    assert!("size>18446744073709551615".parse::<BlockFilter>().is_ok());
+    assert!("mime>text".parse::<BlockFilter>().is_err());
+    assert!("(size>1".parse::<BlockFilter>().is_err());
+    assert!("source='x".parse::<BlockFilter>().is_err());
+}
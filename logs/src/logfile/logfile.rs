@@ -51,6 +51,7 @@ use crate::perf::{Metrics, Tracker};
 use super::super::bbuff::absbuff::*;
 use super::super::perf::Counters;
 use super::block::*;
+use super::{OffsetIndex, OptionIndex};
 use std::fmt::{self, Debug};
 use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::time::Instant;
@@ -66,6 +67,8 @@ where
     buff: B,
     last_blocks: Arc<RwLock<Vec<BlockHeadRead>>>,
     block_buff: streambuff::ByteBuff,
+    option_index: Arc<RwLock<Option<OptionIndex>>>,
+    offset_index: Arc<RwLock<OffsetIndex>>,
     pub counters: Arc<RwLock<Counters>>,
     pub tracker: Arc<Tracker>,
 }
@@ -147,6 +150,12 @@ pub enum LogErr {
     FlatBuff(ABuffError),
     Block(BlockErr),
     LogIsEmpty,
+
+    /// Идентификатор записываемого блока не совпадает со следующим блоком лога
+    BlockIdNotMatched {
+        expected:BlockId,
+        actual:BlockId
+    },
 }
 
 impl From<ABuffError> for LogErr {
@@ -189,6 +198,8 @@ where
                 counters: Arc::new(RwLock::new(Counters::new())),
                 tracker: Arc::new(Tracker::new()),
                 block_buff: streambuff::ByteBuff::new(),
+                option_index: Arc::new(RwLock::new(None)),
+                offset_index: Arc::new(RwLock::new(OffsetIndex::new())),
             });
         }
 
@@ -204,6 +215,8 @@ where
             counters: Arc::new(RwLock::new(Counters::new())),
             tracker: Arc::new(Tracker::new()),
             block_buff: streambuff::ByteBuff::new(),
+            option_index: Arc::new(RwLock::new(None)),
+            offset_index: Arc::new(RwLock::new(OffsetIndex::new())),
         })
     }
 
@@ -256,6 +269,15 @@ where
 
         let t1 = Instant::now();
 
+        {
+            // индекс смещений дополняется только если он полный,
+            // иначе он будет достроен при чтении
+            let mut offset_index = self.offset_index.write()?;
+            if offset_index.len() == writed_block.head.block_id.value() as usize {
+                offset_index.push(writed_block.position)?;
+            }
+        }
+
         {
             let mut last_blocks = self.last_blocks.write()?;
             if is_empty {
@@ -618,27 +640,182 @@ where
         }
     }
 
+    /// Проверка блоков проходом от начала лог файла
+    ///
+    /// Аргументы
+    /// - `read_data` - читать блоки целиком, иначе только заголовки
+    ///
+    /// Проверяется, что заголовки (блоки) читаются, идентификаторы блоков идут подряд с 0
+    /// и последний блок заканчивается в конце файла
+    ///
+    /// Результат - кол-во проверенных блоков,
+    /// ошибка - (позиция блока, ожидаемый идентификатор блока, ошибка)
+    pub fn verify_blocks(&self, read_data: bool) -> Result<u32, (u64, BlockId, LogErr)> {
+        let size = self.buff.bytes_count().map_err(|err| (0u64, BlockId::new(0), LogErr::from(err)))?;
+        self.verify_blocks_until(read_data, size)
+    }
+
+    /// Проверка блоков в первых `size` байтах лог файла, см. [LogFile::verify_blocks]
+    ///
+    /// Блоки, записанные после получения размера `size`, не проверяются
+    pub fn verify_blocks_until(&self, read_data: bool, size: u64) -> Result<u32, (u64, BlockId, LogErr)> {
+        let mut position = 0u64;
+        let mut expected = 0u32;
+
+        while position < size {
+            let fail = |err: LogErr| (position, BlockId::new(expected), err);
+            let (block_id, next) = if read_data {
+                let (block, next) = self.read_block_at(position).map_err(fail)?;
+                (block.head.block_id, next)
+            } else {
+                let head = self.read_head_at(position).map_err(fail)?;
+                (head.head.block_id, position + head.block_size())
+            };
+
+            if block_id != BlockId::new(expected) {
+                return Err(fail(LogErr::BlockIdNotMatched { expected: BlockId::new(expected), actual: block_id }));
+            }
+            if next > size {
+                return Err(fail(LogErr::FlatBuff(ABuffError::Generic(format!("block ends at {next} beyond end of file {size}")))));
+            }
+
+            position = next;
+            expected += 1;
+        }
+
+        Ok(expected)
+    }
+
     /// Получение блока по id
     pub fn read_block(&self, block_id: BlockId) -> Result<Block,LogErr> {
-        let mut ptr = Arc::new(RwLock::new(self.clone())).pointer_to_end()?;
-        ptr = ptr.jump(block_id)?;
-        
-        let head = ptr.current_head().clone();
-        let data = ptr.current_data()?;
-        Ok(Block { head: head.head, data: data })
+        let position = self.block_position(block_id)?;
+        let (block, _) = self.read_block_at(position)?;
+        Ok(block)
     }
 
     /// Чтение заголовка блока по id
     pub fn read_block_header(&self, block_id: BlockId) -> Result<BlockHeadRead, LogErr> {
-        let mut ptr = Arc::new(RwLock::new(self.clone())).pointer_to_end()?;
-        ptr = ptr.jump(block_id)?;
-        
-        let head = ptr.current_head().clone();
-        Ok(head)
+        let position = self.block_position(block_id)?;
+        self.read_head_at(position)
+    }
+
+    /// Получение смещения блока по id
+    /// 
+    /// Смещение берется из [индекса смещений](OffsetIndex),
+    /// если индекс не полный, то он достраивается проходом вперед от последнего известного блока
+    pub fn block_position(&self, block_id: BlockId) -> Result<FileOffset, LogErr> {
+        {
+            let offset_index = self.offset_index.read()?;
+            if let Some(position) = offset_index.get(block_id) {
+                return Ok(position);
+            }
+        }
+
+        let last = { self.last_blocks.read()?.first().cloned() };
+        let last = match last {
+            Some(last) => last,
+            None => return Err(LogErr::LogIsEmpty)
+        };
+
+        let out_of_range = LogErr::JumpOutsideLast { last: last.head.block_id, to: block_id };
+        if block_id > last.head.block_id {
+            return Err(out_of_range);
+        }
+
+        let mut offset_index = self.offset_index.write()?;
+        self.fill_offset_index(&mut offset_index, &last)?;
+        offset_index.get(block_id).ok_or(out_of_range)
+    }
+
+    /// Достраивание индекса смещений до указанного блока
+    fn fill_offset_index(&self, offset_index: &mut OffsetIndex, last: &BlockHeadRead) -> Result<(), LogErr> {
+        let mut head = match offset_index.last() {
+            None => self.read_head_at(0u64)?,
+            Some((_, position)) => {
+                let head = self.read_head_at(position)?;
+                match self.read_next_head(&head)? {
+                    Some(next) => next,
+                    None => return Ok(())
+                }
+            }
+        };
+
+        loop {
+            offset_index.push(head.position)?;
+            if head.head.block_id >= last.head.block_id {
+                break;
+            }
+            match self.read_next_head(&head)? {
+                Some(next) => head = next,
+                None => break
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Подключение индекса смещений блоков
+    /// 
+    /// Индекс проверяется выборочно: смещения должны возрастать, а первая, средняя и последняя записи
+    /// ([OffsetIndex::samples]) - указывать на заголовки блоков с теми же идентификаторами.
+    /// Если индекс не соответствует логу, то он строится заново;
+    /// лишние записи отбрасываются, недостающие записи добавляются в индекс.
+    pub fn attach_offset_index(&self, mut index: OffsetIndex) -> Result<(), LogErr> {
+        let count = self.count()?;
+        index.truncate(count as usize)?;
+
+        let valid = index.is_increasing() && index.samples().into_iter().all(|(block_id, position)|
+            self.read_head_at(position)
+                .map(|head| head.head.block_id == block_id && head.position == position)
+                .unwrap_or(false)
+        );
+        if !valid {
+            index.truncate(0)?;
+        }
+
+        let last = { self.last_blocks.read()?.first().cloned() };
+        if let Some(last) = last {
+            self.fill_offset_index(&mut index, &last)?;
+        }
+
+        let mut cur_index = self.offset_index.write()?;
+        *cur_index = index;
+        Ok(())
     }
 
     /// Добавление данных в лог
     pub fn write_block(&mut self, block_opt: &BlockOptions, data: &[u8]) -> Result<BlockId, LogErr> {
+        self.append_data(DataId::user_data(), block_opt, data)
+    }
+
+    /// Добавление данных в лог с указанным типом данных
+    pub fn write_data(&mut self, data_id: DataId, block_opt: &BlockOptions, data: &[u8]) -> Result<BlockId, LogErr> {
+        self.append_data(data_id, block_opt, data)
+    }
+
+    /// Добавление блока в исходном виде
+    /// 
+    /// Идентификатор блока и тип данных сохраняются, 
+    /// ссылки на предыдущие блоки формируются заново - 
+    /// если лог совпадает с логом источника, то записанные байты совпадут с байтами блока источника.
+    /// 
+    /// Идентификатор блока должен быть следующим в логе, иначе [LogErr::BlockIdNotMatched]
+    pub fn write_raw_block(&mut self, block: &Block) -> Result<BlockId, LogErr> {
+        let expected = {
+            let last_blocks = self.last_blocks.read()?;
+            last_blocks.first()
+                .map(|last| BlockId::new(last.head.block_id.value() + 1))
+                .unwrap_or(BlockId::new(0))
+        };
+        if block.head.block_id != expected {
+            return Err(LogErr::BlockIdNotMatched { expected, actual: block.head.block_id });
+        }
+
+        self.append_data(block.head.data_type_id, &block.head.block_options, &block.data)
+    }
+
+    /// Добавление данных в лог с указанным типом данных
+    fn append_data(&mut self, data_id: DataId, block_opt: &BlockOptions, data: &[u8]) -> Result<BlockId, LogErr> {
         {
             let mut metric = self.counters.write()?;
             metric.inc("append_data");
@@ -650,7 +827,7 @@ where
 
         let block = tracker.track("append_data/build_next_block", || {
             self.build_next_block(
-                DataId::user_data(),
+                data_id,
                 block_opt,
                 data,
                 &tracker.sub_tracker("append_data/build_next_block/"),
@@ -660,6 +837,15 @@ where
 
         let _res = res?;
 
+        {
+            let mut index = self.option_index.write()?;
+            if let Some(index) = index.as_mut() {
+                tracker.track("append_data/option_index", || {
+                    index.add(block.head.block_id, block_opt)
+                })?;
+            }
+        }
+
         {
             self.counters.write()?.inc("append_data.succ");
         }
@@ -685,6 +871,217 @@ where
         let size = self.buff.bytes_count()?;
         Ok(size)
     }
+
+    /// Подключение вторичного индекса по опциям блока
+    /// 
+    /// Блоки, которые есть в логе, но отсутствуют в индексе, добавляются в индекс.
+    /// Если индекс содержит блоки которых нет в логе, то индекс перестраивается,
+    /// sidecar файл индекса перезаписывается.
+    pub fn attach_option_index(&self, mut index: OptionIndex) -> Result<(), LogErr> {
+        let count = self.count()?;
+
+        let from = match index.last_block() {
+            Some(b_id) if b_id.value() < count => b_id.value() + 1,
+            Some(_) => {
+                index.clear()?;
+                0
+            },
+            None => 0
+        };
+
+        if from < count {
+            let mut ptr = Arc::new(RwLock::new(self.clone())).pointer_to_end()?;
+            ptr = ptr.jump(BlockId::new(from))?;
+            loop {
+                let head = ptr.current_head();
+                index.add(head.head.block_id, &head.head.block_options)?;
+                match ptr.next() {
+                    Ok(next) => ptr = next,
+                    Err(LogErr::NextBlockNotExists(_)) => break,
+                    Err(err) => return Err(err)
+                }
+            }
+        }
+
+        let mut cur_index = self.option_index.write()?;
+        *cur_index = Some(index);
+        Ok(())
+    }
+
+    /// Поиск блоков по значению опции
+    /// 
+    /// Если ключ присуствует во вторичном индексе, то используется индекс,
+    /// иначе выполняется полный просмотр лога
+    /// 
+    /// Результат - идентификаторы блоков по возрастанию
+    pub fn find_by_option(&self, key: &str, value: &str) -> Result<Vec<BlockId>, LogErr> {
+        {
+            let index = self.option_index.read()?;
+            if let Some(index) = index.as_ref() {
+                if index.is_indexed(key) {
+                    return Ok(index.find(key, value));
+                }
+            }
+        }
+
+        let mut res = Vec::<BlockId>::new();
+        let mut ptr = match Arc::new(RwLock::new(self.clone())).pointer_to_end() {
+            Ok(ptr) => ptr,
+            Err(LogErr::LogIsEmpty) => return Ok(res),
+            Err(err) => return Err(err)
+        };
+
+        loop {
+            let head = ptr.current_head();
+            if head.head.block_options.get(key).map(|v| v.value() == value).unwrap_or(false) {
+                res.push(head.head.block_id);
+            }
+            match ptr.previous() {
+                Ok(prev) => ptr = prev,
+                Err(LogErr::PreviousBlockNotExists(_)) => break,
+                Err(err) => return Err(err)
+            }
+        }
+
+        res.reverse();
+        Ok(res)
+    }
+}
+
+#[test]
+fn test_offset_index() {
+    let bb = ByteBuff::new_empty_unlimited();
+    let mut log = LogFile::new(bb.clone()).unwrap();
+
+    for n in 0u8..20 {
+        log.write_block(&BlockOptions::default(), &[n]).unwrap();
+    }
+
+    // индекс строится при чтении
+    let log = LogFile::new(bb.clone()).unwrap();
+    let block = log.read_block(BlockId::new(5)).unwrap();
+    assert_eq!(*block.data, vec![5u8]);
+
+    // прыжок вперед
+    let ptr = Arc::new(RwLock::new(log.clone())).pointer_to_end().unwrap();
+    let ptr = ptr.jump(BlockId::new(2)).unwrap();
+    let ptr = ptr.jump(BlockId::new(17)).unwrap();
+    assert_eq!(*ptr.current_data().unwrap(), vec![17u8]);
+    assert_eq!(ptr.current_data_prefix(0).unwrap(), Vec::<u8>::new());
+    assert_eq!(ptr.current_data_prefix(10).unwrap(), vec![17u8]);
+    assert!(matches!(ptr.jump(BlockId::new(20)), Err(LogErr::JumpOutsideLast { .. })));
+
+    // не корректный индекс отбрасывается
+    let mut bad_index = OffsetIndex::new();
+    bad_index.push(FileOffset::new(0)).unwrap();
+    bad_index.push(FileOffset::new(1)).unwrap();
+    log.attach_offset_index(bad_index).unwrap();
+    let block = log.read_block(BlockId::new(19)).unwrap();
+    assert_eq!(*block.data, vec![19u8]);
+    assert_eq!(log.block_position(BlockId::new(1)).unwrap(), log.read_block_header(BlockId::new(1)).unwrap().position);
+
+    // последняя запись верна, но средняя - нет (индекс от другого лога)
+    let position = |id: u32| log.read_block_header(BlockId::new(id)).unwrap().position;
+    let mut stale_index = OffsetIndex::new();
+    for id in 0..20u32 {
+        stale_index.push(if id == 10 { FileOffset::new(position(10).value() + 1) } else { position(id) }).unwrap();
+    }
+    log.attach_offset_index(stale_index).unwrap();
+    assert_eq!(log.block_position(BlockId::new(10)).unwrap(), position(10));
+    assert_eq!(*log.read_block(BlockId::new(10)).unwrap().data, vec![10u8]);
+}
+
+#[test]
+fn test_find_by_option() {
+    let bb = ByteBuff::new_empty_unlimited();
+    let mut log = LogFile::new(bb).unwrap();
+
+    for n in 0u8..10 {
+        let mut opts = BlockOptions::default();
+        opts.set("parity", if n % 2 == 0 { "even" } else { "odd" }).unwrap();
+        log.write_block(&opts, &[n]).unwrap();
+    }
+
+    let scan = log.find_by_option("parity", "odd").unwrap();
+    assert_eq!(scan.iter().map(|b| b.value()).collect::<Vec<_>>(), vec![1,3,5,7,9]);
+
+    log.attach_option_index(OptionIndex::new(["parity"])).unwrap();
+
+    let mut opts = BlockOptions::default();
+    opts.set("parity", "even").unwrap();
+    log.write_block(&opts, &[10]).unwrap();
+
+    let indexed = log.find_by_option("parity", "even").unwrap();
+    assert_eq!(indexed.iter().map(|b| b.value()).collect::<Vec<_>>(), vec![0,2,4,6,8,10]);
+}
+
+#[test]
+fn test_stale_option_index() {
+    use std::fs::{create_dir_all, remove_file};
+    use std::path::PathBuf;
+
+    let dir = PathBuf::from("./target/test/stale_option_index");
+    create_dir_all(&dir).unwrap();
+    let path = dir.join("test.optidx");
+    if path.exists() {
+        remove_file(&path).unwrap();
+    }
+
+    let opts = |n: u8| {
+        let mut opts = BlockOptions::default();
+        opts.set("parity", if n % 2 == 0 { "even" } else { "odd" }).unwrap();
+        opts
+    };
+
+    // индекс от другого лога - 5 блоков
+    {
+        let mut index = OptionIndex::open(&path, ["parity"]).unwrap();
+        for n in 0u8..5 {
+            index.add(BlockId::new(n as u32), &opts(n + 1)).unwrap();
+        }
+    }
+
+    let mut log = LogFile::new(ByteBuff::new_empty_unlimited()).unwrap();
+    for n in 0u8..3 {
+        log.write_block(&opts(n), &[n]).unwrap();
+    }
+
+    log.attach_option_index(OptionIndex::open(&path, ["parity"]).unwrap()).unwrap();
+    let found = log.find_by_option("parity", "even").unwrap();
+    assert_eq!(found.iter().map(|b| b.value()).collect::<Vec<_>>(), vec![0,2]);
+
+    // sidecar файл перестроен и пополняется при записи
+    log.write_block(&opts(3), &[3]).unwrap();
+    let index = OptionIndex::open(&path, ["parity"]).unwrap();
+    assert_eq!(index.last_block(), Some(BlockId::new(3)));
+    assert_eq!(index.find("parity", "even"), vec![BlockId::new(0), BlockId::new(2)]);
+    assert_eq!(index.find("parity", "odd"), vec![BlockId::new(1), BlockId::new(3)]);
+}
+
+#[test]
+fn test_write_raw_block() {
+    let src_bb = ByteBuff::new_empty_unlimited();
+    let mut src = LogFile::new(src_bb.clone()).unwrap();
+    for n in 0u8..40 {
+        let mut opts = BlockOptions::default();
+        opts.set("n", n.to_string()).unwrap();
+        src.write_block(&opts, &vec![n; n as usize]).unwrap();
+    }
+
+    let dst_bb = ByteBuff::new_empty_unlimited();
+    let mut dst = LogFile::new(dst_bb.clone()).unwrap();
+    for b_id in 0..src.count().unwrap() {
+        let block = src.read_block(BlockId::new(b_id)).unwrap();
+        assert_eq!(dst.write_raw_block(&block).unwrap(), block.head.block_id);
+    }
+
+    assert_eq!(*src_bb.data.read().unwrap(), *dst_bb.data.read().unwrap());
+
+    let block = src.read_block(BlockId::new(3)).unwrap();
+    assert!(matches!(
+        dst.write_raw_block(&block),
+        Err(LogErr::BlockIdNotMatched { expected, actual }) if expected.value() == 40 && actual.value() == 3
+    ));
 }
 
 #[test]
@@ -764,6 +1161,27 @@ where
         Ok(block.data)
     }
 
+    /// Возвращает первые `limit` байтов данных текущего блока
+    ///
+    /// Читается только запрошенная часть данных, полный размер - `current_head().data_size`
+    pub fn current_data_prefix(&self, limit: usize) -> Result<Vec<u8>, LogErr> {
+        let head = &self.current_block;
+        let size = (head.data_size.value() as u64).min(limit as u64);
+        let start = head.position.value() + head.head_size.value() as u64;
+
+        let mut data = vec![0u8; size as usize];
+        let mut readed = 0u64;
+        let log = self.log_file.read()?;
+        while readed < size {
+            let n = log.read_raw_bytes(start + readed, &mut data[readed as usize..])?;
+            if n == 0 {
+                return Err(BlockErr::BlockDataTruncated { expect_data_size: size, reads_data_size: readed }.into());
+            }
+            readed += n;
+        }
+        Ok(data)
+    }
+
     /// Возвращает указатель на предыдущий блок
     pub fn previous(&self) -> Result<Self, LogErr> {
         let prev = self
@@ -879,17 +1297,13 @@ where
         //   return self.next();
         // }
 
-        // Прыжок вперед
+        // Прыжок вперед, по индексу смещений
         {
-            let last_ptr = self.clone().log_file.pointer_to_end()?;
-            if last_ptr.current_head().head.block_id < block_id {
-                return Err(LogErr::JumpOutsideLast { 
-                    last: last_ptr.current_head().head.block_id, 
-                    to: block_id
-                });
-            }
-
-            return last_ptr.jump_back(block_id);
+            let block_head = self.log_file.read()?.read_block_header(block_id)?;
+            Ok(Self {
+                log_file: self.log_file.clone(),
+                current_block: block_head,
+            })
         }
     }
 }
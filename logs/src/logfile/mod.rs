@@ -6,3 +6,19 @@ pub mod block;
 /// Лог файл - сумма блоков
 mod logfile;
 pub use logfile::*;
+
+/// Индекс смещений блоков
+mod offset_index;
+pub use offset_index::*;
+
+/// Вторичный индекс по опциям блока
+mod option_index;
+pub use option_index::*;
+
+/// Поиск блока по времени
+mod time_seek;
+pub use time_seek::*;
+
+/// Фильтр блоков по метаданным
+mod filter;
+pub use filter::*;
@@ -0,0 +1,177 @@
+//! Индекс смещений блоков
+//!
+//! Индекс хранит смещение в лог файле для каждого блока, что позволяет
+//! получить доступ к блоку по [BlockId] за O(1), без перехода по цепочке ссылок.
+//!
+//! Индекс может храниться рядом с лог файлом (sidecar файл),
+//! в таком случае при добавлении блока в лог, в sidecar файл дописывается смещение.
+//!
+//! Структура sidecar файла
+//!
+//! | Поле     | Тип        | Описание |
+//! |----------|------------|----------|
+//! | offset   | u64 (LE)   | Смещение блока, повторяется для каждого блока начиная с блока 0 |
+
+use std::path::Path;
+
+use crate::bbuff::absbuff::{FileBuff, ReadBytesFrom, WriteBytesTo, BytesCount, ResizeBytes};
+
+use super::block::{BlockId, FileOffset};
+use super::LogErr;
+
+/// Размер записи в sidecar файле
+const ENTRY_SIZE: u64 = 8;
+
+/// Индекс смещений блоков
+#[derive(Clone, Debug, Default)]
+pub struct OffsetIndex {
+    /// Смещения блоков, индекс в массиве - идентификатор блока
+    offsets: Vec<FileOffset>,
+
+    /// Файл индекса
+    sidecar: Option<FileBuff>,
+}
+
+impl OffsetIndex {
+    /// Создание пустого индекса без sidecar файла
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Кол-во проиндексированных блоков
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Проверка что индекс пуст
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Смещение блока
+    pub fn get(&self, block_id: BlockId) -> Option<FileOffset> {
+        self.offsets.get(block_id.value() as usize).copied()
+    }
+
+    /// Смещение последнего проиндексированного блока
+    pub fn last(&self) -> Option<(BlockId, FileOffset)> {
+        self.offsets
+            .last()
+            .map(|off| (BlockId::new(self.offsets.len() as u32 - 1), *off))
+    }
+
+    /// Записи для выборочной проверки соответствия логу - первая, средняя и последняя
+    pub fn samples(&self) -> Vec<(BlockId, FileOffset)> {
+        let mut ids = vec![0, self.offsets.len() / 2, self.offsets.len().saturating_sub(1)];
+        ids.dedup();
+        ids.into_iter()
+            .filter_map(|id| self.offsets.get(id).map(|off| (BlockId::new(id as u32), *off)))
+            .collect()
+    }
+
+    /// Смещения строго возрастают
+    pub fn is_increasing(&self) -> bool {
+        self.offsets.windows(2).all(|w| w[0].value() < w[1].value())
+    }
+
+    /// Добавление смещения следующего блока
+    ///
+    /// Если указан sidecar файл, то смещение дописывается в файл
+    pub fn push(&mut self, offset: FileOffset) -> Result<(), LogErr> {
+        if let Some(sidecar) = &mut self.sidecar {
+            let pos = self.offsets.len() as u64 * ENTRY_SIZE;
+            sidecar.write_to(pos, &offset.value().to_le_bytes())?;
+        }
+        self.offsets.push(offset);
+        Ok(())
+    }
+
+    /// Усечение индекса до указанного кол-ва блоков
+    pub fn truncate(&mut self, len: usize) -> Result<(), LogErr> {
+        if len >= self.offsets.len() {
+            return Ok(());
+        }
+        if let Some(sidecar) = &mut self.sidecar {
+            sidecar.resize_bytes(len as u64 * ENTRY_SIZE)?;
+        }
+        self.offsets.truncate(len);
+        Ok(())
+    }
+
+    /// Открытие sidecar файла индекса
+    ///
+    /// Неполная последняя запись (например после сбоя при записи) отбрасывается.
+    /// Соответствие индекса лог файлу проверяется при подключении к логу.
+    ///
+    /// Аргументы
+    /// - `path` - путь к файлу индекса
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, LogErr> {
+        let mut sidecar = FileBuff::open_read_write(path)?;
+
+        let size = sidecar.bytes_count()?;
+        let mut data = vec![0u8; size as usize];
+        let reads = sidecar.read_from(0, &mut data)?;
+        data.truncate(reads as usize);
+
+        let full = data.len() - data.len() % ENTRY_SIZE as usize;
+        if full != data.len() {
+            sidecar.resize_bytes(full as u64)?;
+        }
+
+        let offsets = data[0..full]
+            .chunks(ENTRY_SIZE as usize)
+            .map(|chunk| {
+                let mut bytes = [0u8; ENTRY_SIZE as usize];
+                bytes.copy_from_slice(chunk);
+                FileOffset::new(u64::from_le_bytes(bytes))
+            })
+            .collect();
+
+        Ok(Self {
+            offsets,
+            sidecar: Some(sidecar),
+        })
+    }
+}
+
+#[test]
+fn test_offset_index() {
+    use std::fs::{create_dir_all, remove_file, OpenOptions};
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    let dir = PathBuf::from("./target/test/offset_index");
+    create_dir_all(&dir).unwrap();
+    let path = dir.join("test.offsets");
+    if path.exists() {
+        remove_file(&path).unwrap();
+    }
+
+    {
+        let mut index = OffsetIndex::open(&path).unwrap();
+        assert!(index.is_empty());
+        index.push(FileOffset::new(0)).unwrap();
+        index.push(FileOffset::new(33)).unwrap();
+        index.push(FileOffset::new(78)).unwrap();
+    }
+
+    // неполная запись в конце
+    {
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[1, 2, 3]).unwrap();
+    }
+
+    let mut index = OffsetIndex::open(&path).unwrap();
+    assert_eq!(index.len(), 3);
+    assert!(index.is_increasing());
+    assert_eq!(index.samples(), vec![
+        (BlockId::new(0), FileOffset::new(0)), (BlockId::new(1), FileOffset::new(33)), (BlockId::new(2), FileOffset::new(78))
+    ]);
+    assert_eq!(index.get(BlockId::new(1)), Some(FileOffset::new(33)));
+    assert_eq!(index.last(), Some((BlockId::new(2), FileOffset::new(78))));
+    assert_eq!(index.get(BlockId::new(3)), None);
+
+    index.truncate(1).unwrap();
+    let index = OffsetIndex::open(&path).unwrap();
+    assert_eq!(index.len(), 1);
+}
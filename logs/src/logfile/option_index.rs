@@ -0,0 +1,253 @@
+//! Вторичный индекс по опциям блока
+//!
+//! Индекс хранит соответствие `(ключ, значение) -> [BlockId]` для выбранных ключей [BlockOptions].
+//!
+//! Индекс может храниться рядом с лог файлом (sidecar файл),
+//! в таком случае при добавлении блока в лог, в sidecar файл дописывается запись.
+//!
+//! Структура sidecar файла
+//!
+//! | Поле                | Тип        | Описание |
+//! |---------------------|------------|----------|
+//! | keys_count          | u32        | Кол-во индексируемых ключей |
+//! | key                 | String16   | Индексируемый ключ, повторяется keys_count раз |
+//! | entry.block_id      | BlockId    | Идентификатор блока |
+//! | entry.values_count  | u32        | Кол-во пар ключ/значение |
+//! | entry.key           | String16   | Ключ |
+//! | entry.value         | String32   | Значение |
+//!
+//! Записи `entry` повторяются для каждого блока лог файла.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::bbuff::absbuff::{FileBuff, ReadBytesFrom, WriteBytesTo, BytesCount, ResizeBytes};
+use crate::bbuff::streambuff::{ByteBuff, ByteReader, ByteWriter};
+
+use super::block::{BlockId, BlockOptions, String16, String32};
+use super::LogErr;
+
+/// Вторичный индекс по опциям блока
+#[derive(Clone, Debug)]
+pub struct OptionIndex {
+    /// Индексируемые ключи
+    keys: Vec<String>,
+
+    /// Значения индекса
+    values: HashMap<(String, String), Vec<BlockId>>,
+
+    /// Последний проиндексированный блок
+    last_block: Option<BlockId>,
+
+    /// Файл индекса
+    sidecar: Option<FileBuff>,
+}
+
+impl OptionIndex {
+    /// Создание пустого индекса без sidecar файла
+    ///
+    /// Аргументы
+    /// - `keys` - индексируемые ключи опций блока
+    pub fn new<K: Into<String>, I: IntoIterator<Item = K>>(keys: I) -> Self {
+        Self {
+            keys: keys.into_iter().map(|k| k.into()).collect(),
+            values: HashMap::new(),
+            last_block: None,
+            sidecar: None,
+        }
+    }
+
+    /// Индексируемые ключи
+    pub fn keys(&self) -> &Vec<String> {
+        &self.keys
+    }
+
+    /// Проверка что ключ индексируется
+    pub fn is_indexed(&self, key: &str) -> bool {
+        self.keys.iter().any(|k| k == key)
+    }
+
+    /// Последний проиндексированный блок
+    pub fn last_block(&self) -> Option<BlockId> {
+        self.last_block
+    }
+
+    /// Поиск блоков с указанным значением опции
+    ///
+    /// Результат - идентификаторы блоков в порядке добавления
+    pub fn find(&self, key: &str, value: &str) -> Vec<BlockId> {
+        self.values
+            .get(&(key.to_string(), value.to_string()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Добавление блока в индекс
+    ///
+    /// Если указан sidecar файл, то запись дописывается в файл
+    pub fn add(&mut self, block_id: BlockId, options: &BlockOptions) -> Result<(), LogErr> {
+        let entries: Vec<(String16, String32)> = options
+            .entries()
+            .into_iter()
+            .filter(|(k, _)| self.is_indexed(k.value()))
+            .collect();
+
+        if let Some(sidecar) = &mut self.sidecar {
+            let mut bbuf = ByteBuff::new();
+            bbuf.write(block_id);
+            bbuf.write(entries.len() as u32);
+            for (k, v) in entries.iter() {
+                bbuf.write(k.clone());
+                bbuf.write(v.clone());
+            }
+            let pos = sidecar.bytes_count()?;
+            sidecar.write_to(pos, &bbuf.buff)?;
+        }
+
+        self.put(block_id, entries);
+        Ok(())
+    }
+
+    fn put(&mut self, block_id: BlockId, entries: Vec<(String16, String32)>) {
+        for (k, v) in entries {
+            self.values
+                .entry((k.value().to_string(), v.value().to_string()))
+                .or_default()
+                .push(block_id);
+        }
+        self.last_block = Some(block_id);
+    }
+
+    /// Открытие sidecar файла индекса
+    ///
+    /// Если файл пустой, поврежден или содержит другой набор ключей,
+    /// то файл очищается и индекс возвращается пустым - его необходимо перестроить.
+    ///
+    /// Аргументы
+    /// - `path` - путь к файлу индекса
+    /// - `keys` - индексируемые ключи
+    pub fn open<P: AsRef<Path>, K: Into<String>, I: IntoIterator<Item = K>>(
+        path: P,
+        keys: I,
+    ) -> Result<Self, LogErr> {
+        let sidecar = FileBuff::open_read_write(path)?;
+        let mut index = Self::new(keys);
+
+        let size = sidecar.bytes_count()?;
+        let mut data = vec![0u8; size as usize];
+        let reads = sidecar.read_from(0, &mut data)?;
+        data.truncate(reads as usize);
+
+        let restored = if data.is_empty() {
+            None
+        } else {
+            index.restore(data).ok()
+        };
+
+        index.sidecar = Some(sidecar);
+        if restored.is_none() {
+            index.clear()?;
+        }
+        Ok(index)
+    }
+
+    /// Очистка индекса
+    ///
+    /// Если указан sidecar файл, то в файле остается только список ключей
+    pub fn clear(&mut self) -> Result<(), LogErr> {
+        self.values.clear();
+        self.last_block = None;
+
+        if let Some(sidecar) = &mut self.sidecar {
+            sidecar.resize_bytes(0)?;
+
+            let mut bbuf = ByteBuff::new();
+            bbuf.write(self.keys.len() as u32);
+            for k in self.keys.iter() {
+                bbuf.write(String16::try_from(k.as_str())?);
+            }
+            sidecar.write_to(0, &bbuf.buff)?;
+        }
+        Ok(())
+    }
+
+    /// Восстановление индекса из содержимого sidecar файла
+    fn restore(&mut self, data: Vec<u8>) -> Result<(), String> {
+        let mut bbuf = ByteBuff::from(data);
+
+        let mut keys_count = 0u32;
+        bbuf.read(&mut keys_count)?;
+
+        let mut keys = Vec::<String>::new();
+        for _ in 0..keys_count {
+            let mut key = String16::default();
+            bbuf.read(&mut key)?;
+            keys.push(key.value().to_string());
+        }
+
+        if keys != self.keys {
+            return Err(format!("index keys changed {keys:?} -> {:?}", self.keys));
+        }
+
+        while bbuf.position < bbuf.buff.len() {
+            let mut block_id = BlockId::default();
+            let mut values_count = 0u32;
+            bbuf.read(&mut block_id)?;
+            bbuf.read(&mut values_count)?;
+
+            let mut entries = Vec::<(String16, String32)>::new();
+            for _ in 0..values_count {
+                let mut k = String16::default();
+                let mut v = String32::default();
+                bbuf.read(&mut k)?;
+                bbuf.read(&mut v)?;
+                entries.push((k, v));
+            }
+
+            self.put(block_id, entries);
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_option_index() {
+    use std::fs::{create_dir_all, remove_file};
+    use std::path::PathBuf;
+
+    let dir = PathBuf::from("./target/test/option_index");
+    create_dir_all(&dir).unwrap();
+    let path = dir.join("test.optidx");
+    if path.exists() {
+        remove_file(&path).unwrap();
+    }
+
+    let mut opts_a = BlockOptions::default();
+    opts_a.set("mime", "text/plain").unwrap();
+    opts_a.set("time", "t0").unwrap();
+
+    let mut opts_b = BlockOptions::default();
+    opts_b.set("mime", "application/json").unwrap();
+
+    {
+        let mut index = OptionIndex::open(&path, ["mime"]).unwrap();
+        assert!(index.last_block().is_none());
+        index.add(BlockId::new(0), &opts_a).unwrap();
+        index.add(BlockId::new(1), &opts_b).unwrap();
+        index.add(BlockId::new(2), &opts_a).unwrap();
+    }
+
+    let index = OptionIndex::open(&path, ["mime"]).unwrap();
+    assert_eq!(index.last_block(), Some(BlockId::new(2)));
+    assert_eq!(
+        index.find("mime", "text/plain"),
+        vec![BlockId::new(0), BlockId::new(2)]
+    );
+    assert_eq!(index.find("mime", "application/json"), vec![BlockId::new(1)]);
+    assert!(index.find("time", "t0").is_empty());
+
+    // другой набор ключей - индекс сбрасывается
+    let index = OptionIndex::open(&path, ["mime", "time"]).unwrap();
+    assert!(index.last_block().is_none());
+}
@@ -0,0 +1,126 @@
+//! Поиск блока по времени
+//!
+//! Время записи хранится в опции блока [BLOCK_TIME_KEY] в формате RFC 3339,
+//! например `2023-07-21T22:37:38.481669+00:00`.
+//!
+//! Предполагается что время блоков не убывает по мере добавления блоков,
+//! тогда первый блок со временем не раньше указанного можно найти двоичным поиском.
+//!
+//! Блоки без опции времени (например блок с идентификатором лог файла) при поиске пропускаются.
+
+use chrono::{DateTime, FixedOffset};
+
+use super::block::BlockId;
+use super::{FlatBuff, LogErr, LogFile};
+
+/// Ключ опции блока, содержащий время записи
+pub const BLOCK_TIME_KEY: &str = "time";
+
+/// Парсинг времени блока
+///
+/// Результат - время или `None`, если значение не в формате RFC 3339
+pub fn parse_block_time(value: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(value).ok()
+}
+
+impl<B> LogFile<B>
+where
+    B: FlatBuff,
+{
+    /// Чтение времени блока
+    ///
+    /// Результат - время или `None`, если блок не содержит опцию времени
+    pub fn block_time(&self, block_id: BlockId) -> Result<Option<DateTime<FixedOffset>>, LogErr> {
+        let head = self.read_block_header(block_id)?;
+        Ok(head
+            .head
+            .block_options
+            .get(BLOCK_TIME_KEY)
+            .and_then(|v| parse_block_time(v.value())))
+    }
+
+    /// Поиск первого блока со временем в диапазоне `[from, to)`
+    fn first_timed_block(&self, from: u32, to: u32) -> Result<Option<(u32, DateTime<FixedOffset>)>, LogErr> {
+        for b_id in from..to {
+            if let Some(time) = self.block_time(BlockId::new(b_id))? {
+                return Ok(Some((b_id, time)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Время первого блока, содержащего опцию времени
+    pub fn first_time(&self) -> Result<Option<DateTime<FixedOffset>>, LogErr> {
+        Ok(self.first_timed_block(0, self.count()?)?.map(|(_, time)| time))
+    }
+
+    /// Время последнего блока, содержащего опцию времени
+    pub fn last_time(&self) -> Result<Option<DateTime<FixedOffset>>, LogErr> {
+        let count = self.count()?;
+        for b_id in (0..count).rev() {
+            if let Some(time) = self.block_time(BlockId::new(b_id))? {
+                return Ok(Some(time));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Поиск первого блока со временем не раньше указанного
+    ///
+    /// Аргументы
+    /// - `time` - искомое время
+    ///
+    /// Результат - идентификатор блока или `None`, если все блоки раньше указанного времени
+    pub fn seek_time(&self, time: &DateTime<FixedOffset>) -> Result<Option<BlockId>, LogErr> {
+        let mut lo = 0u32;
+        let mut hi = self.count()?;
+        let mut found: Option<u32> = None;
+
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.first_timed_block(mid, hi)? {
+                None => hi = mid,
+                Some((b_id, b_time)) if b_time < *time => lo = b_id + 1,
+                Some((b_id, _)) => {
+                    found = Some(b_id);
+                    hi = mid;
+                }
+            }
+        }
+
+        Ok(found.map(BlockId::new))
+    }
+}
+
+#[test]
+fn test_seek_time() {
+    use crate::bbuff::absbuff::ByteBuff;
+    use super::block::BlockOptions;
+
+    let bb = ByteBuff::new_empty_unlimited();
+    let mut log = LogFile::new(bb).unwrap();
+
+    // блок без времени
+    log.write_block(&BlockOptions::default(), &[0]).unwrap();
+
+    for n in 1u8..20 {
+        let mut opts = BlockOptions::default();
+        if n % 5 != 0 {
+            opts.set(BLOCK_TIME_KEY, format!("2023-07-21T22:{:02}:00.000000+00:00", n * 2).as_str()).unwrap();
+        }
+        log.write_block(&opts, &[n]).unwrap();
+    }
+
+    let t = |s: &str| parse_block_time(s).unwrap();
+
+    assert_eq!(log.seek_time(&t("2023-07-21T00:00:00+00:00")).unwrap(), Some(BlockId::new(1)));
+    assert_eq!(log.seek_time(&t("2023-07-21T22:06:00+00:00")).unwrap(), Some(BlockId::new(3)));
+    assert_eq!(log.seek_time(&t("2023-07-21T22:07:00+00:00")).unwrap(), Some(BlockId::new(4)));
+    // блок 5 без времени - пропускается
+    assert_eq!(log.seek_time(&t("2023-07-21T22:09:00+00:00")).unwrap(), Some(BlockId::new(6)));
+    assert_eq!(log.seek_time(&t("2023-07-21T22:38:00+00:00")).unwrap(), Some(BlockId::new(19)));
+    assert_eq!(log.seek_time(&t("2023-07-21T22:39:00+00:00")).unwrap(), None);
+
+    assert_eq!(log.first_time().unwrap(), Some(t("2023-07-21T22:02:00+00:00")));
+    assert_eq!(log.last_time().unwrap(), Some(t("2023-07-21T22:38:00+00:00")));
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use crate::logfile::{LogFile, FlatBuff};
+use crate::logqueue::{LogFileQueue, LogQueueFileId, LogQueueFileNumID, LoqErr, OpenLogFile, WriteNotify};
+
+use super::Archive;
+
+/// Загруженный из архива лог файл
+type FetchedLog<BUFF> = (PathBuf,LogFile<BUFF>);
+
+/// Очередь, которая загружает из архива лог файлы, отсутствующие на диске
+///
+/// Загруженные лог файлы сохраняются в каталог `cache_dir` и в список файлов очереди не попадают,
+/// остальные операции выполняются исходной очередью.
+pub struct ArchivedQueue<Q,BUFF,FOpen>
+where
+    Q: LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<BUFF>>,
+    BUFF: FlatBuff,
+    FOpen: OpenLogFile<PathBuf,LogFile<BUFF>,LogQueueFileNumID>,
+{
+    queue: Q,
+    archive: Arc<Archive>,
+    open: FOpen,
+    cache_dir: PathBuf,
+
+    /// Загруженные лог файлы
+    fetched: RwLock<HashMap<u128,FetchedLog<BUFF>>>,
+}
+
+impl<Q,BUFF,FOpen> ArchivedQueue<Q,BUFF,FOpen>
+where
+    Q: LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<BUFF>>,
+    BUFF: FlatBuff,
+    FOpen: OpenLogFile<PathBuf,LogFile<BUFF>,LogQueueFileNumID>,
+{
+    /// Конструктор
+    ///
+    /// Аргументы
+    /// - `queue` - исходная очередь
+    /// - `archive` - архив
+    /// - `open` - открытие загруженного лог файла
+    /// - `cache_dir` - каталог загруженных лог файлов
+    pub fn new( queue: Q, archive: Arc<Archive>, open: FOpen, cache_dir: PathBuf ) -> Self {
+        Self { queue, archive, open, cache_dir, fetched: RwLock::new(HashMap::new()) }
+    }
+
+    /// Архив
+    pub fn archive( &self ) -> Arc<Archive> {
+        self.archive.clone()
+    }
+
+    fn fetch( &self, log_id: u128 ) -> Result<Option<FetchedLog<BUFF>>,Box<LoqErr<PathBuf,LogQueueFileNumID>>> {
+        if let Some(found) = self.fetched.read().unwrap_or_else(|err| err.into_inner()).get(&log_id) {
+            return Ok(Some(found.clone()));
+        }
+
+        let fetch_err = |error: String| LoqErr::ArchiveFetch { log_id: LogQueueFileNumID { id: log_id, previous: None }, error };
+        let file = match self.archive.fetch(log_id, &self.cache_dir).map_err(|err| fetch_err(format!("{err:?}")))? {
+            Some((_, file)) => file,
+            None => return Ok(None)
+        };
+
+        let log = self.open.open_log_file(file.clone())?;
+        let actual = LogQueueFileNumID::read(&file, &log)?;
+        if actual.id != log_id {
+            return Err(Box::new(fetch_err(format!("archived file {} contains log id {}", file.display(), actual.id))));
+        }
+
+        self.fetched.write().unwrap_or_else(|err| err.into_inner()).insert(log_id, (file.clone(), log.clone()));
+        Ok(Some((file, log)))
+    }
+}
+
+impl<Q,BUFF,FOpen> LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<BUFF>> for ArchivedQueue<Q,BUFF,FOpen>
+where
+    Q: LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<BUFF>>,
+    BUFF: FlatBuff,
+    FOpen: OpenLogFile<PathBuf,LogFile<BUFF>,LogQueueFileNumID>,
+{
+    fn switch( &mut self ) -> Result<(PathBuf,LogQueueFileNumID),LoqErr<PathBuf,LogQueueFileNumID>> {
+        self.queue.switch()
+    }
+
+    fn switch_to( &mut self, log_id: LogQueueFileNumID ) -> Result<(PathBuf,LogQueueFileNumID),LoqErr<PathBuf,LogQueueFileNumID>> {
+        self.queue.switch_to(log_id)
+    }
+
+    fn find_log( &self, id:LogQueueFileNumID ) -> Result<Option<(PathBuf,LogFile<BUFF>)>,LoqErr<PathBuf,LogQueueFileNumID>> {
+        match self.queue.find_log(id)? {
+            Some(found) => Ok(Some(found)),
+            None => self.fetch(id.id).map_err(|err| *err)
+        }
+    }
+
+    fn offset_log_id( &self, id:LogQueueFileNumID, offset: i64) -> Result<Option<LogQueueFileNumID>, LoqErr<PathBuf,LogQueueFileNumID>> {
+        if let Some(found) = self.queue.offset_log_id(id, offset)? {
+            return Ok(Some(found));
+        }
+
+        // идентификаторы лог файлов последовательны
+        let target = match (id.id as i128).checked_add(offset as i128) {
+            Some(t) if t >= 0 => t as u128,
+            _ => return Ok(None)
+        };
+
+        if let Some((found, _, _)) = self.queue.files().into_iter().find(|(f, _, _)| f.id == target) {
+            return Ok(Some(found));
+        }
+
+        self.archive.meta(target)
+            .map(|meta| meta.map(|m| m.log_file_id()))
+            .map_err(|err| LoqErr::ArchiveFetch { log_id: LogQueueFileNumID { id: target, previous: None }, error: format!("{err:?}") })
+    }
+
+    fn current_log_id( &self ) -> Result<LogQueueFileNumID, LoqErr<PathBuf,LogQueueFileNumID>> {
+        self.queue.current_log_id()
+    }
+
+    fn files( &self ) -> Vec<(LogQueueFileNumID,PathBuf,LogFile<BUFF>)> {
+        self.queue.files()
+    }
+
+    fn tail( &self ) -> (LogQueueFileNumID,PathBuf,LogFile<BUFF>) {
+        self.queue.tail()
+    }
+
+    fn write_notify( &self ) -> WriteNotify {
+        self.queue.write_notify()
+    }
+
+    fn remove_head( &mut self ) -> Result<Option<(LogQueueFileNumID,PathBuf,LogFile<BUFF>)>,LoqErr<PathBuf,LogQueueFileNumID>> {
+        self.queue.remove_head()
+    }
+}
+
+#[test]
+fn test_archived_queue() {
+    use std::fs::{create_dir_all, remove_dir_all};
+    use chrono::Utc;
+    use crate::bbuff::absbuff::FileBuff;
+    use crate::logfile::block::BlockId;
+    use crate::logqueue::archive::{ArchiveErr, ArchiveStore, S3Store, s3_stub};
+    use crate::logqueue::*;
+
+    let root = PathBuf::from("./target/test/archived_queue");
+    let cache = PathBuf::from("./target/test/archived_queue_cache");
+    for dir in [&root, &cache] {
+        if dir.exists() { remove_dir_all(dir).unwrap(); }
+    }
+    create_dir_all(&root).unwrap();
+
+    let conf = test_queue_conf(&root, LogQueueFileNumIDOpen, ValidateStub);
+
+    let store = S3Store {
+        endpoint: s3_stub(),
+        bucket: "logs".to_string(),
+        region: "us-east-1".to_string(),
+        access_key: "key".to_string(),
+        secret_key: "secret".to_string(),
+        prefix: String::new(),
+    };
+    let archive = Arc::new(Archive::new(store.clone()));
+
+    let queue = conf.open().unwrap();
+    let mut queue = ArchivedQueue::new(queue, archive.clone(), LogQueueFileNumIDOpen, cache.clone());
+    let mut records = vec![];
+    for n in 0..3 {
+        let q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &queue;
+        records.push(q.write(&PreparedRecord::from(n)).unwrap());
+        queue.switch().unwrap();
+    }
+
+    let q: &mut dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &mut queue;
+    let uploaded = archive.upload_sealed(q).unwrap();
+    assert_eq!(uploaded.iter().map(|m| m.log_id).collect::<Vec<_>>(), vec![0, 1, 2]);
+    assert!(archive.upload_sealed(q).unwrap().is_empty());
+
+    let removed = apply_retention(q, &Retention { max_files: Some(1), ..Retention::default() }, &RetentionAction::Delete, Utc::now().fixed_offset()).unwrap();
+    assert_eq!(removed.len(), 3);
+    assert_eq!(q.files().len(), 1);
+
+    // чтение записей удаленных с диска лог файлов
+    for (n, rid) in records.iter().enumerate() {
+        let rec = q.read(rid.clone()).unwrap();
+        assert_eq!(rec.data, (n as i32).to_le_bytes().to_vec());
+    }
+    assert_eq!(q.next_record(records[0].clone()).unwrap().map(|r| (r.log_file_id.id, r.block_id)), Some((1, BlockId::new(0))));
+    assert_eq!(q.offset_log_id(q.current_log_id().unwrap(), -1).unwrap().map(|id| id.id), Some(2));
+    assert!(q.find_log(LogQueueFileNumID { id: 10, previous: None }).unwrap().is_none());
+
+    // поврежденный объект в архиве
+    store.put(&Archive::data_key(1), b"broken").unwrap();
+    let fresh = Archive::new(store);
+    assert!(matches!(fresh.fetch(1, &cache), Err(ArchiveErr::ChecksumMismatch { .. })));
+}
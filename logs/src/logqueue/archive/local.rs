@@ -0,0 +1,84 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use super::{ArchiveErr, ArchiveStore};
+
+/// Хранение объектов архива файлами в каталоге
+#[derive(Debug, Clone)]
+pub struct LocalDirStore {
+    /// Каталог
+    pub dir: PathBuf,
+}
+
+impl LocalDirStore {
+    pub fn new<P: Into<PathBuf>>( dir: P ) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn io_err( key: &str, err: std::io::Error ) -> ArchiveErr {
+        ArchiveErr::Io { key: key.to_string(), error: err.to_string() }
+    }
+}
+
+impl ArchiveStore for LocalDirStore {
+    fn put( &self, key: &str, data: &[u8] ) -> Result<(),ArchiveErr> {
+        fs::create_dir_all(&self.dir).map_err(|e| Self::io_err(key, e))?;
+
+        // запись во временный файл и переименование, что бы не оставить частично записанный объект
+        let tmp = self.dir.join(format!("{key}.tmp"));
+        fs::write(&tmp, data).map_err(|e| Self::io_err(key, e))?;
+        fs::rename(&tmp, self.dir.join(key)).map_err(|e| Self::io_err(key, e))
+    }
+
+    fn get( &self, key: &str ) -> Result<Option<Vec<u8>>,ArchiveErr> {
+        match fs::read(self.dir.join(key)) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(Self::io_err(key, err))
+        }
+    }
+
+    fn put_file( &self, key: &str, file: &Path ) -> Result<(),ArchiveErr> {
+        fs::create_dir_all(&self.dir).map_err(|e| Self::io_err(key, e))?;
+
+        let tmp = self.dir.join(format!("{key}.tmp"));
+        fs::copy(file, &tmp).map_err(|e| Self::io_err(key, e))?;
+        fs::rename(&tmp, self.dir.join(key)).map_err(|e| Self::io_err(key, e))
+    }
+
+    fn get_file( &self, key: &str, file: &Path ) -> Result<bool,ArchiveErr> {
+        match fs::copy(self.dir.join(key), file) {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::NotFound && !self.dir.join(key).exists() => Ok(false),
+            Err(err) => Err(Self::io_err(key, err))
+        }
+    }
+
+    fn exists( &self, key: &str ) -> Result<bool,ArchiveErr> {
+        Ok(self.dir.join(key).is_file())
+    }
+}
+
+#[test]
+fn test_local_dir_store() {
+    let dir = PathBuf::from("./target/test/archive_local");
+    if dir.exists() { fs::remove_dir_all(&dir).unwrap(); }
+
+    let store = LocalDirStore::new(&dir);
+    assert!(!store.exists("a").unwrap());
+    assert_eq!(store.get("a").unwrap(), None);
+
+    store.put("a", b"hello").unwrap();
+    assert!(store.exists("a").unwrap());
+    assert_eq!(store.get("a").unwrap(), Some(b"hello".to_vec()));
+
+    store.put("a", b"world").unwrap();
+    assert_eq!(store.get("a").unwrap(), Some(b"world".to_vec()));
+
+    let file = dir.join("a.copy");
+    assert!(!store.get_file("b", &file).unwrap());
+    assert!(store.get_file("a", &file).unwrap());
+    store.put_file("b", &file).unwrap();
+    assert_eq!(store.get("b").unwrap(), Some(b"world".to_vec()));
+}
@@ -0,0 +1,313 @@
+//! Архив лог файлов
+//!
+//! Лог файл, который больше не является актуальным, не изменяется - его можно выгрузить
+//! в хранилище ([ArchiveStore]) и удалить с диска (см. [apply_retention](super::apply_retention)).
+//!
+//! Для каждого лог файла в хранилище сохраняются два объекта
+//! - `{log_id}.binlog` - содержимое лог файла
+//! - `{log_id}.binlog.meta` - сведения о лог файле ([ArchiveMeta]), включая контрольную сумму sha256
+//!
+//! Объект сведений записывается последним, его наличие означает что лог файл выгружен полностью.
+//!
+//! Очередь [ArchivedQueue] при обращении к лог файлу, которого нет на диске,
+//! загружает его из архива, сверяет контрольную сумму и открывает как обычный лог файл.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+
+use crate::logfile::{LogFile, FlatBuff};
+
+use super::{LogFileQueue, LogQueueFileNumID, LogQueueFileId};
+
+/// Хранение в каталоге
+mod local;
+pub use local::*;
+
+/// Хранение в локальной заглушке S3 (HTTP без TLS)
+mod s3;
+pub use s3::*;
+
+/// Очередь с загрузкой лог файлов из архива
+mod archived_queue;
+pub use archived_queue::*;
+
+/// Ошибки архива
+#[derive(Debug, Clone)]
+pub enum ArchiveErr {
+    /// Ошибка ввода/вывода
+    Io { key: String, error: String },
+
+    /// Хранилище вернуло ошибку
+    Http { key: String, status: u16, message: String },
+
+    /// Не поддерживаемый адрес хранилища
+    Unsupported { endpoint: String },
+
+    /// Не корректный ответ хранилища
+    InvalidResponse { key: String, error: String },
+
+    /// Не корректные сведения о лог файле
+    InvalidMeta { key: String, error: String },
+
+    /// Содержимое лог файла не совпадает с контрольной суммой
+    ChecksumMismatch { key: String, expected: String, actual: String },
+}
+
+/// Хранилище архива - набор именованных объектов
+pub trait ArchiveStore: Send + Sync {
+    /// Сохранение объекта, существующий объект заменяется
+    fn put( &self, key: &str, data: &[u8] ) -> Result<(),ArchiveErr>;
+
+    /// Чтение объекта, `None` - объект не найден
+    fn get( &self, key: &str ) -> Result<Option<Vec<u8>>,ArchiveErr>;
+
+    /// Сохранение объекта из файла, содержимое файла не загружается в память целиком
+    fn put_file( &self, key: &str, file: &Path ) -> Result<(),ArchiveErr>;
+
+    /// Чтение объекта в файл, содержимое объекта не загружается в память целиком
+    ///
+    /// Результат - `false` если объект не найден, файл при этом не создается
+    fn get_file( &self, key: &str, file: &Path ) -> Result<bool,ArchiveErr>;
+
+    /// Проверка наличия объекта
+    fn exists( &self, key: &str ) -> Result<bool,ArchiveErr>;
+}
+
+/// Контрольная сумма sha256 в шестнадцатеричном виде
+pub fn sha256_hex( data: &[u8] ) -> String {
+    hex(&Sha256::digest(data))
+}
+
+/// Размер и контрольная сумма sha256 файла, файл читается частями
+pub fn sha256_file( file: &Path ) -> std::io::Result<(u64,String)> {
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut fs::File::open(file)?, &mut hasher)?;
+    Ok((size, hex(&hasher.finalize())))
+}
+
+pub(crate) fn hex( bytes: &[u8] ) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Сведения о выгруженном лог файле
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveMeta {
+    /// Идентификатор лог файла
+    pub log_id: u128,
+
+    /// Идентификатор предыдущего лог файла
+    pub previous: Option<u128>,
+
+    /// Имя исходного файла
+    pub file: String,
+
+    /// Размер в байтах
+    pub size: u64,
+
+    /// Контрольная сумма sha256
+    pub sha256: String,
+}
+
+impl ArchiveMeta {
+    /// Текстовое представление - строки `ключ=значение`
+    pub fn to_text( &self ) -> String {
+        let mut text = format!("log_id={}\n", self.log_id);
+        if let Some(prev) = self.previous {
+            text.push_str(&format!("previous={prev}\n"));
+        }
+        text.push_str(&format!("file={}\nsize={}\nsha256={}\n", self.file, self.size, self.sha256));
+        text
+    }
+
+    /// Разбор текстового представления
+    pub fn parse( key: &str, text: &str ) -> Result<Self,ArchiveErr> {
+        let err = |error: String| ArchiveErr::InvalidMeta { key: key.to_string(), error };
+        let values: HashMap<&str,&str> = text.lines()
+            .filter_map(|line| line.split_once('='))
+            .collect();
+        let value = |name: &str| values.get(name).copied().ok_or_else(|| err(format!("{name} not found")));
+
+        Ok(Self {
+            log_id: value("log_id")?.parse().map_err(|e| err(format!("log_id: {e}")))?,
+            previous: match values.get("previous") {
+                Some(v) => Some(v.parse().map_err(|e| err(format!("previous: {e}")))?),
+                None => None
+            },
+            file: value("file")?.to_string(),
+            size: value("size")?.parse().map_err(|e| err(format!("size: {e}")))?,
+            sha256: value("sha256")?.to_string(),
+        })
+    }
+
+    /// Идентификатор лог файла
+    pub fn log_file_id( &self ) -> LogQueueFileNumID {
+        LogQueueFileNumID { id: self.log_id, previous: self.previous }
+    }
+}
+
+/// Архив лог файлов
+pub struct Archive {
+    store: Box<dyn ArchiveStore>,
+
+    /// Кеш сведений о выгруженных лог файлах
+    metas: Mutex<HashMap<u128,ArchiveMeta>>,
+}
+
+impl Archive {
+    /// Создание архива
+    pub fn new<S: ArchiveStore + 'static>( store: S ) -> Self {
+        Self { store: Box::new(store), metas: Mutex::new(HashMap::new()) }
+    }
+
+    /// Имя объекта с содержимым лог файла
+    pub fn data_key( log_id: u128 ) -> String {
+        format!("{log_id}.binlog")
+    }
+
+    /// Имя объекта со сведениями о лог файле
+    pub fn meta_key( log_id: u128 ) -> String {
+        format!("{log_id}.binlog.meta")
+    }
+
+    fn cached( &self, log_id: u128 ) -> Option<ArchiveMeta> {
+        self.metas.lock().ok().and_then(|m| m.get(&log_id).cloned())
+    }
+
+    fn cache( &self, meta: &ArchiveMeta ) {
+        if let Ok(mut metas) = self.metas.lock() {
+            metas.insert(meta.log_id, meta.clone());
+        }
+    }
+
+    /// Лог файл выгружен - по сведениям, уже полученным из хранилища, без обращения к хранилищу
+    ///
+    /// `false` может означать, что сведения о лог файле еще не запрашивались, см. [Archive::meta]
+    pub fn is_uploaded( &self, log_id: u128 ) -> bool {
+        self.cached(log_id).is_some()
+    }
+
+    /// Сведения о выгруженном лог файле, `None` - лог файл не выгружен
+    pub fn meta( &self, log_id: u128 ) -> Result<Option<ArchiveMeta>,ArchiveErr> {
+        if let Some(meta) = self.cached(log_id) {
+            return Ok(Some(meta));
+        }
+
+        let key = Self::meta_key(log_id);
+        match self.store.get(&key)? {
+            Some(data) => {
+                let meta = ArchiveMeta::parse(&key, &String::from_utf8_lossy(&data))?;
+                self.cache(&meta);
+                Ok(Some(meta))
+            },
+            None => Ok(None)
+        }
+    }
+
+    /// Выгрузка лог файла
+    ///
+    /// Аргументы
+    /// - `log_id` - идентификатор лог файла
+    /// - `file` - лог файл
+    pub fn upload( &self, log_id: LogQueueFileNumID, file: &Path ) -> Result<ArchiveMeta,ArchiveErr> {
+        let data_key = Self::data_key(log_id.id);
+        let (size, sha256) = sha256_file(file)
+            .map_err(|err| ArchiveErr::Io { key: data_key.clone(), error: err.to_string() })?;
+
+        let meta = ArchiveMeta {
+            log_id: log_id.id,
+            previous: log_id.previous,
+            file: file.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_else(|| data_key.clone()),
+            size,
+            sha256,
+        };
+
+        self.store.put_file(&data_key, file)?;
+        self.store.put(&Self::meta_key(log_id.id), meta.to_text().as_bytes())?;
+        self.cache(&meta);
+        Ok(meta)
+    }
+
+    /// Лог файлы очереди, кроме актуального - они больше не изменяются
+    pub fn sealed_files<'a,BUFF: FlatBuff>(
+        queue: &(dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<BUFF>> + 'a)
+    ) -> Vec<(LogQueueFileNumID,PathBuf)> {
+        let (tail_id, _, _) = queue.tail();
+        queue.files().into_iter()
+            .filter(|(log_id, _, _)| log_id.id() != tail_id.id())
+            .map(|(log_id, file, _)| (log_id, file))
+            .collect()
+    }
+
+    /// Выгрузка лог файлов, которые еще не выгружены
+    ///
+    /// Результат - сведения о выгруженных лог файлах
+    pub fn upload_missing( &self, files: &[(LogQueueFileNumID,PathBuf)] ) -> Result<Vec<ArchiveMeta>,ArchiveErr> {
+        let mut uploaded = Vec::<ArchiveMeta>::new();
+        for (log_id, file) in files {
+            if self.meta(log_id.id)?.is_none() {
+                uploaded.push(self.upload(*log_id, file)?);
+            }
+        }
+        Ok(uploaded)
+    }
+
+    /// Выгрузка всех лог файлов очереди, кроме актуального, которые еще не выгружены
+    ///
+    /// Результат - сведения о выгруженных лог файлах
+    pub fn upload_sealed<'a,BUFF: FlatBuff>(
+        &self,
+        queue: &(dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<BUFF>> + 'a)
+    ) -> Result<Vec<ArchiveMeta>,ArchiveErr> {
+        self.upload_missing(&Self::sealed_files(queue))
+    }
+
+    /// Загрузка лог файла из архива с проверкой контрольной суммы
+    ///
+    /// Аргументы
+    /// - `log_id` - идентификатор лог файла
+    /// - `dir` - каталог, в который сохраняется лог файл
+    ///
+    /// Результат - путь к загруженному лог файлу или `None`, если лог файл не выгружен
+    pub fn fetch( &self, log_id: u128, dir: &Path ) -> Result<Option<(ArchiveMeta,PathBuf)>,ArchiveErr> {
+        let meta = match self.meta(log_id)? {
+            Some(meta) => meta,
+            None => return Ok(None)
+        };
+
+        let key = Self::data_key(log_id);
+        let io_err = |err: std::io::Error| ArchiveErr::Io { key: key.clone(), error: err.to_string() };
+        fs::create_dir_all(dir).map_err(io_err)?;
+
+        // загрузка во временный файл, лог файл появляется только после проверки контрольной суммы
+        let file = dir.join(&meta.file);
+        let tmp = dir.join(format!("{}.tmp", meta.file));
+        if !self.store.get_file(&key, &tmp)? {
+            return Err(ArchiveErr::InvalidResponse { key, error: "object not found".to_string() });
+        }
+
+        let (size, sha256) = sha256_file(&tmp).map_err(io_err)?;
+        if sha256 != meta.sha256 || size != meta.size {
+            let _ = fs::remove_file(&tmp);
+            return Err(ArchiveErr::ChecksumMismatch { key, expected: meta.sha256, actual: sha256 });
+        }
+
+        fs::rename(&tmp, &file).map_err(io_err)?;
+        Ok(Some((meta, file)))
+    }
+}
+
+#[test]
+fn test_archive_meta() {
+    let meta = ArchiveMeta { log_id: 3, previous: Some(2), file: "a.binlog".to_string(), size: 10, sha256: sha256_hex(b"abc") };
+    assert_eq!(meta.sha256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(ArchiveMeta::parse("k", &meta.to_text()).unwrap(), meta);
+
+    let head = ArchiveMeta { previous: None, ..meta.clone() };
+    assert_eq!(ArchiveMeta::parse("k", &head.to_text()).unwrap(), head);
+    assert!(matches!(ArchiveMeta::parse("k", "log_id=1\n"), Err(ArchiveErr::InvalidMeta { .. })));
+}
@@ -0,0 +1,401 @@
+//! Позиции потребителей очереди
+//!
+//! Позиция потребителя - идентификатор последней обработанной потребителем записи ([RecID]).
+//!
+//! Позиции хранятся в отдельном (служебном) лог файле, каждая фиксация позиции - это новый блок с опциями
+//!
+//! | Ключ               | Значение |
+//! |--------------------|----------|
+//! | `consumer`         | Имя потребителя |
+//! | `consumer_action`  | `commit` - фиксация позиции, `remove` - удаление потребителя |
+//! | `block_id`         | Идентификатор блока |
+//! | `log_file_id`, ... | Идентификатор лог файла, см [BlockWriter] |
+//!
+//! При открытии служебный лог читается целиком, актуальной считается последняя запись потребителя.
+//!
+//! Что бы служебный лог не рос без ограничения, он периодически сжимается ([ConsumerOffsets::with_snapshot]):
+//! актуальные позиции записываются в новый лог (снимок), который атомарно заменяет прежний ([OffsetsSnapshot]).
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::path::PathBuf;
+
+use log::error;
+
+use crate::bbuff::absbuff::FileBuff;
+use crate::logfile::{LogFile, FlatBuff, LogErr};
+use crate::logfile::block::{BlockErr, BlockId, BlockOptions};
+
+use super::{LogFileQueue, LogIdReadWriteErr, LogQueueFileId, LoqErr, RecID, resolve_rec_id};
+
+pub const CONSUMER_KEY: &str = "consumer";
+pub const CONSUMER_ACTION_KEY: &str = "consumer_action";
+pub const CONSUMER_BLOCK_ID_KEY: &str = "block_id";
+
+const ACTION_COMMIT: &str = "commit";
+const ACTION_REMOVE: &str = "remove";
+
+/// Ошибки работы с позициями потребителей
+#[derive(Debug,Clone)]
+pub enum ConsumerErr {
+    /// Ошибка служебного лог файла
+    Log(LogErr),
+
+    /// Ошибка формирования опций блока
+    Block(BlockErr),
+
+    /// Ошибка чтения/записи идентификатора лог файла
+    LogId(LogIdReadWriteErr),
+
+    /// Не корректная запись в служебном логе
+    BadRecord {
+        block_id: BlockId,
+        error: String,
+    },
+
+    /// Ошибка записи или замены снимка служебного лога
+    Snapshot {
+        error: String,
+    },
+}
+
+impl From<LogErr> for ConsumerErr {
+    fn from(value: LogErr) -> Self {
+        Self::Log(value)
+    }
+}
+
+impl From<BlockErr> for ConsumerErr {
+    fn from(value: BlockErr) -> Self {
+        Self::Block(value)
+    }
+}
+
+impl From<LogIdReadWriteErr> for ConsumerErr {
+    fn from(value: LogIdReadWriteErr) -> Self {
+        Self::LogId(value)
+    }
+}
+
+/// Снимок служебного лога позиций потребителей
+pub trait OffsetsSnapshot<BUFF: FlatBuff> {
+    /// Создание нового пустого лога для снимка, прежний не завершенный снимок удаляется
+    fn create( &self ) -> Result<LogFile<BUFF>, ConsumerErr>;
+
+    /// Атомарная замена служебного лога записанным снимком
+    fn replace( &self ) -> Result<(), ConsumerErr>;
+}
+
+/// Снимок служебного лог файла - файл `{file}.snapshot`, заменяющий служебный лог файл переименованием
+#[derive(Debug, Clone)]
+pub struct FileOffsetsSnapshot {
+    /// Служебный лог файл
+    pub file: PathBuf,
+}
+
+impl FileOffsetsSnapshot {
+    fn snapshot_file( &self ) -> PathBuf {
+        let mut name = self.file.clone().into_os_string();
+        name.push(".snapshot");
+        PathBuf::from(name)
+    }
+}
+
+impl OffsetsSnapshot<FileBuff> for FileOffsetsSnapshot {
+    fn create( &self ) -> Result<LogFile<FileBuff>, ConsumerErr> {
+        let snapshot = self.snapshot_file();
+        if snapshot.exists() {
+            fs::remove_file(&snapshot).map_err(|err| ConsumerErr::Snapshot { error: format!("can't remove {}: {err}", snapshot.display()) })?;
+        }
+        Ok(LogFile::new(FileBuff::open_read_write(&snapshot).map_err(LogErr::from)?)?)
+    }
+
+    fn replace( &self ) -> Result<(), ConsumerErr> {
+        let snapshot = self.snapshot_file();
+        let err = |err: std::io::Error| ConsumerErr::Snapshot { error: format!("can't replace {} with {}: {err}", self.file.display(), snapshot.display()) };
+        fs::File::open(&snapshot).and_then(|f| f.sync_all()).map_err(err)?;
+        fs::rename(&snapshot, &self.file).map_err(err)
+    }
+}
+
+/// Кол-во блоков служебного лога по умолчанию, после которого он сжимается
+pub const SNAPSHOT_BLOCKS: u32 = 1000;
+
+/// Позиции потребителей очереди
+pub struct ConsumerOffsets<LogId, BUFF>
+where
+    LogId: LogQueueFileId,
+    BUFF: FlatBuff,
+{
+    /// Служебный лог
+    log: LogFile<BUFF>,
+
+    /// Актуальные позиции
+    offsets: HashMap<String, RecID<LogId>>,
+
+    /// Сжатие служебного лога - снимок и кол-во блоков, после которого он сжимается
+    snapshot: Option<(Box<dyn OffsetsSnapshot<BUFF> + Send>, u32)>,
+}
+
+impl<LogId, BUFF> ConsumerOffsets<LogId, BUFF>
+where
+    LogId: LogQueueFileId,
+    BUFF: FlatBuff,
+{
+    /// Открытие позиций потребителей, восстановление позиций из служебного лога
+    pub fn open( log: LogFile<BUFF> ) -> Result<Self, ConsumerErr> {
+        let mut offsets = HashMap::<String, RecID<LogId>>::new();
+
+        let count = log.count()?;
+        for b_id in 0..count {
+            let block_id = BlockId::new(b_id);
+            let block = log.read_block(block_id)?;
+            let bad_record = |error: &str| ConsumerErr::BadRecord { block_id, error: error.to_string() };
+
+            let consumer = block.head.block_options.get(CONSUMER_KEY)
+                .ok_or_else(|| bad_record("consumer not defined"))?;
+            let action = block.head.block_options.get(CONSUMER_ACTION_KEY)
+                .ok_or_else(|| bad_record("consumer_action not defined"))?;
+
+            match action.value() {
+                ACTION_REMOVE => {
+                    offsets.remove(consumer.value());
+                },
+                ACTION_COMMIT => {
+                    let rec_block_id = block.head.block_options.get(CONSUMER_BLOCK_ID_KEY)
+                        .ok_or_else(|| bad_record("block_id not defined"))?
+                        .value()
+                        .parse::<u32>()
+                        .map_err(|err| bad_record(&err.to_string()))?;
+                    let log_file_id = LogId::block_read(&block)?;
+                    offsets.insert(consumer.value().to_string(), RecID {
+                        log_file_id,
+                        block_id: BlockId::new(rec_block_id)
+                    });
+                },
+                _ => return Err(bad_record("unknown consumer_action"))
+            }
+        }
+
+        Ok(Self { log, offsets, snapshot: None })
+    }
+
+    /// Сжатие служебного лога
+    ///
+    /// Когда кол-во блоков служебного лога достигает `max_blocks` (но не меньше удвоенного кол-ва потребителей),
+    /// актуальные позиции записываются в снимок, который заменяет служебный лог
+    pub fn with_snapshot<S>( self, snapshot: S, max_blocks: u32 ) -> Self
+    where
+        S: OffsetsSnapshot<BUFF> + Send + 'static
+    {
+        Self { snapshot: Some((Box::new(snapshot), max_blocks)), ..self }
+    }
+
+    /// Блок фиксации позиции потребителя
+    fn write_commit( log: &mut LogFile<BUFF>, consumer: &str, rec_id: &RecID<LogId> ) -> Result<(), ConsumerErr> {
+        let mut options = BlockOptions::default();
+        let mut data = Vec::<u8>::new();
+        rec_id.log_file_id.block_write(&mut options, &mut data)?;
+        options.set(CONSUMER_KEY, consumer)?;
+        options.set(CONSUMER_ACTION_KEY, ACTION_COMMIT)?;
+        options.set(CONSUMER_BLOCK_ID_KEY, rec_id.block_id.value().to_string())?;
+
+        log.write_block(&options, &data)?;
+        Ok(())
+    }
+
+    /// Сжатие после записи, ошибка сжатия не влияет на уже записанный блок - прежний служебный лог остается
+    fn compact( &mut self ) {
+        if let Err(err) = self.compact_if_due() {
+            error!("can't compact consumer offsets: {err:?}");
+        }
+    }
+
+    /// Сжатие служебного лога, если кол-во блоков достигло порога [ConsumerOffsets::with_snapshot]
+    ///
+    /// Результат - `true` если служебный лог заменен снимком
+    pub fn compact_if_due( &mut self ) -> Result<bool, ConsumerErr> {
+        let Some((snapshot, max_blocks)) = &self.snapshot else {
+            return Ok(false);
+        };
+
+        let threshold = (*max_blocks).max(self.offsets.len() as u32 * 2);
+        if self.log.count()? < threshold {
+            return Ok(false);
+        }
+
+        let mut log = snapshot.create()?;
+        for (consumer, rec_id) in self.consumers() {
+            Self::write_commit(&mut log, &consumer, &rec_id)?;
+        }
+        snapshot.replace()?;
+        self.log = log;
+        Ok(true)
+    }
+
+    /// Фиксация позиции потребителя
+    ///
+    /// Аргументы
+    /// - `consumer` - имя потребителя
+    /// - `rec_id` - последняя обработанная запись
+    pub fn commit( &mut self, consumer: &str, rec_id: RecID<LogId> ) -> Result<(), ConsumerErr> {
+        Self::write_commit(&mut self.log, consumer, &rec_id)?;
+        self.offsets.insert(consumer.to_string(), rec_id);
+        self.compact();
+        Ok(())
+    }
+
+    /// Удаление потребителя
+    ///
+    /// Результат - `true` если потребитель был
+    pub fn remove( &mut self, consumer: &str ) -> Result<bool, ConsumerErr> {
+        if !self.offsets.contains_key(consumer) {
+            return Ok(false);
+        }
+
+        let mut options = BlockOptions::default();
+        options.set(CONSUMER_KEY, consumer)?;
+        options.set(CONSUMER_ACTION_KEY, ACTION_REMOVE)?;
+
+        self.log.write_block(&options, &[])?;
+        self.offsets.remove(consumer);
+        self.compact();
+        Ok(true)
+    }
+
+    /// Получение позиции потребителя
+    pub fn fetch( &self, consumer: &str ) -> Option<RecID<LogId>> {
+        self.offsets.get(consumer).cloned()
+    }
+
+    /// Позиции всех потребителей, упорядоченные по имени
+    pub fn consumers( &self ) -> Vec<(String, RecID<LogId>)> {
+        let mut res: Vec<(String, RecID<LogId>)> =
+            self.offsets.iter().map(|(k,v)| (k.clone(), v.clone())).collect();
+        res.sort_by(|a,b| a.0.cmp(&b.0));
+        res
+    }
+}
+
+/// Отставание потребителя - кол-во записей в очереди после указанной
+///
+/// Аргументы
+/// - `queue` - очередь
+/// - `rec_id` - последняя обработанная потребителем запись, лог файл ищется по номеру ([resolve_rec_id])
+pub fn consumer_lag<LogId, FILE, BUFF>(
+    queue: &(dyn LogFileQueue<LogId, FILE, LogFile<BUFF>> + '_),
+    rec_id: &RecID<LogId>
+) -> Result<u64, LoqErr<FILE, LogId>>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    let count_of = |log_id: LogId| {
+        match queue.find_log(log_id)? {
+            None => Err(LoqErr::LogIdNotMatched { log_id }),
+            Some((file, log)) => log.count()
+                .map(|c| c as u64)
+                .map_err(|err| LoqErr::LogCountFail { file, error: err })
+        }
+    };
+
+    let rec_id = resolve_rec_id(queue, rec_id.clone());
+    let count = count_of(rec_id.log_file_id)?;
+    let mut lag = count.saturating_sub(rec_id.block_id.value() as u64 + 1);
+
+    let mut log_id = rec_id.log_file_id;
+    while let Some(next_id) = queue.offset_log_id(log_id, 1)? {
+        lag += count_of(next_id)?;
+        log_id = next_id;
+    }
+
+    Ok(lag)
+}
+
+#[test]
+fn test_consumer_offsets() {
+    use crate::bbuff::absbuff::ByteBuff;
+    use super::LogQueueFileNumID;
+
+    let bb = ByteBuff::new_empty_unlimited();
+    let rid = |log:u128, block:u32| RecID {
+        log_file_id: LogQueueFileNumID { id: log, previous: None },
+        block_id: BlockId::new(block)
+    };
+
+    {
+        let mut offsets = ConsumerOffsets::<LogQueueFileNumID,_>::open(LogFile::new(bb.clone()).unwrap()).unwrap();
+        assert!(offsets.fetch("a").is_none());
+        offsets.commit("a", rid(0, 3)).unwrap();
+        offsets.commit("b", rid(0, 1)).unwrap();
+        offsets.commit("a", rid(1, 2)).unwrap();
+        offsets.commit("c", rid(1, 5)).unwrap();
+        assert!(offsets.remove("c").unwrap());
+        assert!(!offsets.remove("c").unwrap());
+    }
+
+    let offsets = ConsumerOffsets::<LogQueueFileNumID,_>::open(LogFile::new(bb.clone()).unwrap()).unwrap();
+    assert_eq!(offsets.fetch("a"), Some(rid(1, 2)));
+    assert_eq!(offsets.fetch("b"), Some(rid(0, 1)));
+    assert_eq!(offsets.fetch("c"), None);
+    assert_eq!(
+        offsets.consumers().into_iter().map(|(n,_)| n).collect::<Vec<_>>(),
+        vec!["a".to_string(), "b".to_string()]
+    );
+
+    // сжатие служебного лог файла
+    let root = super::test_dir("consumer_offsets");
+    let file = root.join("consumers.binlog");
+    let open = || ConsumerOffsets::<LogQueueFileNumID,_>::open(LogFile::new(FileBuff::open_read_write(&file).unwrap()).unwrap()).unwrap()
+        .with_snapshot(FileOffsetsSnapshot { file: file.clone() }, 10);
+    {
+        let mut offsets = open();
+        for i in 0..25 {
+            offsets.commit(if i % 2 == 0 { "a" } else { "b" }, rid(0, i)).unwrap();
+        }
+        assert!(offsets.log.count().unwrap() < 10);
+    }
+    let offsets = open();
+    assert_eq!(offsets.fetch("a"), Some(rid(0, 24)));
+    assert_eq!(offsets.fetch("b"), Some(rid(0, 23)));
+    assert!(offsets.log.count().unwrap() < 10);
+    assert!(!root.join("consumers.binlog.snapshot").exists());
+}
+
+#[test]
+fn test_consumer_lag() {
+    use std::path::PathBuf;
+    use crate::bbuff::absbuff::FileBuff;
+    use crate::logqueue::*;
+
+    let root = test_dir("consumer_lag");
+
+    let conf = test_queue_conf(&root, LogQueueFileNumIDOpen, ValidateStub);
+    let mut queue = conf.open().unwrap();
+
+    let q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &queue;
+    let r1 = q.write(&PreparedRecord::from(1)).unwrap();
+    let r2 = q.write(&PreparedRecord::from(2)).unwrap();
+    queue.switch().unwrap();
+
+    let q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &queue;
+    let r3 = q.write(&PreparedRecord::from(3)).unwrap();
+
+    assert_eq!(consumer_lag(q, &r3).unwrap(), 0);
+    // r3 и блок 0 второго файла
+    assert_eq!(consumer_lag(q, &r2).unwrap(), 2);
+    assert_eq!(consumer_lag(q, &r1).unwrap(), 3);
+
+    queue.switch().unwrap();
+    let q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &queue;
+    q.write(&PreparedRecord::from(4)).unwrap();
+
+    // позиция по номеру лог файла, без ссылки на предыдущий: r3 - блок 0 и запись третьего файла
+    let by_number = RecID {
+        log_file_id: LogQueueFileNumID { id: r3.log_file_id.id, previous: None },
+        block_id: r3.block_id
+    };
+    assert_eq!(consumer_lag(q, &by_number).unwrap(), 2);
+}
@@ -1,5 +1,4 @@
 use std::sync::{PoisonError, RwLockReadGuard, RwLockWriteGuard};
-#[allow(unused)]
 use std::{path::PathBuf, fmt::Debug};
 
 use crate::logfile::{block::FileOffset, FlatBuff};
@@ -135,6 +134,62 @@ where
         pos: FileOffset,
         data_size: usize,
         error: LogErr,
+    },
+
+    /// Ошибка открытия вторичного индекса
+    OpenOptionIndex {
+        file: FILE,
+        index_file: PathBuf,
+        error: LogErr,
+    },
+
+    /// Ошибка открытия индекса смещений
+    OpenOffsetIndex {
+        file: FILE,
+        index_file: PathBuf,
+        error: LogErr,
+    },
+
+    /// Ошибка поиска по опциям блока
+    LogFindByOption {
+        file: FILE,
+        error: LogErr,
+    },
+
+    /// Ошибка поиска записи по времени
+    LogSeekTime {
+        file: FILE,
+        error: LogErr,
+    },
+
+    /// Идентификатор лог файла записываемого блока не совпадает с идентификатором лог файла очереди
+    RawWriteLogIdNotMatched {
+        expected: LogId,
+        actual: LogId,
+    },
+
+    /// Ошибка удаления или переноса исключенного из очереди лог файла
+    DisposeLogFile {
+        file: FILE,
+        error: String,
+    },
+
+    /// Ошибка загрузки лог файла из архива
+    ArchiveFetch {
+        log_id: LogId,
+        error: String,
+    },
+
+    /// Последняя запись очереди не совпадает с ожидаемой (запись с проверкой хвоста)
+    TailNotMatched {
+        expected_log_id: LogId,
+        expected_block_id: BlockId,
+        actual: Option<(LogId,BlockId)>,
+    },
+
+    /// Проверка очереди при открытии нашла проблемы, см. [QueueValidator](super::QueueValidator)
+    ValidationFailed {
+        report: Box<super::ValidateReport<FILE>>,
     }
 }
 
@@ -0,0 +1,157 @@
+//! Дедупликация записей по ключу идемпотентности
+//!
+//! Ключ, переданный клиентом, сохраняется в опции блока [IDEMPOTENCY_KEY].
+//! Последние ключи хранятся в памяти ([DedupWindow]), после перезапуска окно
+//! восстанавливается чтением заголовков последних записей очереди ([restore_dedup_window]).
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+
+use crate::logfile::{LogFile, FlatBuff};
+
+use super::{LogFileQueue, LogNavigateLast, LogNavigationNear, LogQueueFileId, LogReading, LoqErr, RecID};
+
+/// Опция блока - ключ идемпотентности
+pub const IDEMPOTENCY_KEY: &str = "idempotency_key";
+
+/// Окно дедупликации - ограниченное кол-во последних ключей и идентификаторов записей
+#[derive(Debug, Clone)]
+pub struct DedupWindow<RID: Clone> {
+    capacity: usize,
+    order: VecDeque<String>,
+    ids: HashMap<String, RID>,
+}
+
+impl<RID: Clone> DedupWindow<RID> {
+    /// Создание окна
+    ///
+    /// Аргументы
+    /// - `capacity` - максимальное кол-во ключей, при превышении вытесняются самые старые
+    pub fn new( capacity: usize ) -> Self {
+        Self { capacity, order: VecDeque::new(), ids: HashMap::new() }
+    }
+
+    /// Максимальное кол-во ключей
+    pub fn capacity( &self ) -> usize {
+        self.capacity
+    }
+
+    /// Кол-во ключей
+    pub fn len( &self ) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty( &self ) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Идентификатор записи, добавленной с указанным ключем
+    pub fn get( &self, key: &str ) -> Option<RID> {
+        self.ids.get(key).cloned()
+    }
+
+    /// Добавление ключа
+    ///
+    /// Повторное добавление ключа заменяет идентификатор записи
+    pub fn insert( &mut self, key: &str, rid: RID ) {
+        if self.capacity == 0 { return; }
+
+        if self.ids.insert(key.to_string(), rid).is_none() {
+            self.order.push_back(key.to_string());
+        }
+
+        while self.order.len() > self.capacity {
+            if let Some(old) = self.order.pop_front() {
+                self.ids.remove(&old);
+            }
+        }
+    }
+}
+
+/// Восстановление окна дедупликации по последним записям очереди
+///
+/// Аргументы
+/// - `queue` - очередь
+/// - `capacity` - размер окна, читается не более `capacity` последних записей
+pub fn restore_dedup_window<LogId, FILE, BUFF>(
+    queue: &(dyn LogFileQueue<LogId, FILE, LogFile<BUFF>> + '_),
+    capacity: usize
+) -> Result<DedupWindow<RecID<LogId>>, LoqErr<FILE, LogId>>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    let mut found = Vec::<(String, RecID<LogId>)>::new();
+
+    let mut rid = queue.last_record()?;
+    let mut scanned = 0usize;
+    while let Some(cur) = rid {
+        if scanned >= capacity { break; }
+        scanned += 1;
+
+        let info = queue.info(cur.clone())?;
+        if let Some(key) = info.block_options.get(IDEMPOTENCY_KEY) {
+            found.push((key.value().to_string(), cur.clone()));
+        }
+
+        rid = queue.previous_record(cur)?;
+    }
+
+    let mut window = DedupWindow::new(capacity);
+    for (key, rid) in found.into_iter().rev() {
+        window.insert(&key, rid);
+    }
+
+    Ok(window)
+}
+
+#[test]
+fn test_dedup_window() {
+    let mut window = DedupWindow::<u32>::new(2);
+    window.insert("a", 1);
+    window.insert("b", 2);
+    assert_eq!(window.get("a"), Some(1));
+
+    window.insert("c", 3);
+    assert_eq!(window.len(), 2);
+    assert_eq!(window.get("a"), None);
+    assert_eq!(window.get("b"), Some(2));
+    assert_eq!(window.get("c"), Some(3));
+}
+
+#[test]
+fn test_restore_dedup_window() {
+    use std::path::PathBuf;
+    use crate::bbuff::absbuff::FileBuff;
+    use crate::logfile::block::BlockOptions;
+    use crate::logqueue::*;
+
+    let root = test_dir("dedup_window");
+
+    let conf = test_queue_conf(&root, LogQueueFileNumIDOpen, ValidateStub);
+    let mut queue = conf.open().unwrap();
+
+    let write = |q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>>, key: Option<&str>| {
+        let mut options = BlockOptions::default();
+        if let Some(key) = key { options.set(IDEMPOTENCY_KEY, key).unwrap(); }
+        q.write(&PreparedRecord { data: vec![1], options }).unwrap()
+    };
+
+    let r1 = write(&queue, Some("k1"));
+    write(&queue, None);
+    queue.switch().unwrap();
+    let r2 = write(&queue, Some("k2"));
+    let r3 = write(&queue, Some("k3"));
+
+    let window = restore_dedup_window(&queue, 100).unwrap();
+    assert_eq!(window.get("k1"), Some(r1));
+    assert_eq!(window.get("k2"), Some(r2.clone()));
+    assert_eq!(window.get("k3"), Some(r3.clone()));
+
+    // окно из двух последних записей
+    let window = restore_dedup_window(&queue, 2).unwrap();
+    assert_eq!(window.get("k1"), None);
+    assert_eq!(window.get("k2"), Some(r2));
+    assert_eq!(window.get("k3"), Some(r3));
+}
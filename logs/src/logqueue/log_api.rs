@@ -1,5 +1,6 @@
-use crate::logfile::{block::{BlockOptions, BlockId, FileOffset, BlockHeadSize, BlockDataSize, BlockTailSize}, LogErr};
+use crate::logfile::{block::{Block, BlockOptions, BlockId, FileOffset, BlockHeadSize, BlockDataSize, BlockTailSize}, LogErr};
 use core::fmt::Debug;
+use chrono::{DateTime, FixedOffset};
 use super::{LoqErr, LogFileQueue};
 
 /// Навигация по смеженным записям
@@ -110,6 +111,47 @@ pub trait LogReading {
         Result<u64, LoqErr<Self::FILE, Self::LogId>>;
 }
 
+/// Поиск записей по опциям блока
+pub trait LogOptionSearch<RecordId> {
+    /// Тип файла, имееться виду PathBuf
+    type FILE: Clone + Debug;
+
+    /// Тип идентификатора лог файла, имеется ввиду LogQueueFileNumID
+    type LogId: Clone + Debug;
+
+    /// Поиск записей с указанным значением опции
+    /// 
+    /// Для ключей подключенных к вторичному индексу ([crate::logfile::OptionIndex])
+    /// поиск выполняется по индексу, для остальных - перебором.
+    /// 
+    /// Аргументы
+    /// - `key` - ключ опции
+    /// - `value` - значение опции
+    /// 
+    /// Результат - идентификаторы записей в порядке следования в очереди
+    fn find_by_option( &self, key:&str, value:&str ) -> Result<Vec<RecordId>,LoqErr<Self::FILE,Self::LogId>>;
+}
+
+/// Поиск записи по времени
+pub trait LogTimeSeek<RecordId> {
+    /// Тип файла, имееться виду PathBuf
+    type FILE: Clone + Debug;
+
+    /// Тип идентификатора лог файла, имеется ввиду LogQueueFileNumID
+    type LogId: Clone + Debug;
+
+    /// Поиск первой записи со временем (опция `time`) не раньше указанного
+    /// 
+    /// Поиск выполняется двоичным поиском сначала по лог файлам, затем внутри лог файла.
+    /// Записи без опции `time` пропускаются.
+    /// 
+    /// Аргументы
+    /// - `time` - искомое время
+    /// 
+    /// Результат - идентификатор записи или `None`, если все записи раньше указанного времени
+    fn seek_time( &self, time:&DateTime<FixedOffset> ) -> Result<Option<RecordId>,LoqErr<Self::FILE,Self::LogId>>;
+}
+
 /// Подготовленные данные для записи
 #[derive(Clone)]
 pub struct PreparedRecord {
@@ -132,6 +174,27 @@ pub trait LogWriting<RecordId>
     fn write( &self, record:&PreparedRecord ) -> Result<RecordId,LoqErr<Self::FILE,Self::LogId>>;
 }
 
+/// Запись блоков в исходном виде, для репликации очереди
+pub trait LogRawWriting<RecordId> 
+{
+    /// Тип файла, имееться виду PathBuf
+    type FILE: Clone + Debug;
+
+    /// Тип идентификатора лог файла, имеется ввиду LogQueueFileNumID
+    type LogId: Clone + Debug;
+
+    /// Запись блока с сохранением идентификатора записи
+    /// 
+    /// Аргументы
+    /// - `rec_id` - идентификатор записи в очереди источника
+    /// - `block` - блок источника
+    /// 
+    /// Блок 0 следующего лог файла - это переключение лог файла,
+    /// идентификатор нового лог файла должен совпадать с идентификатором, записанным в блоке.
+    /// Остальные блоки дописываются в текущий лог файл, идентификатор записи должен быть следующим в очереди.
+    fn write_raw( &mut self, rec_id: RecordId, block:&Block ) -> Result<RecordId,LoqErr<Self::FILE,Self::LogId>>;
+}
+
 /// Общий API лог очереди
 pub trait LogQueue<RecordId,LogId,FILE,LOG> 
 : LogNavigateLast<RecordId,FILE,LogId>
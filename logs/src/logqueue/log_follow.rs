@@ -0,0 +1,263 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use crate::logfile::{LogFile, FlatBuff, block::BlockId};
+
+use super::{LogFileQueue, LogQueueFileId, LogQueueImpl, LogReading, LoqErr, PreparedRecord, RecID, WriteNotify, resolve_rec_id};
+
+/// Прочитанная запись очереди и ее идентификатор
+pub type FollowItem<LogId> = (RecID<LogId>,PreparedRecord);
+
+/// Доступ к очереди, защищенной блокировкой
+///
+/// Блокировка удерживается только на время выполнения `work`
+pub trait QueueAccess<LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    fn access<R,F>( &self, work:F ) -> Result<R,LoqErr<FILE,LogId>>
+    where
+        F: FnOnce(&dyn LogFileQueue<LogId,FILE,LogFile<BUFF>>) -> Result<R,LoqErr<FILE,LogId>>;
+}
+
+impl<'a,LogId,FILE,BUFF> QueueAccess<LogId,FILE,BUFF>
+for Arc<RwLock<dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + 'a>>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    fn access<R,F>( &self, work:F ) -> Result<R,LoqErr<FILE,LogId>>
+    where
+        F: FnOnce(&dyn LogFileQueue<LogId,FILE,LogFile<BUFF>>) -> Result<R,LoqErr<FILE,LogId>>
+    {
+        let q = self.read()?;
+        work(&*q)
+    }
+}
+
+impl<'a,LogId,FILE,BUFF> QueueAccess<LogId,FILE,BUFF>
+for Arc<Mutex<dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + 'a>>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    fn access<R,F>( &self, work:F ) -> Result<R,LoqErr<FILE,LogId>>
+    where
+        F: FnOnce(&dyn LogFileQueue<LogId,FILE,LogFile<BUFF>>) -> Result<R,LoqErr<FILE,LogId>>
+    {
+        let q = self.lock()
+            .map_err(|err| LoqErr::CantCaptureWriteLock { error: err.to_string() })?;
+        work(&*q)
+    }
+}
+
+impl<'a,LogId,FILE,BUFF> QueueAccess<LogId,FILE,BUFF>
+for LogQueueImpl<'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    fn access<R,F>( &self, work:F ) -> Result<R,LoqErr<FILE,LogId>>
+    where
+        F: FnOnce(&dyn LogFileQueue<LogId,FILE,LogFile<BUFF>>) -> Result<R,LoqErr<FILE,LogId>>
+    {
+        self.queue.access(work)
+    }
+}
+
+/// Чтение очереди с ожиданием новых записей
+///
+/// Итератор начинает с указанной записи и переходит между лог файлами.
+/// Достигнув конца очереди, итератор ожидает новых записей ([WriteNotify]).
+///
+/// Если указан таймаут ([LogFollow::with_timeout()]) и за это время новых записей не появилось,
+/// то итератор возвращает `None`, повторный вызов `next()` продолжит ожидание.
+///
+/// После ошибки итератор завершается.
+pub struct LogFollow<Q,LogId,FILE,BUFF>
+where
+    Q: QueueAccess<LogId,FILE,BUFF>,
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    queue: Q,
+    notify: WriteNotify,
+    position: RecID<LogId>,
+    timeout: Option<Duration>,
+    failed: bool,
+    _p: PhantomData<(FILE,BUFF)>,
+}
+
+impl<Q,LogId,FILE,BUFF> LogFollow<Q,LogId,FILE,BUFF>
+where
+    Q: QueueAccess<LogId,FILE,BUFF>,
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    /// Создание итератора
+    ///
+    /// Аргументы
+    /// - `queue` - очередь
+    /// - `from` - первая читаемая запись, может указывать на еще не записанный блок;
+    ///   идентификатор лог файла заменяется идентификатором из очереди ([resolve_rec_id])
+    pub fn new( queue:Q, from:RecID<LogId> ) -> Result<Self,LoqErr<FILE,LogId>> {
+        let (notify, position) = queue.access(|q| Ok((q.write_notify(), resolve_rec_id(q, from))))?;
+        Ok(Self {
+            queue,
+            notify,
+            position,
+            timeout: None,
+            failed: false,
+            _p: PhantomData
+        })
+    }
+
+    /// Указание максимального времени ожидания новых записей
+    pub fn with_timeout( self, timeout:Duration ) -> Self {
+        Self { timeout: Some(timeout), ..self }
+    }
+
+    /// Следующая читаемая запись
+    pub fn position( &self ) -> RecID<LogId> {
+        self.position.clone()
+    }
+
+    /// Уведомление о записи в очередь
+    pub fn write_notify( &self ) -> WriteNotify {
+        self.notify.clone()
+    }
+
+    /// Чтение следующей записи без ожидания
+    ///
+    /// Результат - запись или `None`, если достигнут конец очереди
+    pub fn try_next( &mut self ) -> Result<Option<FollowItem<LogId>>,LoqErr<FILE,LogId>> {
+        let position = self.position.clone();
+        let found = self.queue.access(|q| {
+            let mut pos = position;
+            loop {
+                let (file,log) = q.find_log(pos.log_file_id)?
+                    .ok_or(LoqErr::LogIdNotMatched { log_id: pos.log_file_id })?;
+                let count = log.count()
+                    .map_err(|err| LoqErr::LogCountFail { file, error: err })?;
+
+                if pos.block_id.value() < count {
+                    let rec = q.read(pos.clone())?;
+                    return Ok(Some((pos,rec)));
+                }
+
+                match q.offset_log_id(pos.log_file_id, 1)? {
+                    Some(next_log_id) => {
+                        pos = RecID { log_file_id: next_log_id, block_id: BlockId::new(0) };
+                    },
+                    None => {
+                        return Ok(None);
+                    }
+                }
+            }
+        })?;
+
+        if let Some((rid,_)) = &found {
+            self.position = RecID {
+                log_file_id: rid.log_file_id,
+                block_id: BlockId::new(rid.block_id.value() + 1)
+            };
+        }
+
+        Ok(found)
+    }
+}
+
+impl<Q,LogId,FILE,BUFF> Iterator for LogFollow<Q,LogId,FILE,BUFF>
+where
+    Q: QueueAccess<LogId,FILE,BUFF>,
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    type Item = Result<FollowItem<LogId>,LoqErr<FILE,LogId>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed { return None; }
+        loop {
+            let version = self.notify.version();
+            match self.try_next() {
+                Ok(Some(rec)) => return Some(Ok(rec)),
+                Err(err) => {
+                    self.failed = true;
+                    return Some(Err(err));
+                },
+                Ok(None) => {
+                    if self.notify.wait_change(version, self.timeout) == version {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_follow() {
+    use std::path::PathBuf;
+    use crate::bbuff::absbuff::FileBuff;
+    use crate::logqueue::*;
+
+    let root = test_dir("follow_queue");
+
+    let conf = test_queue_conf(&root, LogQueueFileNumIDOpen, ValidateStub);
+    let queue: Arc<Mutex<dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>>>> = 
+        Arc::new(Mutex::new(conf.open().unwrap()));
+
+    let first = queue.access(|q| q.write(&PreparedRecord::from(1))).unwrap();
+
+    let mut follow = LogFollow::new(queue.clone(), first.clone()).unwrap()
+        .with_timeout(Duration::from_millis(10));
+
+    let (rid, rec) = follow.next().unwrap().unwrap();
+    assert_eq!(rid, first);
+    assert_eq!(rec.data, 1i32.to_le_bytes().to_vec());
+
+    // конец очереди - ожидание завершается по таймауту
+    let version = follow.write_notify().version();
+    assert!(follow.next().is_none());
+
+    queue.lock().unwrap().switch().unwrap();
+    let second = queue.access(|q| q.write(&PreparedRecord::from(2))).unwrap();
+    assert!(follow.write_notify().version() >= version + 2);
+
+    // блок 0 нового лог файла - идентификатор лог файла
+    let (rid, _) = follow.next().unwrap().unwrap();
+    assert_eq!(rid.block_id.value(), 0);
+    assert_eq!(rid.log_file_id, second.log_file_id);
+
+    let (rid, rec) = follow.next().unwrap().unwrap();
+    assert_eq!(rec.data, 2i32.to_le_bytes().to_vec());
+    assert_eq!(rid, second);
+    assert_eq!(follow.position().block_id.value(), 2);
+
+    // позиция по номеру лог файла (из запроса) - без ссылки на предыдущий лог файл
+    let by_number = RecID {
+        log_file_id: LogQueueFileNumID { id: second.log_file_id.id, previous: None },
+        block_id: BlockId::new(2)
+    };
+    let mut follow = LogFollow::new(queue.clone(), by_number).unwrap()
+        .with_timeout(Duration::from_millis(10));
+    assert_eq!(follow.position().log_file_id, second.log_file_id);
+    assert!(follow.next().is_none());
+
+    queue.lock().unwrap().switch().unwrap();
+    let third = queue.access(|q| q.write(&PreparedRecord::from(3))).unwrap();
+    assert_eq!(follow.next().unwrap().unwrap().0.block_id.value(), 0);
+    let (rid, rec) = follow.next().unwrap().unwrap();
+    assert_eq!(rid, third);
+    assert_eq!(rec.data, 3i32.to_le_bytes().to_vec());
+}
@@ -1,10 +1,10 @@
-use std::cell::RefCell;
 use std::collections::HashMap;
 use core::fmt::Debug;
 use std::marker::PhantomData;
+use std::sync::{PoisonError, RwLock};
 
 use crate::logfile::{LogFile, FlatBuff};
-use super::{log_id::*, LoqErr, FindFiles, OpenLogFile, ValidateLogFiles, PreparedRecord, LogQueueImpl, LogQueue};
+use super::{log_id::*, LoqErr, FindFiles, OpenLogFile, ValidateLogFiles, PreparedRecord, LogQueueImpl, LogQueue, WriteNotify};
 
 use log::info;
 
@@ -19,6 +19,14 @@ where
     /// Возвращает идентификатор нового лог файла
     fn switch( &mut self ) -> Result<(FILE,LogId),LoqErr<FILE,LogId>>;
 
+    /// Переключение на лог файл с указанным идентификатором, а не следующим за актуальным
+    /// 
+    /// Цепочка лог файлов прерывается - используется репликой, которая начинает копирование
+    /// не с первого лог файла источника (первые лог файлы источника уже исключены).
+    /// 
+    /// Возвращает идентификатор нового лог файла
+    fn switch_to( &mut self, log_id: LogId ) -> Result<(FILE,LogId),LoqErr<FILE,LogId>>;
+
     /// Поиск лог файла по его ID
     /// 
     /// Аргументы
@@ -34,7 +42,7 @@ where
     /// 
     /// Аргументы
     /// ==============
-    /// - `id` идентификатор
+    /// - `id` идентификатор, лог ищется по номеру (`id()`), как и в [find_log](Self::find_log)
     /// - `offset` смещение
     ///    - `0` - возвращает сам аргумент `id`
     ///    - `-1` - предшедствующий указаному
@@ -54,10 +62,19 @@ where
     /// Работа с актуальным лог файлом
     fn tail( &self ) -> (LogId,FILE,LOG);
 
+    /// Уведомление о записи в очередь и переключении лог файла
+    fn write_notify( &self ) -> WriteNotify;
+
+    /// Исключение первого (самого старого) лог файла из очереди
+    /// 
+    /// Актуальный лог файл не исключается, сам файл не удаляется.
+    /// Оставшиеся лог файлы образуют частичную очередь, которую принимает [validate_sequence](super::validate_sequence)
+    /// 
+    /// Результат - исключенный лог файл или `None`, если в очереди только актуальный лог файл
+    fn remove_head( &mut self ) -> Result<Option<(LogId,FILE,LOG)>,LoqErr<FILE,LogId>>;
 }
 
 /// Очередь логов
-#[derive(Clone)]
 pub struct LogFileQueueImpl<LogId,FILE,BUFF,FNewFile,FOpen> 
 where
     BUFF: FlatBuff,
@@ -79,13 +96,48 @@ where
     open_file: FOpen,
 
     /// текущий id лога
-    current_log_id: RefCell<Option<LogId>>,
+    current_log_id: RwLock<Option<LogId>>,
 
     /// Кеш ид - лог файл
-    log_id_to_log: RefCell<Option<HashMap<LogId::ID,(FILE,LogFile<BUFF>)>>>,
+    log_id_to_log: RwLock<Option<HashMap<LogId::ID,(FILE,LogFile<BUFF>)>>>,
 
     /// Очередность id логов
-    log_id_order: RefCell<Option<Vec<LogId>>>,
+    log_id_order: RwLock<Option<Vec<LogId>>>,
+
+    /// Уведомление о записи
+    notify: WriteNotify,
+}
+
+impl<LogId,FILE,BUFF,FNewFile,FOpen> Clone for LogFileQueueImpl<LogId,FILE,BUFF,FNewFile,FOpen> 
+where
+    BUFF: FlatBuff,
+    FILE: Clone + Debug,
+    LogId: LogQueueFileId,
+    FNewFile: NewLogFile<FILE,LogId> + Clone,
+    FOpen: OpenLogFile<FILE,LogFile<BUFF>,LogId> + Clone
+{
+    fn clone(&self) -> Self {
+        Self { 
+            files: self.files.clone(), 
+            tail: self.tail.clone(), 
+            new_file: self.new_file.clone(), 
+            open_file: self.open_file.clone(), 
+            current_log_id: cached(&self.current_log_id), 
+            log_id_to_log: cached(&self.log_id_to_log), 
+            log_id_order: cached(&self.log_id_order), 
+            notify: self.notify.clone(),
+        }
+    }
+}
+
+/// Копия кеша
+fn cached<T:Clone>( lock:&RwLock<T> ) -> RwLock<T> {
+    RwLock::new(lock.read().unwrap_or_else(|err| err.into_inner()).clone())
+}
+
+/// Ошибка захвата блокировки кеша
+fn lock_err<FILE:Clone+Debug,LogId:Clone+Debug,G>( err:PoisonError<G> ) -> LoqErr<FILE,LogId> {
+    LoqErr::CantCaptureWriteLock { error: err.to_string() }
 }
 
 /// Очередь логов - базовые методы
@@ -116,31 +168,37 @@ where
             tail: tail, 
             new_file: new_file, 
             open_file: open_file,
-            current_log_id: RefCell::new(None),
-            log_id_to_log: RefCell::new(None),
-            log_id_order: RefCell::new(None),            
+            current_log_id: RwLock::new(None),
+            log_id_to_log: RwLock::new(None),
+            log_id_order: RwLock::new(None),
+            notify: WriteNotify::new(),
         }
     }
 
     /// Сброс кеша
     pub fn invalidate_cache( &self ) {
-        let mut r = self.log_id_to_log.borrow_mut();
-        *r = None;
-
-        let mut r = self.log_id_order.borrow_mut();
-        *r = None;
-
-        let mut r = self.current_log_id.borrow_mut();
-        *r = None;
+        *self.log_id_to_log.write().unwrap_or_else(|err| err.into_inner()) = None;
+        *self.log_id_order.write().unwrap_or_else(|err| err.into_inner()) = None;
+        *self.current_log_id.write().unwrap_or_else(|err| err.into_inner()) = None;
     }
 
     // пересоздание кеша, если необходимо и обход кеша
+    //
+    // читатели обходят готовый кеш под блокировкой на чтение,
+    // блокировка на запись берется только для построения кеша
     fn log_id_map_cache_read<R,F>( &self, default:R, consume:F ) -> Result<R,LoqErr<FILE,LogId>>
     where
         R: Sized,
         F: for <'a> Fn(&'a HashMap<LogId::ID,(FILE,LogFile<BUFF>)>) -> R,
     {
-        let mut cache_opt = self.log_id_to_log.borrow_mut();
+        {
+            let cache_opt = self.log_id_to_log.read().map_err(lock_err)?;
+            if let Some(cache) = cache_opt.as_ref() {
+                return Ok(consume(cache));
+            }
+        }
+
+        let mut cache_opt = self.log_id_to_log.write().map_err(lock_err)?;
         if cache_opt.is_none() {
             let mut cache : HashMap<LogId::ID,(FILE,LogFile<BUFF>)> = HashMap::new();
             for file_log in &self.files {
@@ -161,7 +219,14 @@ where
         R: Sized,
         F: for <'a> Fn(&'a Vec<LogId>) -> R
     {
-        let mut cache_opt = self.log_id_order.borrow_mut();
+        {
+            let cache_opt = self.log_id_order.read().map_err(lock_err)?;
+            if let Some(cache) = cache_opt.as_ref() {
+                return Ok(consume(cache));
+            }
+        }
+
+        let mut cache_opt = self.log_id_order.write().map_err(lock_err)?;
         if cache_opt.is_none() {
             let mut cache: Vec<LogId> = Vec::new();
             for file_log in &self.files {
@@ -181,7 +246,11 @@ where
         R: Sized,
         F: Fn(LogId) -> R 
     {
-        let mut cache_opt = self.current_log_id.borrow_mut();
+        if let Some(id) = *self.current_log_id.read().map_err(lock_err)? {
+            return Ok(consume(id));
+        }
+
+        let mut cache_opt = self.current_log_id.write().map_err(lock_err)?;
         if cache_opt.is_none() {            
             let id = LogId::read(&self.tail.1, &self.tail.2)?;
             *cache_opt = Some(id);
@@ -200,16 +269,21 @@ where
     FOpen: OpenLogFile<FILE,LogFile<BUFF>,LogId>
 {
     fn switch( &mut self ) -> Result<(FILE,LogId),LoqErr<FILE,LogId>> {
+        let new_log_id = self.current_log_id_read(|id| LogId::new(Some(id.id())))?;
+        self.switch_to(new_log_id)
+    }
+
+    fn switch_to( &mut self, new_log_id: LogId ) -> Result<(FILE,LogId),LoqErr<FILE,LogId>> {
         let file_name = self.new_file.new_log_file()?;
         let mut log_file = self.open_file.open_log_file(file_name.clone())?;
-        let new_log_id = self.current_log_id_read(|id| LogId::new(Some(id.id())))?;
         new_log_id.write(&file_name, &mut log_file)?;
         self.invalidate_cache();
 
         self.tail = (new_log_id.clone(),file_name.clone(),log_file);
         self.files.push( self.tail.clone() );
 
-        (*self.current_log_id.borrow_mut()) = Some(new_log_id);
+        (*self.current_log_id.write().map_err(lock_err)?) = Some(new_log_id);
+        self.notify.notify_all();
         Ok((file_name.clone(),new_log_id))
     }
 
@@ -231,7 +305,7 @@ where
 
         let idx = self.log_order_cache_read(None, |ids| {
             ids.iter().enumerate()
-                .find(|(_,found_id)| id == **found_id )
+                .find(|(_,found_id)| id.id() == found_id.id() )
                 .map(|(idx,_)| idx)
         })?;
 
@@ -258,6 +332,20 @@ where
     fn tail( &self ) -> (LogId,FILE,LogFile<BUFF>) {        
         self.tail.clone()
     }
+
+    fn write_notify( &self ) -> WriteNotify {
+        self.notify.clone()
+    }
+
+    fn remove_head( &mut self ) -> Result<Option<(LogId,FILE,LogFile<BUFF>)>,LoqErr<FILE,LogId>> {
+        if self.files.len() < 2 || self.files[0].0.id() == self.tail.0.id() {
+            return Ok(None);
+        }
+
+        let head = self.files.remove(0);
+        self.invalidate_cache();
+        Ok(Some(head))
+    }
 }
 
 //////////////////////////////////////////////////////////////////////
@@ -312,11 +400,15 @@ where
     FNewFile: NewLogFile<FILE,LogId>,
 {
     /// Открытие логов
+    /// 
+    /// Если лог файлов нет или все найденные лог файлы пустые, то создается новый лог файл
     pub fn open( &self ) -> 
     Result<LogFileQueueImpl<LogId,FILE,BUFF,FNewFile,FOpen>,LoqErr<FILE,LogId>> 
     {
         let found_files = self.find_files.find_files()?;
-        if !found_files.is_empty() {
+        if found_files.is_empty() {
+            info!("log files not found, try init");
+        } else {
             info!("found {cnt} files in {at:?}", cnt=&found_files.len(), at=&self.find_files);
 
             let not_validated_open_files = found_files.iter().fold( 
@@ -331,42 +423,63 @@ where
                 })
             })?;
 
-            let validated_order = 
-                self.validate.validate(&not_validated_open_files)?;
-
-            let queue = 
-            LogFileQueueImpl::new(
-                validated_order.files.iter().map(|(id,(file,log))|(id.clone(), file.clone(), log.clone())).collect(), 
-                (validated_order.tail.0, validated_order.tail.1.0, validated_order.tail.1.1), 
-                self.new_file.clone(), 
-                self.open_log_file.clone()
-            );
-
-            Ok(queue)
-        } else {
-            info!("log files not found, try init");
-
-            let file_name = (self.new_file.clone()).new_log_file()?;
-            let mut log_file = 
-                self.open_log_file.open_log_file(file_name.clone())?;
-            let id = LogId::new(None);
-            id.write(&file_name, &mut log_file)?;
-
-            let queue = 
-            LogFileQueueImpl::new(
-                vec![(id.clone(), file_name.clone(), log_file.clone())], 
-                (id.clone(), file_name, log_file), 
-                self.new_file.clone(), 
-                self.open_log_file.clone()
-            );
-
-            Ok(queue)
+            let has_blocks = not_validated_open_files.iter()
+                .any(|(_,log)| log.count().map(|cnt| cnt > 0).unwrap_or(true));
+            if has_blocks {
+                let validated_order = 
+                    self.validate.validate(&not_validated_open_files)?;
+
+                let queue = 
+                LogFileQueueImpl::new(
+                    validated_order.files.iter().map(|(id,(file,log))|(id.clone(), file.clone(), log.clone())).collect(), 
+                    (validated_order.tail.0, validated_order.tail.1.0, validated_order.tail.1.1), 
+                    self.new_file.clone(), 
+                    self.open_log_file.clone()
+                );
+
+                return Ok(queue);
+            }
+            info!("found only empty log files, try init");
         }
+
+        let file_name = (self.new_file.clone()).new_log_file()?;
+        let mut log_file = 
+            self.open_log_file.open_log_file(file_name.clone())?;
+        let id = LogId::new(None);
+        id.write(&file_name, &mut log_file)?;
+
+        let queue = 
+        LogFileQueueImpl::new(
+            vec![(id.clone(), file_name.clone(), log_file.clone())], 
+            (id.clone(), file_name, log_file), 
+            self.new_file.clone(), 
+            self.open_log_file.clone()
+        );
+
+        Ok(queue)
     }
 }
 
 pub trait LogWriteExt<RecordId,FILE:Clone+Debug,LogId:Clone+Debug> {
     fn append<Record: Into<PreparedRecord>>( &self, record:Record ) -> Result<RecordId,LoqErr<FILE,LogId>>;
+
+    /// Добавление записи, если последняя запись очереди совпадает с `expected`
+    /// 
+    /// Позволяет нескольким писателям согласовать запись без внешней блокировки:
+    /// если после чтения `expected` очередь была дополнена, возвращается [LoqErr::TailNotMatched]
+    fn append_if_tail<Record: Into<PreparedRecord>>( &self, expected:RecordId, record:Record ) -> Result<RecordId,LoqErr<FILE,LogId>>;
+}
+
+/// Проверка, что последняя запись очереди совпадает с ожидаемой
+pub fn check_tail<FILE:Clone+Debug,LogId:LogQueueFileId>( expected:&RecID<LogId>, actual:Option<RecID<LogId>> ) -> Result<(),LoqErr<FILE,LogId>> {
+    match &actual {
+        Some(actual) if actual.log_file_id.id() == expected.log_file_id.id() && actual.block_id == expected.block_id => Ok(()),
+        _ => Err(LoqErr::TailNotMatched { 
+            expected_log_id: expected.log_file_id, 
+            expected_block_id: expected.block_id, 
+            actual: actual.map(|rid| (rid.log_file_id, rid.block_id))
+        })
+    }
 }
 
 impl<'a,FILE:Clone+Debug,LogId:LogQueueFileId,BUFF:FlatBuff> LogWriteExt<RecID<LogId>,FILE,LogId> 
@@ -379,6 +492,17 @@ for LogQueueImpl<'a,LogId,FILE,BUFF>
         let q = self.queue.read()?;
         q.write(&rec)
     }
+
+    fn append_if_tail<Record: Into<PreparedRecord>>( &self, expected:RecID<LogId>, record:Record ) -> Result<RecID<LogId>,LoqErr<FILE,LogId>> {
+        use crate::logqueue::log_api::*;
+
+        let rec: PreparedRecord = record.into();
+        // блокировка на запись - между проверкой и записью очередь не может быть дополнена через этот же экземпляр
+        let q = self.queue.write()
+            .map_err(|err| LoqErr::CantCaptureWriteLock { error: err.to_string() })?;
+        check_tail(&expected, q.last_record()?)?;
+        q.write(&rec)
+    }
 }
 
 impl<'a,FILE:Clone+Debug,LogId:LogQueueFileId,LOG> LogWriteExt<RecID<LogId>,FILE,LogId>
@@ -387,6 +511,30 @@ for dyn LogQueue<RecID<LogId>, LogId, FILE, LOG> {
         let rec: PreparedRecord = record.into();
         self.write(&rec)
     }
+
+    fn append_if_tail<Record: Into<PreparedRecord>>( &self, expected:RecID<LogId>, record:Record ) -> Result<RecID<LogId>,LoqErr<FILE,LogId>> {
+        let rec: PreparedRecord = record.into();
+        check_tail(&expected, self.last_record()?)?;
+        self.write(&rec)
+    }
+}
+
+impl<'a,FILE:Clone+Debug,LogId:LogQueueFileId,BUFF:FlatBuff> LogWriteExt<RecID<LogId>,FILE,LogId>
+for dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + 'a {
+    fn append<Record: Into<PreparedRecord>>( &self, record:Record ) -> Result<RecID<LogId>,LoqErr<FILE,LogId>> {
+        use crate::logqueue::log_api::*;
+
+        let rec: PreparedRecord = record.into();
+        self.write(&rec)
+    }
+
+    fn append_if_tail<Record: Into<PreparedRecord>>( &self, expected:RecID<LogId>, record:Record ) -> Result<RecID<LogId>,LoqErr<FILE,LogId>> {
+        use crate::logqueue::log_api::*;
+
+        let rec: PreparedRecord = record.into();
+        check_tail(&expected, self.last_record()?)?;
+        self.write(&rec)
+    }
 }
 
 #[cfg(test)]
@@ -421,7 +569,7 @@ mod full_test {
 
     use crate::bbuff::absbuff::FileBuff;
     use crate::logfile::LogFile;
-    use crate::logqueue::{LogQueueFileNumIDOpen, ValidateStub, path_template, LogQueueImpl, LogQueue, LogWriteExt};
+    use crate::logqueue::{LogQueueFileNumIDOpen, ValidateStub, path_template, LogQueueImpl, LogQueue, LogWriteExt, LoqErr};
 
     use crate::logqueue::{log_id::*, LogQueueConf };
     use crate::logqueue::find_logs::FsLogFind;
@@ -500,6 +648,16 @@ mod full_test {
 
         let rec4 = log_queue.last_record().unwrap();
         println!("last rec = {:?}",rec4);
+
+        // запись с проверкой хвоста
+        let tail = rec4.unwrap();
+        let rec5 = log_queue.append_if_tail(tail.clone(), 36).unwrap();
+        assert!(rec5.block_id.value() > tail.block_id.value());
+        assert!(matches!(
+            log_queue.append_if_tail(tail, 38), 
+            Err(LoqErr::TailNotMatched { expected_log_id:_, expected_block_id:_, actual: Some(_) })
+        ));
+        assert_eq!(log_queue.last_record().unwrap(), Some(rec5));
         //let rec_id = log_queue.last
 
     }
@@ -2,7 +2,7 @@ use core::fmt::Debug;
 use std::sync::{Arc, RwLock};
 
 use crate::logfile::FlatBuff;
-use crate::logfile::block::FileOffset;
+use crate::logfile::block::{Block, FileOffset};
 use super::super::logfile::LogFile;
 use super::*;
 
@@ -62,6 +62,10 @@ where
         self.queue.write().unwrap().switch()
     }
 
+    fn switch_to( &mut self, log_id: LogId ) -> Result<(FILE,LogId),LoqErr<FILE,LogId>> {
+        self.queue.write().unwrap().switch_to(log_id)
+    }
+
     fn find_log( &self, id:LogId ) -> Result<Option<(FILE,LogFile<BUFF>)>,LoqErr<FILE,LogId>> {
         self.queue.read()?.find_log(id)
     }
@@ -81,6 +85,16 @@ where
     fn tail( &self ) -> (LogId,FILE,LogFile<BUFF>) {
         self.queue.read().unwrap().tail()
     }
+
+    fn write_notify( &self ) -> WriteNotify {
+        self.queue.read().unwrap().write_notify()
+    }
+
+    fn remove_head( &mut self ) -> Result<Option<(LogId,FILE,LogFile<BUFF>)>,LoqErr<FILE,LogId>> {
+        let mut q = self.queue.write()
+            .map_err(|err| LoqErr::CantCaptureWriteLock { error: err.to_string() })?;
+        q.remove_head()
+    }
 }
 
 impl<'a,LogId,FILE,BUFF> LogNavigationNear
@@ -103,6 +117,36 @@ where
     }
 }
 
+impl<'a,LogId,FILE,BUFF> LogOptionSearch<RecID<LogId>>
+for LogQueueImpl<'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff
+{
+    type FILE = FILE;
+    type LogId = LogId;
+
+    fn find_by_option( &self, key:&str, value:&str ) -> Result<Vec<RecID<LogId>>,LoqErr<Self::FILE,Self::LogId>> {
+        self.queue.read()?.find_by_option(key, value)
+    }
+}
+
+impl<'a,LogId,FILE,BUFF> LogTimeSeek<RecID<LogId>>
+for LogQueueImpl<'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff
+{
+    type FILE = FILE;
+    type LogId = LogId;
+
+    fn seek_time( &self, time:&chrono::DateTime<chrono::FixedOffset> ) -> Result<Option<RecID<LogId>>,LoqErr<Self::FILE,Self::LogId>> {
+        self.queue.read()?.seek_time(time)
+    }
+}
+
 impl<'a,LogId,FILE,BUFF> LogReading
 for LogQueueImpl<'a,LogId,FILE,BUFF>
 where
@@ -130,6 +174,24 @@ where
     }
 }
 
+impl<'a,LogId,FILE,BUFF> LogRawWriting<RecID<LogId>>
+for LogQueueImpl<'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff
+{
+    type FILE = FILE;
+    type LogId = LogId;
+
+    fn write_raw( &mut self, rec_id: RecID<LogId>, block:&Block ) -> Result<RecID<LogId>,LoqErr<Self::FILE,Self::LogId>>
+    {
+        let mut q = self.queue.write()
+            .map_err(|err| LoqErr::CantCaptureWriteLock { error: err.to_string() })?;
+        q.write_raw(rec_id, block)
+    }
+}
+
 impl<'a,LogId,FILE,BUFF> LogWriting<RecID<LogId>>
 for LogQueueImpl<'a,LogId,FILE,BUFF>
 where
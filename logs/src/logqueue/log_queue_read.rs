@@ -191,3 +191,53 @@ where
     }    
 }
 
+
+/// Идентификатор записи с идентификатором лог файла из очереди
+///
+/// Идентификатор, восстановленный по номеру лог файла (например из запроса), не содержит
+/// ссылки на предыдущий лог файл и не равен идентификатору из очереди.
+/// Если лог файла с таким номером нет, то идентификатор возвращается как есть
+pub fn resolve_rec_id<LogId, FILE, BUFF>(
+    queue: &(dyn LogFileQueue<LogId, FILE, LogFile<BUFF>> + '_),
+    record_id: RecID<LogId>
+) -> RecID<LogId>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    match queue.files().into_iter().find(|(log_id, _, _)| log_id.id() == record_id.log_file_id.id()) {
+        Some((log_file_id, _, _)) => RecID { log_file_id, block_id: record_id.block_id },
+        None => record_id
+    }
+}
+
+#[test]
+fn test_navigate_by_number() {
+    use crate::bbuff::absbuff::FileBuff;
+    use crate::logqueue::*;
+
+    let root = test_dir("navigate_by_number");
+    let mut queue = test_queue_conf(&root, LogQueueFileNumIDOpen, ValidateStub).open().unwrap();
+    for n in 0..3 {
+        if n > 0 { queue.switch().unwrap(); }
+        let q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &queue;
+        q.write(&PreparedRecord::from(n)).unwrap();
+    }
+    let q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &queue;
+
+    // номер лог файла без ссылки на предыдущий
+    let by_number = |log: u128, block: u32| RecID {
+        log_file_id: LogQueueFileNumID { id: log, previous: None },
+        block_id: BlockId::new(block)
+    };
+
+    let next = q.next_record(by_number(1, 1)).unwrap().unwrap();
+    assert_eq!(next.log_file_id.id, 2);
+    assert_eq!(next.log_file_id.previous, Some(1));
+    assert_eq!(q.previous_record(by_number(2, 0)).unwrap().unwrap().log_file_id.id, 1);
+
+    let resolved = resolve_rec_id(q, by_number(1, 1));
+    assert_eq!(resolved.log_file_id.previous, Some(0));
+    assert_eq!(resolve_rec_id(q, by_number(7, 1)), by_number(7, 1));
+}
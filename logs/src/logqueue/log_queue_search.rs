@@ -0,0 +1,185 @@
+use std::fmt::Debug;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::logfile::{LogFile, FlatBuff};
+
+use super::{LogOptionSearch, LogTimeSeek, log_id::{RecID, LogQueueFileId}, log_queue::LogFileQueue, LoqErr};
+
+/// Идентификаторы лог файлов в порядке следования: от первого к последнему
+fn ordered_log_ids<LogId, FILE, BUFF>( queue: &(dyn LogFileQueue<LogId, FILE, LogFile<BUFF>> + '_) ) -> Result<Vec<LogId>,LoqErr<FILE,LogId>> 
+where
+    LogId: LogQueueFileId,
+    BUFF: FlatBuff,
+    FILE: Clone + Debug,
+{
+    let mut log_ids = vec![queue.current_log_id()?];
+    while let Some(prev) = queue.offset_log_id(log_ids[log_ids.len()-1], -1)? {
+        log_ids.push(prev);
+    }
+    log_ids.reverse();
+    Ok(log_ids)
+}
+
+/// Поиск по опциям блока для dyn LogFileQueue
+impl<'a, LogId, FILE, BUFF> LogOptionSearch<RecID<LogId>>
+for dyn LogFileQueue<LogId, FILE, LogFile<BUFF>> + 'a
+where
+    LogId: LogQueueFileId,
+    BUFF: FlatBuff,
+    FILE: Clone + Debug,
+{
+    type FILE = FILE;
+    type LogId = LogId;
+
+    fn find_by_option( &self, key:&str, value:&str ) -> Result<Vec<RecID<LogId>>,LoqErr<FILE,LogId>> {
+        let mut result = Vec::<RecID<LogId>>::new();
+        for log_id in ordered_log_ids(self)? {
+            let (file,log) = match self.find_log(log_id)? {
+                Some(v) => v,
+                None => continue
+            };
+
+            let blocks = log.find_by_option(key, value).map_err(|err| 
+                LoqErr::LogFindByOption { file: file.clone(), error: err }
+            )?;
+
+            result.extend(blocks.into_iter().map(|block_id| RecID { 
+                log_file_id: log_id, 
+                block_id 
+            }));
+        }
+
+        Ok(result)
+    }
+}
+
+/// Поиск по времени для dyn LogFileQueue
+impl<'a, LogId, FILE, BUFF> LogTimeSeek<RecID<LogId>>
+for dyn LogFileQueue<LogId, FILE, LogFile<BUFF>> + 'a
+where
+    LogId: LogQueueFileId,
+    BUFF: FlatBuff,
+    FILE: Clone + Debug,
+{
+    type FILE = FILE;
+    type LogId = LogId;
+
+    fn seek_time( &self, time:&DateTime<FixedOffset> ) -> Result<Option<RecID<LogId>>,LoqErr<FILE,LogId>> {
+        let mut files = Vec::<(LogId,FILE,LogFile<BUFF>)>::new();
+        for log_id in ordered_log_ids(self)? {
+            if let Some((file,log)) = self.find_log(log_id)? {
+                files.push((log_id,file,log));
+            }
+        }
+
+        // время последней записи файла
+        let last_time = |idx:usize| {
+            let (_,file,log) = &files[idx];
+            log.last_time().map_err(|err| LoqErr::LogSeekTime { file: file.clone(), error: err })
+        };
+
+        // двоичный поиск первого файла, последняя запись которого не раньше указанного времени,
+        // файлы без записей со временем пропускаются
+        let mut lo = 0usize;
+        let mut hi = files.len();
+        let mut found: Option<usize> = None;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let mut probe = mid;
+            let mut probe_time = None;
+            while probe < hi {
+                probe_time = last_time(probe)?;
+                if probe_time.is_some() { break; }
+                probe += 1;
+            }
+            match probe_time {
+                None => hi = mid,
+                Some(t) if t < *time => lo = probe + 1,
+                Some(_) => {
+                    found = Some(probe);
+                    hi = mid;
+                }
+            }
+        }
+
+        match found {
+            None => Ok(None),
+            Some(idx) => {
+                let (log_id,file,log) = &files[idx];
+                let block_id = log.seek_time(time)
+                    .map_err(|err| LoqErr::LogSeekTime { file: file.clone(), error: err })?;
+                Ok(block_id.map(|block_id| RecID { log_file_id: *log_id, block_id }))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_find_by_option() {
+    use crate::logfile::block::BlockOptions;
+    use crate::logqueue::*;
+
+    let root = test_dir("find_by_option_queue");
+
+    let open = || {
+        let conf = test_queue_conf(&root, IndexedLogFileOpen { keys: vec!["mime".to_string()], offset_index: true }, ValidateStub);
+        LogQueueImpl::new(conf.open().unwrap())
+    };
+
+    let record = |mime:&str| {
+        let mut options = BlockOptions::default();
+        options.set("mime", mime).unwrap();
+        PreparedRecord { data: mime.as_bytes().to_vec(), options }
+    };
+
+    let rec1;
+    let rec2;
+    {
+        let mut queue = open();
+        rec1 = queue.write(&record("text/plain")).unwrap();
+        queue.write(&record("application/json")).unwrap();
+        queue.switch().unwrap();
+        rec2 = queue.write(&record("text/plain")).unwrap();
+    }
+
+    let queue = open();
+    let found = queue.find_by_option("mime", "text/plain").unwrap();
+    assert_eq!(found, vec![rec1, rec2]);
+
+    // поиск по не индексированному ключу
+    let found = queue.find_by_option("log_file_id_type", "LogQueueFileNumID").unwrap();
+    assert_eq!(found.len(), 2);
+}
+
+#[test]
+fn test_seek_time() {
+    use crate::logfile::{block::BlockOptions, parse_block_time, BLOCK_TIME_KEY};
+    use crate::logqueue::*;
+
+    let root = test_dir("seek_time_queue");
+
+    let conf = test_queue_conf(&root, LogQueueFileNumIDOpen, ValidateStub);
+    let mut queue = LogQueueImpl::new(conf.open().unwrap());
+
+    let record = |minute:u32| {
+        let mut options = BlockOptions::default();
+        options.set(BLOCK_TIME_KEY, format!("2023-07-21T22:{minute:02}:00+00:00").as_str()).unwrap();
+        PreparedRecord { data: vec![minute as u8], options }
+    };
+
+    let mut recs = vec![];
+    for minute in [1, 2, 3] { recs.push(queue.write(&record(minute)).unwrap()); }
+    queue.switch().unwrap();
+    queue.switch().unwrap();
+    for minute in [5, 7] { recs.push(queue.write(&record(minute)).unwrap()); }
+    queue.switch().unwrap();
+    for minute in [9] { recs.push(queue.write(&record(minute)).unwrap()); }
+
+    let t = |s:&str| parse_block_time(s).unwrap();
+    assert_eq!(queue.seek_time(&t("2023-07-21T00:00:00+00:00")).unwrap(), Some(recs[0].clone()));
+    assert_eq!(queue.seek_time(&t("2023-07-21T22:02:00+00:00")).unwrap(), Some(recs[1].clone()));
+    assert_eq!(queue.seek_time(&t("2023-07-21T22:04:00+00:00")).unwrap(), Some(recs[3].clone()));
+    assert_eq!(queue.seek_time(&t("2023-07-21T22:08:00+00:00")).unwrap(), Some(recs[5].clone()));
+    assert_eq!(queue.seek_time(&t("2023-07-21T22:10:00+00:00")).unwrap(), None);
+}
@@ -145,6 +145,18 @@ where
     fn tail( &self ) -> (LogId,FILE,LOG) {
         self.wrap.tail( (),self.target.tail() )
     }
+
+    fn write_notify( &self ) -> WriteNotify {
+        self.target.write_notify()
+    }
+
+    fn switch_to( &mut self, log_id: LogId ) -> Result<(FILE,LogId),LoqErr<FILE,LogId>> {
+        self.target.switch_to(log_id)
+    }
+
+    fn remove_head( &mut self ) -> Result<Option<(LogId,FILE,LOG)>,LoqErr<FILE,LogId>> {
+        self.target.remove_head()
+    }
 }
 
 impl<Q,L,LogId,FILE,LOG> LogNavigateLast<RecID<LogId>, FILE, LogId> for Wrapper<Q,L,LogId,FILE,LOG> 
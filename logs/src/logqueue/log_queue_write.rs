@@ -1,6 +1,7 @@
+use std::any::type_name;
 use std::fmt::Debug;
-use crate::logfile::{block::BlockOptions, LogFile, FlatBuff};
-use super::{LogWriting, RecID, LogFileQueue, LogQueueFileId, LoqErr, PreparedRecord};
+use crate::logfile::{block::{Block, BlockId, BlockOptions, DataId}, LogErr, LogFile, FlatBuff};
+use super::{LogWriting, LogRawWriting, RecID, LogFileQueue, LogQueueFileId, LoqErr, PreparedRecord};
 
 impl<'a,FILE,BUFF,LogId> LogWriting<RecID<LogId>> 
 for dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + 'a
@@ -14,21 +15,111 @@ where
 
     fn write( &self, record:&PreparedRecord ) -> Result<RecID<LogId>,LoqErr<Self::FILE,Self::LogId>> 
     {
-        //let prepared : PreparedRecord = record.into();
-        let (_,file, mut log) = self.tail();
-        let b_id = log.write_block(&record.options, &record.data)
-            .map_err(|err| 
-                LoqErr::LogDataWrite { 
-                    file: file.clone(),
-                    error: err 
-                }
-            )?;
-
-        let id = LogId::read(&file, &log)?;
-        Ok( RecID { log_file_id:id, block_id: b_id } )
+        write_data(self, DataId::user_data(), record)
     }
 }
 
+/// Добавление записи с указанным типом данных в текущий лог файл
+/// 
+/// [LogWriting::write] добавляет записи с типом [DataId::user_data()],
+/// другой тип сохраняется например при импорте ранее выгруженных блоков
+pub fn write_data<FILE,BUFF,LogId>( queue: &(dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + '_), data_id: DataId, record:&PreparedRecord ) -> Result<RecID<LogId>,LoqErr<FILE,LogId>>
+where 
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+    LogId: LogQueueFileId
+{
+    let (_,file, mut log) = queue.tail();
+    let b_id = log.write_data(data_id, &record.options, &record.data)
+        .map_err(|err| 
+            LoqErr::LogDataWrite { 
+                file: file.clone(),
+                error: err 
+            }
+        )?;
+
+    let id = LogId::read(&file, &log)?;
+    queue.write_notify().notify_all();
+    Ok( RecID { log_file_id:id, block_id: b_id } )
+}
+
+impl<'a,FILE,BUFF,LogId> LogRawWriting<RecID<LogId>> 
+for dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + 'a
+where 
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+    LogId: LogQueueFileId
+{
+    type FILE = FILE;
+    type LogId = LogId;
+
+    fn write_raw( &mut self, rec_id: RecID<LogId>, block:&Block ) -> Result<RecID<LogId>,LoqErr<Self::FILE,Self::LogId>> 
+    {
+        if raw_write_switches(self, &rec_id, block) {
+            let (cur_id, file, _) = self.tail();
+            let expected = LogId::new(Some(cur_id.id()));
+            let actual = LogId::block_read(block)
+                .map_err(|err| LoqErr::CantParseLogId { 
+                    file: file.clone(), 
+                    error: err, 
+                    log_id_type: type_name::<LogId>().to_string() 
+                })?;
+
+            if expected != actual || actual.id() != rec_id.log_file_id.id() {
+                return Err(LoqErr::RawWriteLogIdNotMatched { expected, actual });
+            }
+
+            let (_, log_id) = self.switch()?;
+            return Ok( RecID { log_file_id: log_id, block_id: BlockId::new(0) } );
+        }
+
+        write_raw_tail(self, rec_id, block)
+    }
+}
+
+/// Нужно ли переключение лог файла для записи блока в исходном виде
+/// 
+/// Блок 0 с идентификатором, отличным от текущего лог файла, - переключение лог файла,
+/// такая запись требует `&mut` доступа к очереди ([LogRawWriting::write_raw])
+pub fn raw_write_switches<FILE,BUFF,LogId>( queue: &(dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + '_), rec_id: &RecID<LogId>, block:&Block ) -> bool
+where 
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+    LogId: LogQueueFileId
+{
+    let (cur_id, _, _) = queue.tail();
+    block.head.block_id.value() == 0 && rec_id.log_file_id.id() != cur_id.id()
+}
+
+/// Запись блока в исходном виде в текущий лог файл, без переключения лог файла
+/// 
+/// Достаточно `&self` доступа к очереди, см. [raw_write_switches]
+pub fn write_raw_tail<FILE,BUFF,LogId>( queue: &(dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + '_), rec_id: RecID<LogId>, block:&Block ) -> Result<RecID<LogId>,LoqErr<FILE,LogId>>
+where 
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+    LogId: LogQueueFileId
+{
+    let (cur_id, file, mut log) = queue.tail();
+
+    if rec_id.log_file_id.id() != cur_id.id() {
+        return Err(LoqErr::RawWriteLogIdNotMatched { expected: cur_id, actual: rec_id.log_file_id });
+    }
+
+    if rec_id.block_id != block.head.block_id {
+        return Err(LoqErr::LogDataWrite { 
+            file, 
+            error: LogErr::BlockIdNotMatched { expected: rec_id.block_id, actual: block.head.block_id } 
+        });
+    }
+
+    let b_id = log.write_raw_block(block)
+        .map_err(|err| LoqErr::LogDataWrite { file, error: err })?;
+
+    queue.write_notify().notify_all();
+    Ok( RecID { log_file_id: cur_id, block_id: b_id } )
+}
+
 impl From<i32> for PreparedRecord {
     fn from(value: i32) -> Self {
         let mut data = Vec::<u8>::new();
@@ -38,4 +129,55 @@ impl From<i32> for PreparedRecord {
             options: BlockOptions::default()
         }
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_write_raw() {
+    use std::fs::read;
+    use std::path::PathBuf;
+    use crate::bbuff::absbuff::FileBuff;
+    use crate::logqueue::*;
+
+    let open = |name: &str| test_queue_conf(&test_dir(name), LogQueueFileNumIDOpen, ValidateStub).open().unwrap();
+
+    let mut src = open("write_raw_src");
+    let mut dst = open("write_raw_dst");
+
+    {
+        let q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &src;
+        q.write(&PreparedRecord::from(1)).unwrap();
+        q.write(&PreparedRecord::from(2)).unwrap();
+    }
+    src.switch().unwrap();
+    {
+        let q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &src;
+        q.write(&PreparedRecord::from(3)).unwrap();
+    }
+
+    let src_q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &src;
+    let dst_q: &mut dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &mut dst;
+
+    // блок 0 первого лог файла уже есть в обеих очередях
+    let mut rid = src_q.next_record(RecID { log_file_id: src_q.files()[0].0, block_id: BlockId::new(0) }).unwrap();
+    while let Some(src_rid) = rid {
+        let (file, log) = src_q.find_log(src_rid.log_file_id).unwrap().unwrap();
+        let block = log.read_block(src_rid.block_id)
+            .map_err(|err| LoqErr::<PathBuf,LogQueueFileNumID>::LogGetBlock { file, error: err, block_id: src_rid.block_id })
+            .unwrap();
+        let dst_rid = dst_q.write_raw(src_rid.clone(), &block).unwrap();
+        assert_eq!(dst_rid, src_rid);
+        rid = src_q.next_record(src_rid).unwrap();
+    }
+
+    assert_eq!(dst_q.last_record().unwrap(), src_q.last_record().unwrap());
+    for ((src_id, src_file, _), (dst_id, dst_file, _)) in src_q.files().into_iter().zip(dst_q.files()) {
+        assert_eq!(src_id, dst_id);
+        assert_eq!(read(src_file).unwrap(), read(dst_file).unwrap());
+    }
+
+    // повторная запись той же записи
+    let last = src_q.last_record().unwrap().unwrap();
+    let (_, log) = src_q.find_log(last.log_file_id).unwrap().unwrap();
+    let block = log.read_block(last.block_id).unwrap();
+    assert!(matches!(dst_q.write_raw(last, &block), Err(LoqErr::LogDataWrite { .. })));
+}
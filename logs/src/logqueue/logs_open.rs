@@ -1,8 +1,8 @@
 use std::fs::OpenOptions;
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
-use std::{path::PathBuf, fmt::Debug};
-use crate::{logfile::LogFile, bbuff::absbuff::FileBuff};
+use std::{path::{Path, PathBuf}, fmt::Debug};
+use crate::{logfile::{LogFile, OffsetIndex, OptionIndex}, bbuff::absbuff::FileBuff};
 use super::new_file::NewFileGenerator;
 use path_template::PathTemplateParser;
 use super::{log_seq_verifier::OrderedLogs, find_logs::FsLogFind, LoqErr, LogQueueFileNumID, validate_sequence, SeqValidateOp, IdOf};
@@ -58,6 +58,82 @@ impl OpenLogFile<PathBuf,LogFile<FileBuff>,LogQueueFileNumID> for LogQueueFileNu
     }
 }
 
+/// Открытие лог файла с индексами
+/// 
+/// Индексы хранятся рядом с лог файлом
+/// - вторичный индекс по опциям блока - в файле с расширением [OPTION_INDEX_EXT]
+/// - индекс смещений блоков - в файле с расширением [OFFSET_INDEX_EXT]
+/// 
+/// При отсуствии или повреждении файла индекса, индекс перестраивается.
+/// 
+/// Если список ключей пуст, то вторичный индекс не подключается
+#[derive(Clone,Debug)]
+pub struct IndexedLogFileOpen {
+    /// Индексируемые ключи опций блока
+    pub keys: Vec<String>,
+
+    /// Хранить индекс смещений блоков в файле
+    pub offset_index: bool,
+}
+
+/// Расширение файла вторичного индекса
+pub const OPTION_INDEX_EXT: &str = ".optidx";
+
+/// Расширение файла индекса смещений
+pub const OFFSET_INDEX_EXT: &str = ".offsets";
+
+/// Путь к файлу индекса смещений для указанного лог файла
+pub fn offset_index_file( log_file: &Path ) -> PathBuf {
+    let mut path = log_file.to_path_buf().into_os_string();
+    path.push(OFFSET_INDEX_EXT);
+    PathBuf::from(path)
+}
+
+/// Путь к файлу вторичного индекса для указанного лог файла
+pub fn option_index_file( log_file: &Path ) -> PathBuf {
+    let mut path = log_file.to_path_buf().into_os_string();
+    path.push(OPTION_INDEX_EXT);
+    PathBuf::from(path)
+}
+
+impl OpenLogFile<PathBuf,LogFile<FileBuff>,LogQueueFileNumID> for IndexedLogFileOpen {
+    fn open_log_file( &self, path:PathBuf ) -> Result<LogFile<FileBuff>, LoqErr<PathBuf,LogQueueFileNumID>> {
+        let log = LogQueueFileNumIDOpen.open_log_file(path.clone())?;
+
+        if self.offset_index {
+            let index_file = offset_index_file(&path);
+            OffsetIndex::open(&index_file)
+                .and_then(|index| log.attach_offset_index(index))
+                .map_err(|err| LoqErr::OpenOffsetIndex { 
+                    file: path.clone(), 
+                    index_file: index_file.clone(), 
+                    error: err 
+                })?;
+        }
+
+        if self.keys.is_empty() {
+            return Ok(log);
+        }
+
+        let index_file = option_index_file(&path);
+        let index = OptionIndex::open(&index_file, self.keys.clone())
+            .map_err(|err| LoqErr::OpenOptionIndex { 
+                file: path.clone(), 
+                index_file: index_file.clone(), 
+                error: err 
+            })?;
+
+        log.attach_option_index(index)
+            .map_err(|err| LoqErr::OpenOptionIndex { 
+                file: path.clone(), 
+                index_file: index_file.clone(), 
+                error: err 
+            })?;
+
+        Ok(log)
+    }
+}
+
 /// Валидация логов
 pub trait ValidateLogFiles<FILE,LOG,LogId> 
 where 
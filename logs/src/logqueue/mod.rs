@@ -60,6 +60,14 @@ pub use log_id::*;
 mod log_seq_verifier;
 pub use log_seq_verifier::*;
 
+/// Проверка очереди с отчетом о найденных проблемах
+mod validate;
+pub use validate::*;
+
+/// Восстановление очереди с нарушенной цепочкой лог файлов
+mod repair;
+pub use repair::*;
+
 /// Очередь лог файлов
 mod log_queue;
 pub use log_queue::*;
@@ -76,6 +84,46 @@ pub use log_queue_read::*;
 mod log_queue_write;
 pub use log_queue_write::*;
 
+/// Уведомление о записи
+mod write_notify;
+pub use write_notify::*;
+
+/// Чтение очереди с ожиданием новых записей
+mod log_follow;
+pub use log_follow::*;
+
+/// Очередь с одним писателем и параллельными читателями
+mod shared_queue;
+pub use shared_queue::*;
+
+/// Позиции потребителей очереди
+mod consumer_offsets;
+pub use consumer_offsets::*;
+
+/// Окно дедупликации записей по ключу идемпотентности
+mod idempotency;
+pub use idempotency::*;
+
+/// Политика хранения лог файлов
+mod retention;
+pub use retention::*;
+
+/// Автоматическое переключение лог файла
+mod rotation;
+pub use rotation::*;
+
+/// Архив лог файлов
+pub mod archive;
+
+/// Поиск по опциям блока
+mod log_queue_search;
+
+/// Очередь для тестов
+#[cfg(test)]
+mod test_queue;
+#[cfg(test)]
+pub(crate) use test_queue::*;
+
 mod log_queue_impl;
 pub use log_queue_impl::*;
 
@@ -88,7 +88,7 @@ where
             match path.parent() {
                 Some(parent) => {
                     if !parent.is_dir() {
-                        match create_dir_all(path.clone()) {
+                        match create_dir_all(parent) {
                             Ok(_) => {},
                             Err(e) => {
                                 error!("can't mkdir {dir:?} for new file {nf:?} by template {t:?} error {e}",
@@ -0,0 +1,590 @@
+//! Восстановление очереди с нарушенной цепочкой лог файлов
+//!
+//! По отчету проверки ([ValidateReport]) строится план ([RepairPlan]) - набор действий,
+//! после которых очередь открывается
+//!
+//! | Проблема                              | Действие                                                          |
+//! |---------------------------------------|-------------------------------------------------------------------|
+//! | пустой лог файл                       | удаление                                                          |
+//! | первый блок не идентификатор          | перенос в карантин                                                |
+//! | повтор идентификатора                 | в очереди остается лог файл с большим кол-вом блоков, остальные - в карантин |
+//! | несколько начальных лог файлов        | остается цепочка с большим кол-вом блоков, остальные - в карантин |
+//! | ветвление цепочки                     | остается ветвь с большим кол-вом блоков, остальные - в карантин   |
+//! | нет начального лог файла (цикл)       | лог файл с наименьшим идентификатором становится начальным        |
+//! | лог файл вне цепочки (разрыв)         | перепривязка к концу цепочки                                      |
+//!
+//! Перепривязка переписывает первый блок лог файла (идентификатор), идентификатор лог файла
+//! меняется на следующий за концом цепочки, соответственно меняются и идентификаторы записей (RecID).
+//!
+//! Повреждение блоков, ошибки чтения и расхождение контрольных сумм автоматически не исправляются.
+//!
+//! Восстановление выполняется над закрытой очередью, перед изменением лог файлы
+//! и их индексы копируются в каталог резервных копий.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{self, Debug, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bbuff::absbuff::FileBuff;
+use crate::logfile::LogFile;
+use crate::logfile::block::BlockId;
+use super::find_logs::FsLogFind;
+use super::{FindFiles, FileReport, LogQueueFileId, LogQueueFileNumID, QueueValidator, ValidateIssue, ValidateLevel, ValidateReport};
+use super::{offset_index_file, option_index_file};
+use super::retention::move_file;
+
+/// Действие над лог файлом
+#[derive(Debug,Clone)]
+pub enum RepairAction<FILE: Clone + Debug> {
+    /// Удаление пустого лог файла
+    Drop { file: FILE },
+
+    /// Перенос лог файла в каталог карантина
+    Quarantine { file: FILE, reason: String },
+
+    /// Замена идентификатора лог файла
+    Relink { file: FILE, from: LogQueueFileNumID, to: LogQueueFileNumID, reason: String },
+}
+
+impl<FILE: Clone + Debug> RepairAction<FILE> {
+    /// Изменяемый лог файл
+    pub fn file( &self ) -> &FILE {
+        match self {
+            Self::Drop { file } | Self::Quarantine { file, .. } | Self::Relink { file, .. } => file
+        }
+    }
+}
+
+impl<FILE: Clone + Debug> Display for RepairAction<FILE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Drop { file } => write!(f, "drop empty log file {file:?}"),
+            Self::Quarantine { file, reason } => write!(f, "quarantine log file {file:?}: {reason}"),
+            Self::Relink { file, from, to, reason } => write!(f, "relink log file {file:?} {from} -> {to}: {reason}"),
+        }
+    }
+}
+
+/// План восстановления очереди
+#[derive(Debug,Clone)]
+pub struct RepairPlan<FILE: Clone + Debug> {
+    /// Отчет проверки, по которому построен план
+    pub report: ValidateReport<FILE>,
+
+    /// Предлагаемые действия
+    pub actions: Vec<RepairAction<FILE>>,
+
+    /// Проблемы, которые не исправляются автоматически
+    pub unresolved: Vec<ValidateIssue<FILE>>,
+
+    /// Идентификаторы лог файлов в порядке цепочки после восстановления
+    pub chain: Vec<u128>,
+}
+
+impl<FILE: Clone + Debug> RepairPlan<FILE> {
+    /// Построение плана по отчету проверки
+    pub fn new( report: ValidateReport<FILE> ) -> Self {
+        let mut actions = Vec::<RepairAction<FILE>>::new();
+        let mut unresolved = Vec::<ValidateIssue<FILE>>::new();
+
+        for issue in &report.issues {
+            match issue {
+                ValidateIssue::EmptyFile { file } => actions.push(RepairAction::Drop { file: file.clone() }),
+                ValidateIssue::BadLogId { file, .. } => actions.push(RepairAction::Quarantine {
+                    file: file.clone(), reason: "first block is not a log id".to_string()
+                }),
+                ValidateIssue::ReadFailed { .. }
+                | ValidateIssue::BadBlock { .. }
+                | ValidateIssue::BadLink { .. }
+                | ValidateIssue::ChecksumMismatch { .. }
+                | ValidateIssue::ChecksumFailed { .. } => unresolved.push(issue.clone()),
+                // исправляются построением цепочки
+                ValidateIssue::NoHead
+                | ValidateIssue::TwoHeads { .. }
+                | ValidateIssue::DuplicateId { .. }
+                | ValidateIssue::Gap { .. }
+                | ValidateIssue::Fork { .. }
+                | ValidateIssue::Unlinked { .. } => {}
+            }
+        }
+
+        let chain = if report.is_ok() {
+            report.chain.clone()
+        } else {
+            Chain::new(&report.files).repair(&mut actions, &mut unresolved)
+        };
+
+        Self { report, actions, unresolved, chain }
+    }
+
+    /// Исправлять нечего
+    pub fn is_empty( &self ) -> bool {
+        self.actions.is_empty() && self.unresolved.is_empty()
+    }
+}
+
+impl<FILE: Clone + Debug> Display for RepairPlan<FILE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.report)?;
+        if self.is_empty() {
+            return writeln!(f, "nothing to repair");
+        }
+        for action in &self.actions {
+            writeln!(f, "action {action}")?;
+        }
+        for issue in &self.unresolved {
+            writeln!(f, "unresolved {}: {issue}", issue.kind())?;
+        }
+        writeln!(f, "chain after repair {:?}", self.chain)
+    }
+}
+
+/// Граф лог файлов по идентификаторам
+struct Chain<'a,FILE: Clone + Debug> {
+    /// Лог файлы с прочитанным идентификатором
+    nodes: BTreeMap<u128,Vec<&'a FileReport<FILE>>>,
+
+    /// Ссылки на предыдущий лог файл: предыдущий -> следующие
+    next: BTreeMap<u128,Vec<u128>>,
+}
+
+impl<'a,FILE: Clone + Debug> Chain<'a,FILE> {
+    fn new( files: &'a [FileReport<FILE>] ) -> Self {
+        let mut nodes = BTreeMap::<u128,Vec<&FileReport<FILE>>>::new();
+        for f in files {
+            if let Some(id) = f.log_id {
+                nodes.entry(id.id).or_default().push(f);
+            }
+        }
+        Self { nodes, next: BTreeMap::new() }
+    }
+
+    fn log_id( &self, id: u128 ) -> LogQueueFileNumID {
+        self.nodes[&id][0].log_id.unwrap()
+    }
+
+    fn file( &self, id: u128 ) -> FILE {
+        self.nodes[&id][0].file.clone()
+    }
+
+    /// Кол-во блоков в лог файле и всех следующих за ним
+    fn weight( &self, id: u128, visited: &mut HashSet<u128> ) -> u64 {
+        if !visited.insert(id) { return 0; }
+        let own = self.nodes[&id][0].blocks.unwrap_or(0) as u64;
+        let children = self.next.get(&id).cloned().unwrap_or_default();
+        own + children.iter().map(|c| self.weight(*c, visited)).sum::<u64>()
+    }
+
+    /// Лог файл с наибольшим кол-вом блоков, при равенстве - с наименьшим идентификатором
+    fn heaviest( &self, ids: &[u128] ) -> u128 {
+        let mut best = ids[0];
+        let mut best_weight = self.weight(best, &mut HashSet::new());
+        for id in &ids[1..] {
+            let weight = self.weight(*id, &mut HashSet::new());
+            if weight > best_weight {
+                best = *id;
+                best_weight = weight;
+            }
+        }
+        best
+    }
+
+    /// Перенос в карантин лог файла и всех следующих за ним
+    fn quarantine( &self, id: u128, reason: &str, removed: &mut HashSet<u128>, actions: &mut Vec<RepairAction<FILE>> ) {
+        if !removed.insert(id) { return; }
+        actions.push(RepairAction::Quarantine { file: self.file(id), reason: format!("log id {id}, {reason}") });
+        for child in self.next.get(&id).cloned().unwrap_or_default() {
+            self.quarantine(child, reason, removed, actions);
+        }
+    }
+
+    fn repair( mut self, actions: &mut Vec<RepairAction<FILE>>, unresolved: &mut Vec<ValidateIssue<FILE>> ) -> Vec<u128> {
+        // повтор идентификатора - остается лог файл с большим кол-вом блоков
+        for (id, list) in self.nodes.iter_mut() {
+            if list.len() < 2 { continue; }
+            let keep = (0..list.len()).fold(0, |best, i| {
+                let key = |f: &FileReport<FILE>| (f.blocks.unwrap_or(0), f.bytes.unwrap_or(0));
+                if key(list[i]) > key(list[best]) { i } else { best }
+            });
+            let kept = list.remove(keep);
+            for dup in list.drain(..) {
+                actions.push(RepairAction::Quarantine {
+                    file: dup.file.clone(),
+                    reason: format!("duplicate log id {id}, kept {:?}", kept.file)
+                });
+            }
+            list.push(kept);
+        }
+
+        for (id, list) in &self.nodes {
+            if let Some(previous) = list[0].log_id.and_then(|i| i.previous) {
+                self.next.entry(previous).or_default().push(*id);
+            }
+        }
+
+        if self.nodes.is_empty() {
+            return vec![];
+        }
+
+        let mut removed = HashSet::<u128>::new();
+
+        // начальный лог файл
+        let roots: Vec<u128> = self.nodes.keys().copied().filter(|id| self.log_id(*id).previous.is_none()).collect();
+        let head = if !roots.is_empty() {
+            let head = self.heaviest(&roots);
+            for root in roots.iter().filter(|r| **r != head) {
+                self.quarantine(*root, &format!("second head, queue starts from log id {head}"), &mut removed, actions);
+            }
+            head
+        } else {
+            let dangling: Vec<u128> = self.nodes.keys().copied()
+                .filter(|id| self.log_id(*id).previous.map(|p| !self.nodes.contains_key(&p)).unwrap_or(false))
+                .collect();
+            match dangling.first() {
+                // частичная очередь, начало удалено политикой хранения
+                Some(head) => *head,
+                // цикл ссылок
+                None => {
+                    let head = *self.nodes.keys().next().unwrap();
+                    actions.push(RepairAction::Relink {
+                        file: self.file(head),
+                        from: self.log_id(head),
+                        to: LogQueueFileNumID { id: head, previous: None },
+                        reason: "no head log file, lowest log id becomes head".to_string()
+                    });
+                    head
+                }
+            }
+        };
+
+        // цепочка от начального лог файла, при ветвлении остается ветвь с большим кол-вом блоков
+        let mut chain = vec![head];
+        let mut visited = HashSet::<u128>::from([head]);
+        let mut current = head;
+        loop {
+            let children: Vec<u128> = self.next.get(&current).cloned().unwrap_or_default()
+                .into_iter().filter(|c| !removed.contains(c) && !visited.contains(c)).collect();
+            if children.is_empty() { break; }
+
+            let keep = self.heaviest(&children);
+            for child in children.iter().filter(|c| **c != keep) {
+                self.quarantine(*child, &format!("fork after log id {current}, queue continues with log id {keep}"), &mut removed, actions);
+            }
+            current = keep;
+            visited.insert(current);
+            chain.push(current);
+        }
+
+        // лог файлы вне цепочки - перепривязка к концу цепочки в порядке идентификаторов
+        let orphans: Vec<u128> = self.nodes.keys().copied()
+            .filter(|id| !visited.contains(id) && !removed.contains(id))
+            .collect();
+        for id in orphans {
+            let tail = *chain.last().unwrap();
+            let to = LogQueueFileNumID::new(Some(tail));
+            if visited.contains(&to.id) {
+                unresolved.push(ValidateIssue::Unlinked { file: self.file(id), id });
+                continue;
+            }
+            actions.push(RepairAction::Relink {
+                file: self.file(id),
+                from: self.log_id(id),
+                to,
+                reason: format!("orphan log file, append after log id {tail}")
+            });
+            visited.insert(to.id);
+            chain.push(to.id);
+        }
+
+        chain
+    }
+}
+
+/// Ошибка восстановления очереди
+#[derive(Debug,Clone)]
+pub enum RepairErr {
+    /// Ошибка поиска лог файлов
+    Find { error: String },
+
+    /// Ошибка чтения/записи лог файла
+    Log { file: PathBuf, error: String },
+
+    /// Ошибка ввода/вывода
+    Io { file: PathBuf, error: String },
+}
+
+impl Display for RepairErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Find { error } => write!(f, "can't find log files: {error}"),
+            Self::Log { file, error } => write!(f, "log file {file:?}: {error}"),
+            Self::Io { file, error } => write!(f, "file {file:?}: {error}"),
+        }
+    }
+}
+
+fn io_err( file: &Path ) -> impl Fn(std::io::Error) -> RepairErr + '_ {
+    move |err| RepairErr::Io { file: file.to_path_buf(), error: err.to_string() }
+}
+
+fn log_err<E: Debug>( file: &Path ) -> impl Fn(E) -> RepairErr + '_ {
+    move |err| RepairErr::Log { file: file.to_path_buf(), error: format!("{err:?}") }
+}
+
+/// Параметры восстановления
+#[derive(Debug,Clone)]
+pub struct RepairOptions {
+    /// Только показать план, лог файлы не изменяются
+    pub dry_run: bool,
+
+    /// Каталог резервных копий изменяемых лог файлов
+    pub backup_dir: PathBuf,
+
+    /// Каталог карантина
+    pub quarantine_dir: PathBuf,
+}
+
+impl RepairOptions {
+    /// Каталоги резервных копий и карантина рядом с корнем очереди: `{root}.repair/{time}/backup`, `{root}.repair/{time}/quarantine`
+    pub fn beside( root: &Path, time: &str ) -> Self {
+        let mut dir = root.as_os_str().to_os_string();
+        dir.push(".repair");
+        let dir = PathBuf::from(dir).join(time);
+        Self { dry_run: false, backup_dir: dir.join("backup"), quarantine_dir: dir.join("quarantine") }
+    }
+}
+
+/// Результат восстановления
+#[derive(Debug,Clone,Default)]
+pub struct RepairDone {
+    /// Кол-во выполненных действий
+    pub applied: usize,
+
+    /// Резервные копии
+    pub backup: Vec<PathBuf>,
+}
+
+/// Лог файл и его индексы
+fn with_indexes( file: &Path ) -> Vec<PathBuf> {
+    [file.to_path_buf(), offset_index_file(file), option_index_file(file)]
+        .into_iter().filter(|f| f.exists()).collect()
+}
+
+/// Путь в каталоге с именем файла, к имени добавляется номер (`.1`, `.2` ...), если файл уже есть
+fn free_name_in_dir( file: &Path, dir: &Path ) -> Result<PathBuf,RepairErr> {
+    let name = file.file_name().ok_or_else(|| RepairErr::Io { file: file.to_path_buf(), error: "no file name".to_string() })?;
+    let mut target = dir.join(name);
+    let mut n = 1;
+    while target.exists() {
+        let mut numbered = name.to_os_string();
+        numbered.push(format!(".{n}"));
+        target = dir.join(numbered);
+        n += 1;
+    }
+    Ok(target)
+}
+
+/// Копирование файла в каталог с сохранением имени, существующий файл не заменяется
+fn copy_to_dir( file: &Path, dir: &Path ) -> Result<PathBuf,RepairErr> {
+    let target = free_name_in_dir(file, dir)?;
+    fs::copy(file, &target).map_err(io_err(file))?;
+    Ok(target)
+}
+
+/// Перенос лог файла и его индексов в каталог, существующие файлы не заменяются,
+/// индексы переименовываются вслед за лог файлом
+fn move_to_dir( file: &Path, dir: &Path ) -> Result<(),RepairErr> {
+    let target = free_name_in_dir(file, dir)?;
+    let moves = [
+        (file.to_path_buf(), target.clone()),
+        (offset_index_file(file), offset_index_file(&target)),
+        (option_index_file(file), option_index_file(&target)),
+    ];
+    for (from, to) in moves.iter().filter(|(from, _)| from.exists()) {
+        move_file(from, to).map_err(io_err(from))?;
+    }
+    Ok(())
+}
+
+/// Замена идентификатора: лог файл переписывается с новым первым блоком, индексы удаляются и перестраиваются при открытии
+fn rewrite_log_id( file: &Path, id: &LogQueueFileNumID ) -> Result<(),RepairErr> {
+    let mut tmp = file.as_os_str().to_os_string();
+    tmp.push(".repair");
+    let tmp = PathBuf::from(tmp);
+    if tmp.exists() { fs::remove_file(&tmp).map_err(io_err(&tmp))?; }
+
+    {
+        let src = LogFile::new(FileBuff::open_read_only(file).map_err(log_err(file))?).map_err(log_err(file))?;
+        let mut dst = LogFile::new(FileBuff::open_read_write(&tmp).map_err(log_err(&tmp))?).map_err(log_err(&tmp))?;
+        id.write(&tmp, &mut dst).map_err(log_err(&tmp))?;
+
+        let count = src.count().map_err(log_err(file))?;
+        for b in 1..count {
+            let block = src.read_block(BlockId::new(b)).map_err(log_err(file))?;
+            dst.write_raw_block(&block).map_err(log_err(&tmp))?;
+        }
+    }
+
+    fs::rename(&tmp, file).map_err(io_err(file))?;
+    for index in with_indexes(file).into_iter().skip_while(|f| f == file) {
+        fs::remove_file(&index).map_err(io_err(&index))?;
+    }
+    Ok(())
+}
+
+impl RepairPlan<PathBuf> {
+    /// Выполнение плана
+    ///
+    /// Каждый изменяемый лог файл (с индексами) предварительно копируется в [RepairOptions::backup_dir].
+    /// При [RepairOptions::dry_run] ничего не выполняется
+    pub fn apply( &self, options: &RepairOptions ) -> Result<RepairDone,RepairErr> {
+        let mut done = RepairDone::default();
+        if options.dry_run || self.actions.is_empty() {
+            return Ok(done);
+        }
+
+        fs::create_dir_all(&options.backup_dir).map_err(io_err(&options.backup_dir))?;
+        for action in &self.actions {
+            let file = action.file();
+            for f in with_indexes(file) {
+                done.backup.push(copy_to_dir(&f, &options.backup_dir)?);
+            }
+
+            match action {
+                RepairAction::Drop { .. } => {
+                    for f in with_indexes(file) {
+                        fs::remove_file(&f).map_err(io_err(&f))?;
+                    }
+                },
+                RepairAction::Quarantine { .. } => {
+                    fs::create_dir_all(&options.quarantine_dir).map_err(io_err(&options.quarantine_dir))?;
+                    move_to_dir(file, &options.quarantine_dir)?;
+                },
+                RepairAction::Relink { to, .. } => rewrite_log_id(file, to)?,
+            }
+            done.applied += 1;
+        }
+
+        Ok(done)
+    }
+}
+
+/// Проверка лог файлов очереди и план восстановления
+///
+/// Лог файлы открываются только на чтение и закрываются до возврата
+pub fn repair_plan( find: &FsLogFind, level: ValidateLevel ) -> Result<RepairPlan<PathBuf>,RepairErr> {
+    let files = FindFiles::<PathBuf,LogQueueFileNumID>::find_files(find)
+        .map_err(|err| RepairErr::Find { error: format!("{err:?}") })?;
+
+    let mut logs = Vec::<(PathBuf,LogFile<FileBuff>)>::new();
+    for file in files {
+        let buff = FileBuff::open_read_only(&file).map_err(log_err(&file))?;
+        let log = LogFile::new(buff).map_err(log_err(&file))?;
+        logs.push((file, log));
+    }
+
+    Ok(RepairPlan::new(QueueValidator::new(level).report(&logs)))
+}
+
+#[test]
+fn test_repair() {
+    use std::fs::{create_dir_all, remove_dir_all, remove_file};
+    use crate::logqueue::*;
+    use crate::logfile::block::DataId;
+
+    let base = PathBuf::from("./target/test/repair");
+    let root = base.join("queue");
+    if base.exists() { remove_dir_all(&base).unwrap(); }
+    create_dir_all(&root).unwrap();
+
+    let conf = || {
+        let conf = test_queue_conf(&root, LogQueueFileNumIDOpen, QueueValidator::new(ValidateLevel::Headers));
+        conf
+    };
+    let find = FsLogFind::new(root.to_str().unwrap(), "*.binlog", true).unwrap();
+    let options = RepairOptions { dry_run: false, backup_dir: base.join("backup"), quarantine_dir: base.join("quarantine") };
+    let repair = || {
+        let plan = repair_plan(&find, ValidateLevel::Headers).unwrap();
+        plan.apply(&options).unwrap();
+        plan
+    };
+
+    // очередь из трех лог файлов
+    let mut files = vec![];
+    {
+        let mut queue = conf().open().unwrap();
+        for i in 0..3 {
+            let q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &queue;
+            q.write(&PreparedRecord::from(i)).unwrap();
+            if i < 2 { queue.switch().unwrap(); }
+        }
+        for (_, file, _) in queue.files() { files.push(file); }
+    }
+
+    let plan = repair_plan(&find, ValidateLevel::Headers).unwrap();
+    assert!(plan.is_empty(), "{plan}");
+
+    // повтор идентификатора и пустой лог файл
+    fs::copy(&files[2], root.join("copy.binlog")).unwrap();
+    fs::write(root.join("empty.binlog"), b"").unwrap();
+    let plan = repair_plan(&find, ValidateLevel::Headers).unwrap();
+    assert_eq!(plan.actions.len(), 2, "{plan}");
+    assert!(conf().open().is_err());
+
+    // пробный прогон ничего не меняет
+    let done = plan.apply(&RepairOptions { dry_run: true, ..options.clone() }).unwrap();
+    assert_eq!(done.applied, 0);
+    assert!(root.join("empty.binlog").exists());
+
+    let done = plan.apply(&options).unwrap();
+    assert_eq!(done.applied, 2);
+    assert!(!root.join("empty.binlog").exists());
+    assert_eq!(fs::read_dir(&options.quarantine_dir).unwrap().count(), 1);
+    assert_eq!(fs::read_dir(&options.backup_dir).unwrap().count(), 2);
+    assert_eq!(conf().open().unwrap().files().len(), 3);
+
+    // второй начальный лог файл
+    {
+        let file = root.join("head.binlog");
+        let mut log = LogFile::new(FileBuff::open_read_write(&file).unwrap()).unwrap();
+        LogQueueFileNumID { id: 10, previous: None }.write(&file, &mut log).unwrap();
+    }
+    let plan = repair();
+    assert!(matches!(&plan.actions[..], [RepairAction::Quarantine { file, .. }] if file.ends_with("head.binlog")), "{plan}");
+    assert_eq!(conf().open().unwrap().files().len(), 3);
+
+    // в карантине уже есть файл с таким именем - прежний файл не заменяется
+    {
+        create_dir_all(root.join("sub")).unwrap();
+        let file = root.join("sub").join("head.binlog");
+        let mut log = LogFile::new(FileBuff::open_read_write(&file).unwrap()).unwrap();
+        LogQueueFileNumID { id: 20, previous: None }.write(&file, &mut log).unwrap();
+    }
+    repair();
+    assert!(options.quarantine_dir.join("head.binlog").exists());
+    assert!(options.quarantine_dir.join("head.binlog.1").exists());
+
+    // блок с собственным типом данных в последнем лог файле
+    // (из повторов идентификатора в очереди мог остаться файл copy.binlog)
+    {
+        let (_, tail, _) = conf().open().unwrap().tail();
+        let mut log = LogFile::new(FileBuff::open_read_write(&tail).unwrap()).unwrap();
+        let mut block = log.read_block(BlockId::new(1)).unwrap();
+        block.head.block_id = BlockId::new(2);
+        block.head.data_type_id = DataId::new(7);
+        log.write_raw_block(&block).unwrap();
+    }
+
+    // разрыв цепочки - лог файл перепривязывается
+    remove_file(&files[1]).unwrap();
+    let plan = repair();
+    assert!(matches!(&plan.actions[..], [RepairAction::Relink { to: LogQueueFileNumID { id: 1, previous: Some(0) }, .. }]), "{plan}");
+    assert_eq!(plan.chain, vec![0, 1]);
+
+    let queue = conf().open().unwrap();
+    assert_eq!(queue.files().len(), 2);
+    let (_, _, log) = queue.tail();
+    assert_eq!(log.count().unwrap(), 3);
+    assert_eq!(log.read_block(BlockId::new(2)).unwrap().head.data_type_id, DataId::new(7));
+}
@@ -0,0 +1,280 @@
+//! Политика хранения лог файлов
+//!
+//! Самые старые лог файлы исключаются из очереди ([LogFileQueue::remove_head]),
+//! после чего удаляются или переносятся в каталог архива вместе с файлами индексов.
+//!
+//! Исключаются только файлы с начала очереди и никогда актуальный лог файл,
+//! поэтому оставшиеся файлы образуют непрерывную частичную очередь.
+
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, FixedOffset, Utc};
+
+use crate::logfile::{LogFile, FlatBuff};
+
+use super::{LogFileQueue, LogQueueFileId, LoqErr, offset_index_file, option_index_file};
+
+/// Ограничения хранения лог файлов, `None` - без ограничения
+#[derive(Debug, Clone, Default)]
+pub struct Retention {
+    /// Максимальный суммарный размер лог файлов в байтах
+    pub max_bytes: Option<u64>,
+
+    /// Максимальный возраст последней записи лог файла
+    pub max_age: Option<Duration>,
+
+    /// Максимальное кол-во лог файлов
+    pub max_files: Option<usize>,
+}
+
+/// Сведения о лог файле для проверки ограничений
+#[derive(Debug, Clone)]
+pub struct RetentionFile {
+    /// Размер в байтах
+    pub bytes: u64,
+
+    /// Время последней записи
+    pub time: Option<DateTime<FixedOffset>>,
+}
+
+impl Retention {
+    /// Кол-во самых старых лог файлов, которые нарушают ограничения
+    ///
+    /// Аргументы
+    /// - `files` - лог файлы от старых к новым, последний - актуальный, он не учитывается как устаревший
+    /// - `now` - текущее время
+    pub fn expired_count( &self, files: &[RetentionFile], now: DateTime<FixedOffset> ) -> usize {
+        let removable = files.len().saturating_sub(1);
+        let mut count = 0usize;
+
+        if let Some(max_files) = self.max_files {
+            count = count.max(files.len().saturating_sub(max_files));
+        }
+
+        if let Some(max_bytes) = self.max_bytes {
+            let mut total: u64 = files.iter().map(|f| f.bytes).sum();
+            let mut idx = 0usize;
+            while total > max_bytes && idx < removable {
+                total -= files[idx].bytes;
+                idx += 1;
+            }
+            count = count.max(idx);
+        }
+
+        if let Some(max_age) = self.max_age.and_then(|age| chrono::Duration::from_std(age).ok()) {
+            let oldest = now - max_age;
+            for (idx, file) in files.iter().enumerate().take(removable) {
+                if file.time.map(|t| t < oldest).unwrap_or(false) {
+                    count = count.max(idx + 1);
+                }
+            }
+        }
+
+        count.min(removable)
+    }
+
+    /// Ограничения не заданы
+    pub fn is_empty( &self ) -> bool {
+        self.max_bytes.is_none() && self.max_age.is_none() && self.max_files.is_none()
+    }
+}
+
+/// Что делать с устаревшим лог файлом
+#[derive(Debug, Clone)]
+pub enum RetentionAction {
+    /// Удалить
+    Delete,
+
+    /// Перенести в каталог
+    Archive(PathBuf),
+}
+
+/// Сведения о лог файле: размер файла и время последней записи (опция `time` блока),
+/// если записей со временем нет - время изменения файла
+fn retention_file<BUFF: FlatBuff>( file: &Path, log: &LogFile<BUFF> ) -> RetentionFile {
+    let meta = fs::metadata(file).ok();
+    let time = log.last_time().ok().flatten().or_else(|| {
+        meta.as_ref()
+            .and_then(|m| m.modified().ok())
+            .map(|t: SystemTime| DateTime::<Utc>::from(t).fixed_offset())
+    });
+    RetentionFile { bytes: meta.map(|m| m.len()).unwrap_or(0), time }
+}
+
+/// Перенос файла, если переименовать не удалось (другая файловая система) - копирование и удаление
+pub(super) fn move_file( from: &Path, to: &Path ) -> std::io::Result<()> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+/// Удаление или перенос лог файла и его индексов
+pub fn dispose_file( file: &Path, action: &RetentionAction ) -> std::io::Result<()> {
+    let files = [file.to_path_buf(), offset_index_file(file), option_index_file(file)];
+    for (idx, f) in files.iter().enumerate() {
+        // индексы есть не всегда
+        if idx > 0 && !f.exists() { continue; }
+        match action {
+            RetentionAction::Delete => fs::remove_file(f)?,
+            RetentionAction::Archive(dir) => {
+                fs::create_dir_all(dir)?;
+                let name = f.file_name().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "file name"))?;
+                move_file(f, &dir.join(name))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Применение ограничений хранения к очереди
+///
+/// Аргументы
+/// - `queue` - очередь
+/// - `retention` - ограничения
+/// - `action` - что делать с устаревшими лог файлами
+/// - `now` - текущее время
+///
+/// Результат - исключенные из очереди лог файлы
+pub fn apply_retention<'a,LogId,BUFF>(
+    queue: &mut (dyn LogFileQueue<LogId,PathBuf,LogFile<BUFF>> + 'a),
+    retention: &Retention,
+    action: &RetentionAction,
+    now: DateTime<FixedOffset>,
+) -> Result<Vec<PathBuf>,LoqErr<PathBuf,LogId>>
+where
+    LogId: LogQueueFileId,
+    BUFF: FlatBuff,
+{
+    apply_retention_if(queue, retention, action, now, |_| true)
+}
+
+/// Применение ограничений хранения к очереди, только для лог файлов, которые можно исключить
+///
+/// Лог файлы исключаются с начала очереди, пока `removable` возвращает `true` для первого лог файла,
+/// например пока лог файл выгружен в архив
+///
+/// Аргументы как у [apply_retention], `removable` - можно ли исключить лог файл
+pub fn apply_retention_if<'a,LogId,BUFF,F>(
+    queue: &mut (dyn LogFileQueue<LogId,PathBuf,LogFile<BUFF>> + 'a),
+    retention: &Retention,
+    action: &RetentionAction,
+    now: DateTime<FixedOffset>,
+    removable: F,
+) -> Result<Vec<PathBuf>,LoqErr<PathBuf,LogId>>
+where
+    LogId: LogQueueFileId,
+    BUFF: FlatBuff,
+    F: Fn(&LogId) -> bool,
+{
+    if retention.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let files: Vec<RetentionFile> = queue.files().iter()
+        .map(|(_, file, log)| retention_file(file, log))
+        .collect();
+
+    let mut removed = Vec::<PathBuf>::new();
+    for _ in 0..retention.expired_count(&files, now) {
+        match queue.files().first() {
+            Some((log_id, _, _)) if removable(log_id) => {},
+            _ => break
+        }
+        let (_, file, _) = match queue.remove_head()? {
+            Some(head) => head,
+            None => break
+        };
+        dispose_file(&file, action)
+            .map_err(|err| LoqErr::DisposeLogFile { file: file.clone(), error: err.to_string() })?;
+        removed.push(file);
+    }
+
+    Ok(removed)
+}
+
+#[test]
+fn test_expired_count() {
+    let t = |s: &str| DateTime::parse_from_rfc3339(s).unwrap();
+    let file = |bytes: u64, time: &str| RetentionFile { bytes, time: Some(t(time)) };
+    let now = t("2023-07-21T12:00:00+00:00");
+
+    let files = vec![
+        file(100, "2023-07-20T10:00:00+00:00"),
+        file(100, "2023-07-21T08:00:00+00:00"),
+        file(100, "2023-07-21T11:00:00+00:00"),
+        file(10, "2023-07-21T11:59:00+00:00"),
+    ];
+
+    assert_eq!(Retention::default().expired_count(&files, now), 0);
+    assert_eq!(Retention { max_files: Some(2), ..Retention::default() }.expired_count(&files, now), 2);
+    assert_eq!(Retention { max_bytes: Some(250), ..Retention::default() }.expired_count(&files, now), 1);
+    assert_eq!(Retention { max_bytes: Some(200), ..Retention::default() }.expired_count(&files, now), 2);
+    assert_eq!(Retention { max_age: Some(Duration::from_secs(5 * 3600)), ..Retention::default() }.expired_count(&files, now), 1);
+    assert_eq!(
+        Retention { max_age: Some(Duration::from_secs(5 * 3600)), max_files: Some(3), ..Retention::default() }.expired_count(&files, now),
+        1
+    );
+
+    // актуальный лог файл не исключается
+    assert_eq!(Retention { max_files: Some(0), ..Retention::default() }.expired_count(&files, now), 3);
+    assert_eq!(Retention { max_bytes: Some(0), ..Retention::default() }.expired_count(&files, now), 3);
+    assert_eq!(Retention { max_age: Some(Duration::from_secs(0)), ..Retention::default() }.expired_count(&files, now), 3);
+}
+
+#[test]
+fn test_apply_retention() {
+    use std::fs::{create_dir_all, remove_dir_all};
+    use crate::bbuff::absbuff::FileBuff;
+    use crate::logqueue::*;
+
+    let root = PathBuf::from("./target/test/retention");
+    let archive = PathBuf::from("./target/test/retention_archive");
+    for dir in [&root, &archive] {
+        if dir.exists() { remove_dir_all(dir).unwrap(); }
+    }
+    create_dir_all(&root).unwrap();
+
+    let conf = test_queue_conf(&root, LogQueueFileNumIDOpen, ValidateStub);
+
+    let mut queue = conf.open().unwrap();
+    for n in 0..4 {
+        let q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &queue;
+        q.write(&PreparedRecord::from(n)).unwrap();
+        queue.switch().unwrap();
+    }
+    let files: Vec<PathBuf> = queue.files().into_iter().map(|(_, f, _)| f).collect();
+    assert_eq!(files.len(), 5);
+
+    let now = Utc::now().fixed_offset();
+    let q: &mut dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &mut queue;
+    let removed = apply_retention(q, &Retention { max_files: Some(3), ..Retention::default() }, &RetentionAction::Archive(archive.clone()), now).unwrap();
+    assert_eq!(removed, files[0..2].to_vec());
+    assert!(!files[0].exists());
+    assert!(archive.join(files[0].file_name().unwrap()).exists());
+
+    let removed = apply_retention(q, &Retention { max_files: Some(2), ..Retention::default() }, &RetentionAction::Delete, now).unwrap();
+    assert_eq!(removed, files[2..3].to_vec());
+    assert!(!files[2].exists());
+    assert_eq!(q.files().len(), 2);
+    assert!(q.find_log(LogQueueFileNumID { id: 2, previous: None }).unwrap().is_none());
+
+    // первый лог файл нельзя исключить - следующие тоже не исключаются
+    let removed = apply_retention_if(q, &Retention { max_files: Some(1), ..Retention::default() }, &RetentionAction::Delete, now, |id| id.id != 3).unwrap();
+    assert!(removed.is_empty());
+    assert_eq!(q.files().len(), 2);
+
+    // оставшаяся часть очереди открывается как частичная очередь
+    drop(queue);
+    let queue = conf.open().unwrap();
+    let q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &queue;
+    let ids: Vec<u128> = q.files().iter().map(|(id, _, _)| id.id).collect();
+    assert_eq!(ids, vec![3, 4]);
+    assert_eq!(q.current_log_id().unwrap().id, 4);
+    let rid = q.write(&PreparedRecord::from(5)).unwrap();
+    assert_eq!(rid.log_file_id.id, 4);
+}
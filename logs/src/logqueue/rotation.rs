@@ -0,0 +1,179 @@
+//! Автоматическое переключение актуального лог файла
+//!
+//! Актуальный лог файл переключается ([LogFileQueue::switch]), когда он превышает
+//! размер, кол-во блоков или прошло указанное время с первой записи в нем.
+//!
+//! Лог файл, в котором нет записей кроме блока с идентификатором, не переключается.
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::logfile::{LogFile, FlatBuff};
+
+use super::{LogFileQueue, LogQueueFileId, LoqErr};
+
+/// Условия переключения лог файла, `None` - условие не проверяется
+#[derive(Debug, Clone, Default)]
+pub struct Rotation {
+    /// Размер лог файла в байтах, при достижении которого он переключается
+    pub max_bytes: Option<u64>,
+
+    /// Кол-во блоков (включая блок с идентификатором), при достижении которого лог файл переключается
+    pub max_blocks: Option<u32>,
+
+    /// Время с первой записи (опция `time` блока), по истечении которого лог файл переключается
+    pub interval: Option<Duration>,
+}
+
+/// Состояние актуального лог файла
+#[derive(Debug, Clone)]
+pub struct RotationTail {
+    /// Размер в байтах
+    pub bytes: u64,
+
+    /// Кол-во блоков
+    pub blocks: u32,
+
+    /// Время первой записи
+    pub first_time: Option<DateTime<FixedOffset>>,
+}
+
+impl Rotation {
+    /// Условия не заданы
+    pub fn is_empty( &self ) -> bool {
+        self.max_bytes.is_none() && self.max_blocks.is_none() && self.interval.is_none()
+    }
+
+    /// Проверка, что лог файл следует переключить
+    ///
+    /// Аргументы
+    /// - `tail` - состояние актуального лог файла
+    /// - `now` - текущее время
+    pub fn is_due( &self, tail: &RotationTail, now: DateTime<FixedOffset> ) -> bool {
+        if tail.blocks < 2 {
+            return false;
+        }
+
+        self.max_bytes.map(|max| tail.bytes >= max).unwrap_or(false)
+        || self.max_blocks.map(|max| tail.blocks >= max).unwrap_or(false)
+        || self.interval
+            .and_then(|interval| chrono::Duration::from_std(interval).ok())
+            .zip(tail.first_time)
+            .map(|(interval, first)| now - first >= interval)
+            .unwrap_or(false)
+    }
+}
+
+/// Проверка, что актуальный лог файл следует переключить, без изменения очереди
+///
+/// Достаточно доступа читателя, переключение выполняется [rotate_if_due] под монопольным доступом
+///
+/// Аргументы
+/// - `queue` - очередь
+/// - `rotation` - условия переключения
+/// - `now` - текущее время
+pub fn rotation_due<'a,LogId,FILE,BUFF>(
+    queue: &(dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + 'a),
+    rotation: &Rotation,
+    now: DateTime<FixedOffset>,
+) -> Result<bool,LoqErr<FILE,LogId>>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    if rotation.is_empty() {
+        return Ok(false);
+    }
+
+    let (_, file, log) = queue.tail();
+    let count_err = |error| LoqErr::LogCountFail { file: file.clone(), error };
+    let tail = RotationTail {
+        bytes: log.bytes_count().map_err(count_err)?,
+        blocks: log.count().map_err(count_err)?,
+        first_time: if rotation.interval.is_some() { log.first_time().map_err(count_err)? } else { None },
+    };
+
+    Ok(rotation.is_due(&tail, now))
+}
+
+/// Переключение актуального лог файла, если выполнено одно из условий
+///
+/// Аргументы
+/// - `queue` - очередь
+/// - `rotation` - условия переключения
+/// - `now` - текущее время
+///
+/// Результат - новый лог файл или `None`, если переключение не требуется
+pub fn rotate_if_due<'a,LogId,FILE,BUFF>(
+    queue: &mut (dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + 'a),
+    rotation: &Rotation,
+    now: DateTime<FixedOffset>,
+) -> Result<Option<(FILE,LogId)>,LoqErr<FILE,LogId>>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    if !rotation_due(queue, rotation, now)? {
+        return Ok(None);
+    }
+
+    Ok(Some(queue.switch()?))
+}
+
+#[test]
+fn test_rotation_is_due() {
+    let t = |s: &str| DateTime::parse_from_rfc3339(s).unwrap();
+    let now = t("2023-07-21T12:00:00+00:00");
+    let tail = |bytes: u64, blocks: u32, first_time: Option<&str>| RotationTail { bytes, blocks, first_time: first_time.map(t) };
+
+    let by_size = Rotation { max_bytes: Some(1000), ..Rotation::default() };
+    assert!(!by_size.is_due(&tail(999, 10, None), now));
+    assert!(by_size.is_due(&tail(1000, 10, None), now));
+
+    let by_blocks = Rotation { max_blocks: Some(10), ..Rotation::default() };
+    assert!(!by_blocks.is_due(&tail(5000, 9, None), now));
+    assert!(by_blocks.is_due(&tail(0, 10, None), now));
+
+    let by_time = Rotation { interval: Some(Duration::from_secs(3600)), ..Rotation::default() };
+    assert!(!by_time.is_due(&tail(0, 5, Some("2023-07-21T11:30:00+00:00")), now));
+    assert!(by_time.is_due(&tail(0, 5, Some("2023-07-21T11:00:00+00:00")), now));
+    assert!(!by_time.is_due(&tail(0, 5, None), now));
+
+    // лог файл только с идентификатором не переключается
+    assert!(!Rotation { max_bytes: Some(0), max_blocks: Some(0), ..Rotation::default() }.is_due(&tail(100, 1, None), now));
+    assert!(!Rotation::default().is_due(&tail(u64::MAX, u32::MAX, Some("2000-01-01T00:00:00+00:00")), now));
+}
+
+#[test]
+fn test_rotate_if_due() {
+    use std::path::PathBuf;
+    use chrono::Utc;
+    use crate::bbuff::absbuff::FileBuff;
+    use crate::logqueue::*;
+
+    let root = test_dir("rotation");
+
+    let conf = test_queue_conf(&root, LogQueueFileNumIDOpen, ValidateStub);
+
+    let mut queue = conf.open().unwrap();
+    let q: &mut dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &mut queue;
+    let rotation = Rotation { max_blocks: Some(3), ..Rotation::default() };
+    let now = Utc::now().fixed_offset();
+
+    assert!(rotate_if_due(q, &rotation, now).unwrap().is_none());
+    q.write(&PreparedRecord::from(1)).unwrap();
+    assert!(rotate_if_due(q, &rotation, now).unwrap().is_none());
+    assert!(!rotation_due(q, &rotation, now).unwrap());
+    q.write(&PreparedRecord::from(2)).unwrap();
+    assert!(rotation_due(q, &rotation, now).unwrap());
+
+    let (_, log_id) = rotate_if_due(q, &rotation, now).unwrap().unwrap();
+    assert_eq!(log_id.id, 1);
+    assert_eq!(q.current_log_id().unwrap().id, 1);
+    assert!(!rotation_due(q, &rotation, now).unwrap());
+    assert!(rotate_if_due(q, &rotation, now).unwrap().is_none());
+}
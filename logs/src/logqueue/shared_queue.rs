@@ -0,0 +1,253 @@
+use std::fmt::Debug;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::logfile::{block::Block, LogFile, FlatBuff};
+use super::{LogFileQueue, LogQueueFileId, LogRawWriting, LoqErr, QueueAccess, RecID, raw_write_switches, write_raw_tail};
+
+type SyncQueue<'a,LogId,FILE,BUFF> = dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + Send + Sync + 'a;
+
+/// Очередь с одним писателем и параллельными читателями
+///
+/// Доступ к очереди разделен на три вида
+///
+/// | Доступ                           | Блокировка                            | Кто использует                          |
+/// |----------------------------------|---------------------------------------|-----------------------------------------|
+/// | [read](Self::read)               | очередь на чтение                     | чтение записей, навигация               |
+/// | [writer](Self::writer)           | писатель + очередь на чтение          | добавление записей в актуальный лог     |
+/// | [exclusive](Self::exclusive)     | писатель + очередь на запись          | переключение лог файла, удаление файлов |
+///
+/// Читатели не блокируют друг друга и писателя: запись блока выполняется в конец актуального лог файла,
+/// позиция блока публикуется (индекс смещений лог файла) только после записи,
+/// поэтому читатель видит только записанные блоки.
+/// Писатель в каждый момент один - добавление записей упорядочено блокировкой писателя.
+/// Читатели ожидают только изменения списка лог файлов ([exclusive](Self::exclusive)).
+pub struct SharedLogQueue<'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    queue: Arc<RwLock<SyncQueue<'a,LogId,FILE,BUFF>>>,
+    writer: Arc<Mutex<()>>,
+}
+
+impl<'a,LogId,FILE,BUFF> Clone for SharedLogQueue<'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    fn clone(&self) -> Self {
+        Self { queue: self.queue.clone(), writer: self.writer.clone() }
+    }
+}
+
+fn lock_err<FILE:Clone+Debug,LogId:Clone+Debug,G>( err:PoisonError<G> ) -> LoqErr<FILE,LogId> {
+    LoqErr::CantCaptureWriteLock { error: err.to_string() }
+}
+
+impl<'a,LogId,FILE,BUFF> SharedLogQueue<'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    /// Конструктор
+    pub fn new<Q>( queue: Q ) -> Self
+    where
+        Q: LogFileQueue<LogId,FILE,LogFile<BUFF>> + Send + Sync + 'a
+    {
+        Self { queue: Arc::new(RwLock::new(queue)), writer: Arc::new(Mutex::new(())) }
+    }
+
+    /// Доступ на чтение, параллельно с другими читателями и писателем
+    pub fn read( &self ) -> Result<QueueRead<'_,'a,LogId,FILE,BUFF>,LoqErr<FILE,LogId>> {
+        Ok(QueueRead { queue: self.queue.read().map_err(lock_err)? })
+    }
+
+    /// Доступ писателя - добавление записей в актуальный лог файл, параллельно с читателями
+    pub fn writer( &self ) -> Result<QueueWriter<'_,'a,LogId,FILE,BUFF>,LoqErr<FILE,LogId>> {
+        let writer = self.writer.lock().map_err(lock_err)?;
+        Ok(QueueWriter { queue: self.queue.read().map_err(lock_err)?, _writer: writer })
+    }
+
+    /// Монопольный доступ - изменение списка лог файлов
+    pub fn exclusive( &self ) -> Result<QueueExclusive<'_,'a,LogId,FILE,BUFF>,LoqErr<FILE,LogId>> {
+        let writer = self.writer.lock().map_err(lock_err)?;
+        Ok(QueueExclusive { queue: self.queue.write().map_err(lock_err)?, _writer: writer })
+    }
+
+    /// Запись блока в исходном виде (репликация)
+    ///
+    /// Монопольный доступ берется только для переключения лог файла,
+    /// остальные блоки записываются с доступом писателя
+    pub fn write_raw( &self, rec_id: RecID<LogId>, block:&Block ) -> Result<RecID<LogId>,LoqErr<FILE,LogId>> {
+        let writer = self.writer.lock().map_err(lock_err)?;
+        let queue = self.queue.read().map_err(lock_err)?;
+        if !raw_write_switches(&*queue, &rec_id, block) {
+            return write_raw_tail(&*queue, rec_id, block);
+        }
+        drop(queue);
+
+        // блокировка писателя удерживается - между проверкой и переключением очередь не меняется
+        let mut queue = self.queue.write().map_err(lock_err)?;
+        let queue: &mut (dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + 'a) = &mut *queue;
+        let res = queue.write_raw(rec_id, block);
+        drop(writer);
+        res
+    }
+}
+
+/// Доступ на чтение, см. [SharedLogQueue::read]
+pub struct QueueRead<'g,'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    queue: RwLockReadGuard<'g,SyncQueue<'a,LogId,FILE,BUFF>>,
+}
+
+impl<'g,'a,LogId,FILE,BUFF> Deref for QueueRead<'g,'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    type Target = dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.queue
+    }
+}
+
+/// Доступ писателя, см. [SharedLogQueue::writer]
+pub struct QueueWriter<'g,'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    queue: RwLockReadGuard<'g,SyncQueue<'a,LogId,FILE,BUFF>>,
+    _writer: MutexGuard<'g,()>,
+}
+
+impl<'g,'a,LogId,FILE,BUFF> Deref for QueueWriter<'g,'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    type Target = dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.queue
+    }
+}
+
+/// Монопольный доступ, см. [SharedLogQueue::exclusive]
+pub struct QueueExclusive<'g,'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    queue: RwLockWriteGuard<'g,SyncQueue<'a,LogId,FILE,BUFF>>,
+    _writer: MutexGuard<'g,()>,
+}
+
+impl<'g,'a,LogId,FILE,BUFF> Deref for QueueExclusive<'g,'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    type Target = dyn LogFileQueue<LogId,FILE,LogFile<BUFF>> + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.queue
+    }
+}
+
+impl<'g,'a,LogId,FILE,BUFF> DerefMut for QueueExclusive<'g,'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut *self.queue
+    }
+}
+
+impl<'a,LogId,FILE,BUFF> QueueAccess<LogId,FILE,BUFF>
+for SharedLogQueue<'a,LogId,FILE,BUFF>
+where
+    LogId: LogQueueFileId,
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    fn access<R,F>( &self, work:F ) -> Result<R,LoqErr<FILE,LogId>>
+    where
+        F: FnOnce(&dyn LogFileQueue<LogId,FILE,LogFile<BUFF>>) -> Result<R,LoqErr<FILE,LogId>>
+    {
+        let q = self.read()?;
+        work(&*q)
+    }
+}
+
+#[test]
+fn test_concurrent_read_write() {
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::thread;
+    use crate::bbuff::absbuff::FileBuff;
+    use crate::logqueue::*;
+
+    let root = test_dir("shared_queue");
+
+    let conf = test_queue_conf(&root, LogQueueFileNumIDOpen, ValidateStub);
+    let queue: SharedLogQueue<LogQueueFileNumID,PathBuf,FileBuff> = SharedLogQueue::new(conf.open().unwrap());
+
+    let writes = 300;
+    let done = Arc::new(AtomicBool::new(false));
+    let reads = Arc::new(AtomicUsize::new(0));
+
+    let readers: Vec<_> = (0..4).map(|_| {
+        let queue = queue.clone();
+        let done = done.clone();
+        let reads = reads.clone();
+        thread::spawn(move || {
+            while !done.load(Ordering::SeqCst) {
+                let q = queue.read().unwrap();
+                // каждая видимая запись должна читаться целиком
+                for (_, _, log) in q.files() {
+                    let count = log.count().unwrap();
+                    for b in 1..count {
+                        let block = log.read_block(crate::logfile::block::BlockId::new(b)).unwrap();
+                        assert_eq!(block.data.len(), 4);
+                        reads.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            }
+        })
+    }).collect();
+
+    for i in 0..writes {
+        if i % 100 == 99 {
+            queue.exclusive().unwrap().switch().unwrap();
+        } else {
+            let q = queue.writer().unwrap();
+            q.write(&PreparedRecord::from(i)).unwrap();
+        }
+    }
+
+    done.store(true, Ordering::SeqCst);
+    for r in readers { r.join().unwrap(); }
+
+    let q = queue.read().unwrap();
+    let total: u32 = q.files().iter().map(|(_, _, log)| log.count().unwrap() - 1).sum();
+    assert_eq!(total as i32, writes - writes / 100);
+    assert!(reads.load(Ordering::SeqCst) > 0);
+}
@@ -0,0 +1,34 @@
+use std::fs::{create_dir_all, remove_dir_all};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use crate::bbuff::absbuff::FileBuff;
+use crate::logfile::LogFile;
+
+use super::find_logs::FsLogFind;
+use super::{LogQueueConf, LogQueueFileNumID, NewLogFile, OpenLogFile, ValidateLogFiles, path_template};
+
+/// Пустой каталог `./target/test/{name}`, прежнее содержимое удаляется
+pub(crate) fn test_dir( name: &str ) -> PathBuf {
+    let root = PathBuf::from(format!("./target/test/{name}"));
+    if root.exists() { remove_dir_all(&root).unwrap(); }
+    create_dir_all(&root).unwrap();
+    root
+}
+
+/// Настройки очереди в каталоге `root`: лог файлы `*.binlog`, имя нового файла - время и случайный суффикс
+pub(crate) fn test_queue_conf<FOpen, FValidate>( root: &Path, open_log_file: FOpen, validate: FValidate )
+-> LogQueueConf<LogQueueFileNumID, PathBuf, FileBuff, FsLogFind, FOpen, FValidate, impl NewLogFile<PathBuf,LogQueueFileNumID>>
+where
+    FOpen: OpenLogFile<PathBuf,LogFile<FileBuff>,LogQueueFileNumID>,
+    FValidate: ValidateLogFiles<PathBuf,LogFile<FileBuff>,LogQueueFileNumID>,
+{
+    let root = root.to_str().unwrap();
+    LogQueueConf {
+        find_files: FsLogFind::new(root, "*.binlog", true).unwrap(),
+        open_log_file,
+        validate,
+        new_file: path_template(root, "${root}/${time:local:yyyy-mm-ddThh-mi-ss}-${rnd:5}.binlog").unwrap(),
+        _p: PhantomData,
+    }
+}
@@ -0,0 +1,556 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{self, Debug, Display};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+
+use crate::logfile::{LogFile, FlatBuff};
+use super::archive::{Archive, hex};
+use super::{LogQueueFileId, LogQueueFileNumID, LoqErr, OrderedLogs, ValidateLogFiles};
+
+/// Уровень проверки очереди
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Default)]
+pub enum ValidateLevel {
+    /// Цепочка идентификаторов лог файлов: одно начало, нет разрывов, ветвлений и повторов,
+    /// первый блок каждого лог файла содержит идентификатор
+    Quick,
+
+    /// Дополнительно читаются заголовки всех блоков
+    #[default]
+    Headers,
+
+    /// Дополнительно читаются все блоки целиком
+    Full,
+}
+
+impl ValidateLevel {
+    pub fn name( &self ) -> &'static str {
+        match self {
+            Self::Quick => "quick",
+            Self::Headers => "headers",
+            Self::Full => "full",
+        }
+    }
+}
+
+impl FromStr for ValidateLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "quick" => Ok(Self::Quick),
+            "headers" => Ok(Self::Headers),
+            "full" => Ok(Self::Full),
+            _ => Err(format!("unknown validate level {s}, expect quick, headers or full"))
+        }
+    }
+}
+
+/// Сведения о проверенном лог файле
+#[derive(Debug,Clone)]
+pub struct FileReport<FILE: Clone + Debug> {
+    pub file: FILE,
+
+    /// Идентификатор лог файла, если прочитан
+    pub log_id: Option<LogQueueFileNumID>,
+
+    /// Кол-во блоков
+    pub blocks: Option<u32>,
+
+    /// Размер в байтах
+    pub bytes: Option<u64>,
+
+    /// Кол-во проверенных блоков (уровни [ValidateLevel::Headers] и [ValidateLevel::Full])
+    pub verified_blocks: Option<u32>,
+
+    /// Контрольная сумма sha256, если выполнялась сверка с архивом
+    pub sha256: Option<String>,
+}
+
+/// Проблема, найденная при проверке очереди
+#[derive(Debug,Clone)]
+pub enum ValidateIssue<FILE: Clone + Debug> {
+    /// Лог файл не содержит блоков, в очередь не включается
+    EmptyFile { file: FILE },
+
+    /// Ошибка чтения лог файла
+    ReadFailed { file: FILE, error: String },
+
+    /// Первый блок не содержит идентификатор лог файла
+    BadLogId { file: FILE, error: String },
+
+    /// Нет начального лог файла (все лог файлы ссылаются на предыдущие)
+    NoHead,
+
+    /// Несколько начальных лог файлов
+    TwoHeads { heads: Vec<(FILE,u128)> },
+
+    /// Один идентификатор у нескольких лог файлов
+    DuplicateId { id: u128, files: Vec<FILE> },
+
+    /// Ссылка на отсутствующий лог файл - разрыв цепочки
+    Gap { file: FILE, id: u128, previous: u128 },
+
+    /// Идентификатор лог файла не следует за идентификатором предыдущего
+    BadLink { file: FILE, id: u128, previous: u128 },
+
+    /// На один лог файл ссылаются несколько
+    Fork { previous: u128, files: Vec<(FILE,u128)> },
+
+    /// Лог файл не входит в цепочку от начального лог файла
+    Unlinked { file: FILE, id: u128 },
+
+    /// Ошибка чтения блока
+    BadBlock { file: FILE, position: u64, block_id: u32, error: String },
+
+    /// Контрольная сумма не совпадает с архивом
+    ChecksumMismatch { file: FILE, id: u128, expected: String, actual: String },
+
+    /// Не удалось сверить контрольную сумму
+    ChecksumFailed { file: FILE, id: u128, error: String },
+}
+
+impl<FILE: Clone + Debug> ValidateIssue<FILE> {
+    /// Вид проблемы
+    pub fn kind( &self ) -> &'static str {
+        match self {
+            Self::EmptyFile { .. } => "empty_file",
+            Self::ReadFailed { .. } => "read_failed",
+            Self::BadLogId { .. } => "bad_log_id",
+            Self::NoHead => "no_head",
+            Self::TwoHeads { .. } => "two_heads",
+            Self::DuplicateId { .. } => "duplicate_id",
+            Self::Gap { .. } => "gap",
+            Self::BadLink { .. } => "bad_link",
+            Self::Fork { .. } => "fork",
+            Self::Unlinked { .. } => "unlinked",
+            Self::BadBlock { .. } => "bad_block",
+            Self::ChecksumMismatch { .. } => "checksum_mismatch",
+            Self::ChecksumFailed { .. } => "checksum_failed",
+        }
+    }
+
+    /// Проблема не позволяет открыть очередь
+    ///
+    /// Пустой лог файл не включается в очередь и не мешает открытию
+    pub fn is_error( &self ) -> bool {
+        !matches!(self, Self::EmptyFile { .. })
+    }
+
+    /// Лог файлы, к которым относится проблема
+    pub fn files( &self ) -> Vec<FILE> {
+        match self {
+            Self::NoHead => vec![],
+            Self::TwoHeads { heads: files } | Self::Fork { files, .. } => files.iter().map(|(f,_)| f.clone()).collect(),
+            Self::DuplicateId { files, .. } => files.clone(),
+            Self::EmptyFile { file }
+            | Self::ReadFailed { file, .. }
+            | Self::BadLogId { file, .. }
+            | Self::Gap { file, .. }
+            | Self::BadLink { file, .. }
+            | Self::Unlinked { file, .. }
+            | Self::BadBlock { file, .. }
+            | Self::ChecksumMismatch { file, .. }
+            | Self::ChecksumFailed { file, .. } => vec![file.clone()],
+        }
+    }
+}
+
+impl<FILE: Clone + Debug> Display for ValidateIssue<FILE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyFile { file } => write!(f, "log file {file:?} is empty"),
+            Self::ReadFailed { file, error } => write!(f, "can't read log file {file:?}: {error}"),
+            Self::BadLogId { file, error } => write!(f, "first block of log file {file:?} is not a log id: {error}"),
+            Self::NoHead => write!(f, "no head log file, every log file references previous one"),
+            Self::TwoHeads { heads } => write!(f, "{} head log files: {heads:?}", heads.len()),
+            Self::DuplicateId { id, files } => write!(f, "log id {id} used by {} log files: {files:?}", files.len()),
+            Self::Gap { file, id, previous } => write!(f, "log file {file:?} (id {id}) references missing log id {previous}"),
+            Self::BadLink { file, id, previous } => write!(f, "log file {file:?} id {id} does not follow previous log id {previous}"),
+            Self::Fork { previous, files } => write!(f, "log id {previous} is referenced by {} log files: {files:?}", files.len()),
+            Self::Unlinked { file, id } => write!(f, "log file {file:?} (id {id}) is not reachable from head log file"),
+            Self::BadBlock { file, position, block_id, error } => write!(f, "log file {file:?} block {block_id} at {position}: {error}"),
+            Self::ChecksumMismatch { file, id, expected, actual } => write!(f, "log file {file:?} (id {id}) sha256 {actual}, archived {expected}"),
+            Self::ChecksumFailed { file, id, error } => write!(f, "can't check sha256 of log file {file:?} (id {id}): {error}"),
+        }
+    }
+}
+
+/// Результат проверки очереди
+#[derive(Debug,Clone)]
+pub struct ValidateReport<FILE: Clone + Debug> {
+    /// Уровень проверки
+    pub level: ValidateLevel,
+
+    /// Проверенные лог файлы, в исходном порядке
+    pub files: Vec<FileReport<FILE>>,
+
+    /// Идентификаторы лог файлов в порядке цепочки, от начального лог файла
+    pub chain: Vec<u128>,
+
+    /// Найденные проблемы
+    pub issues: Vec<ValidateIssue<FILE>>,
+}
+
+impl<FILE: Clone + Debug> ValidateReport<FILE> {
+    /// Проблем, не позволяющих открыть очередь, нет
+    pub fn is_ok( &self ) -> bool {
+        !self.issues.iter().any(|i| i.is_error())
+    }
+
+    /// Сведения о лог файле по идентификатору
+    pub fn file_of( &self, id: u128 ) -> Option<&FileReport<FILE>> {
+        self.files.iter().find(|f| f.log_id.map(|i| i.id == id).unwrap_or(false))
+    }
+}
+
+impl<FILE: Clone + Debug> Display for ValidateReport<FILE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "validate level {}, {} log files, chain {:?}", self.level.name(), self.files.len(), self.chain)?;
+        for issue in &self.issues {
+            writeln!(f, "{} {}: {issue}", if issue.is_error() { "error" } else { "warning" }, issue.kind())?;
+        }
+        Ok(())
+    }
+}
+
+/// Размер лог файла на момент снимка очереди
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct LogSize {
+    /// Кол-во байт
+    pub bytes: u64,
+
+    /// Кол-во блоков
+    pub blocks: u32,
+}
+
+/// Проверка очереди при открытии, с отчетом о всех найденных проблемах
+///
+/// В отличии от [validate_sequence](super::validate_sequence), проверка не прекращается на первой проблеме,
+/// если проблемы есть, то открытие завершается ошибкой [LoqErr::ValidationFailed] с отчетом
+#[derive(Clone,Default)]
+pub struct QueueValidator {
+    /// Уровень проверки
+    pub level: ValidateLevel,
+
+    /// Архив, с контрольными суммами которого сверяются лог файлы (кроме актуального)
+    pub checksums: Option<Arc<Archive>>,
+}
+
+impl Debug for QueueValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueueValidator")
+            .field("level", &self.level)
+            .field("checksums", &self.checksums.is_some())
+            .finish()
+    }
+}
+
+impl QueueValidator {
+    pub fn new( level: ValidateLevel ) -> Self {
+        Self { level, checksums: None }
+    }
+
+    /// Сверка контрольных сумм с архивом
+    pub fn with_checksums( self, archive: Arc<Archive> ) -> Self {
+        Self { checksums: Some(archive), ..self }
+    }
+
+    /// Проверка лог файлов
+    pub fn report<FILE,BUFF>( &self, log_files: &[(FILE,LogFile<BUFF>)] ) -> ValidateReport<FILE>
+    where
+        FILE: Clone + Debug,
+        BUFF: FlatBuff,
+    {
+        let log_files: Vec<_> = log_files.iter()
+            .map(|(file, log)| (file.clone(), log.clone(), None))
+            .collect();
+        self.report_sized(&log_files)
+    }
+
+    /// Проверка лог файлов без блокировки очереди
+    ///
+    /// Для лог файла с указанным размером ([LogSize], снимок актуального лог файла под блокировкой)
+    /// проверяются только блоки в пределах этого размера, записи добавленные позже не проверяются
+    pub fn report_sized<FILE,BUFF>( &self, log_files: &[(FILE,LogFile<BUFF>,Option<LogSize>)] ) -> ValidateReport<FILE>
+    where
+        FILE: Clone + Debug,
+        BUFF: FlatBuff,
+    {
+        let mut issues = Vec::<ValidateIssue<FILE>>::new();
+        let mut files = Vec::<FileReport<FILE>>::new();
+
+        // лог файлы и их идентификаторы ..........
+        for (file, log, size) in log_files {
+            let mut report = FileReport { file: file.clone(), log_id: None, blocks: None, bytes: None, verified_blocks: None, sha256: None };
+            let read_failed = |error: String| ValidateIssue::ReadFailed { file: file.clone(), error };
+
+            match size.map(|size| Ok(size.bytes)).unwrap_or_else(|| log.bytes_count()) {
+                Ok(bytes) => report.bytes = Some(bytes),
+                Err(err) => issues.push(read_failed(format!("{err:?}")))
+            }
+
+            match size.map(|size| Ok(size.blocks)).unwrap_or_else(|| log.count()) {
+                Ok(0) => {
+                    issues.push(ValidateIssue::EmptyFile { file: file.clone() });
+                    report.blocks = Some(0);
+                    files.push(report);
+                    continue;
+                },
+                Ok(count) => report.blocks = Some(count),
+                Err(err) => {
+                    issues.push(read_failed(format!("{err:?}")));
+                    files.push(report);
+                    continue;
+                }
+            }
+
+            match LogQueueFileNumID::read(file, log) {
+                Ok(id) => report.log_id = Some(id),
+                Err(err) => issues.push(ValidateIssue::BadLogId { file: file.clone(), error: format!("{err:?}") })
+            }
+
+            if self.level >= ValidateLevel::Headers {
+                let read_data = self.level >= ValidateLevel::Full;
+                let verified = match size {
+                    Some(size) => log.verify_blocks_until(read_data, size.bytes),
+                    None => log.verify_blocks(read_data)
+                };
+                match verified {
+                    Ok(verified) => report.verified_blocks = Some(verified),
+                    Err((position, block_id, err)) => issues.push(ValidateIssue::BadBlock {
+                        file: file.clone(), position, block_id: block_id.value(), error: format!("{err:?}")
+                    })
+                }
+            }
+
+            files.push(report);
+        }
+
+        // цепочка идентификаторов ..........
+        let chain = Self::chain(&files, &mut issues);
+
+        // контрольные суммы ..........
+        if let Some(archive) = &self.checksums {
+            let sealed = chain.len().saturating_sub(1);
+            for id in &chain[..sealed] {
+                let idx = files.iter().position(|f| f.log_id.map(|i| i.id == *id).unwrap_or(false)).unwrap();
+                let file = files[idx].file.clone();
+                let failed = |error: String| ValidateIssue::ChecksumFailed { file: file.clone(), id: *id, error };
+
+                let meta = match archive.meta(*id) {
+                    Ok(Some(meta)) => meta,
+                    Ok(None) => continue,
+                    Err(err) => { issues.push(failed(format!("{err:?}"))); continue; }
+                };
+
+                let log = &log_files[idx].1;
+                match sha256_of(log) {
+                    Ok(actual) => {
+                        if actual != meta.sha256 {
+                            issues.push(ValidateIssue::ChecksumMismatch { file: file.clone(), id: *id, expected: meta.sha256.clone(), actual: actual.clone() });
+                        }
+                        files[idx].sha256 = Some(actual);
+                    },
+                    Err(err) => issues.push(failed(format!("{err:?}")))
+                }
+            }
+        }
+
+        ValidateReport { level: self.level, files, chain, issues }
+    }
+
+    /// Построение цепочки от начального лог файла
+    fn chain<FILE: Clone + Debug>( files: &[FileReport<FILE>], issues: &mut Vec<ValidateIssue<FILE>> ) -> Vec<u128> {
+        let mut by_id = BTreeMap::<u128,Vec<(FILE,LogQueueFileNumID)>>::new();
+        for f in files {
+            if let Some(id) = f.log_id {
+                by_id.entry(id.id).or_default().push((f.file.clone(), id));
+            }
+        }
+
+        let mut excluded = HashSet::<u128>::new();
+        for (id, list) in &by_id {
+            if list.len() > 1 {
+                issues.push(ValidateIssue::DuplicateId { id: *id, files: list.iter().map(|(f,_)| f.clone()).collect() });
+                excluded.insert(*id);
+            }
+        }
+
+        // начальный лог файл - без ссылки на предыдущий,
+        // в частичной очереди (начало удалено политикой хранения) - ссылающийся на отсутствующий
+        let mut roots = Vec::<(FILE,u128)>::new();
+        let mut dangling = Vec::<(FILE,u128,u128)>::new();
+        let mut next = BTreeMap::<u128,Vec<(FILE,u128)>>::new();
+        for (id, list) in &by_id {
+            let (file, log_id) = &list[0];
+            match log_id.previous {
+                None => roots.push((file.clone(), *id)),
+                Some(previous) => {
+                    if !by_id.contains_key(&previous) {
+                        dangling.push((file.clone(), *id, previous));
+                        continue;
+                    }
+                    if log_id.id() != LogQueueFileNumID::new(Some(previous)).id() {
+                        issues.push(ValidateIssue::BadLink { file: file.clone(), id: *id, previous });
+                    }
+                    next.entry(previous).or_default().push((file.clone(), *id));
+                }
+            }
+        }
+
+        let head = match (roots.len(), dangling.len()) {
+            (0, 0) => {
+                if !by_id.is_empty() { issues.push(ValidateIssue::NoHead); }
+                return vec![];
+            },
+            (1, _) => roots[0].1,
+            (0, _) => {
+                let head = dangling.remove(0);
+                head.1
+            },
+            _ => {
+                issues.push(ValidateIssue::TwoHeads { heads: roots });
+                return vec![];
+            }
+        };
+        for (file, id, previous) in dangling {
+            issues.push(ValidateIssue::Gap { file, id, previous });
+            excluded.insert(id);
+        }
+
+        let mut chain = vec![head];
+        let mut visited = HashSet::<u128>::from([head]);
+        let mut current = head;
+        while let Some(children) = next.get(&current) {
+            if children.len() > 1 {
+                issues.push(ValidateIssue::Fork { previous: current, files: children.clone() });
+                break;
+            }
+            current = children[0].1;
+            if !visited.insert(current) {
+                break;
+            }
+            chain.push(current);
+        }
+
+        for (id, list) in &by_id {
+            if !visited.contains(id) && !excluded.contains(id) {
+                issues.push(ValidateIssue::Unlinked { file: list[0].0.clone(), id: *id });
+            }
+        }
+
+        chain
+    }
+}
+
+/// Контрольная сумма sha256 содержимого лог файла
+pub fn sha256_of<BUFF: FlatBuff>( log: &LogFile<BUFF> ) -> Result<String,crate::logfile::LogErr> {
+    let size = log.bytes_count()?;
+    let mut hasher = Sha256::new();
+    let mut buff = vec![0u8; 64 * 1024];
+    let mut pos = 0u64;
+    while pos < size {
+        let reads = log.read_raw_bytes(pos, &mut buff)?;
+        if reads == 0 { break; }
+        hasher.update(&buff[..reads as usize]);
+        pos += reads;
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+impl<FILE,BUFF> ValidateLogFiles<FILE,LogFile<BUFF>,LogQueueFileNumID> for QueueValidator
+where
+    FILE: Clone + Debug,
+    BUFF: FlatBuff,
+{
+    fn validate( &self, log_files: &Vec<(FILE,LogFile<BUFF>)> ) -> Result<OrderedLogs<LogQueueFileNumID,(FILE,LogFile<BUFF>)>,LoqErr<FILE,LogQueueFileNumID>> {
+        let report = self.report(log_files);
+        if !report.is_ok() {
+            return Err(LoqErr::ValidationFailed { report: Box::new(report) });
+        }
+
+        let ordered: Vec<(LogQueueFileNumID,(FILE,LogFile<BUFF>))> = report.chain.iter().map(|id| {
+            let idx = report.files.iter().position(|f| f.log_id.map(|i| i.id == *id).unwrap_or(false)).unwrap();
+            (report.files[idx].log_id.unwrap(), log_files[idx].clone())
+        }).collect();
+
+        // без ошибок цепочка пуста, только если все лог файлы пустые - такие файлы LogQueueConf::open не проверяет
+        match ordered.last() {
+            Some(tail) => Ok(OrderedLogs { tail: tail.clone(), files: ordered }),
+            None => Err(LoqErr::ValidationFailed { report: Box::new(report) })
+        }
+    }
+}
+
+#[test]
+fn test_validate_report() {
+    use std::fs::remove_file;
+    use std::path::PathBuf;
+    use crate::bbuff::absbuff::FileBuff;
+    use crate::logqueue::*;
+
+    let root = test_dir("validate_report");
+
+    let conf = |level: ValidateLevel| {
+        let conf = test_queue_conf(&root, LogQueueFileNumIDOpen, QueueValidator::new(level));
+        conf
+    };
+
+    // исправная очередь из трех лог файлов
+    let mut files = vec![];
+    {
+        let mut queue = conf(ValidateLevel::Full).open().unwrap();
+        for i in 0..3 {
+            let q: &dyn LogFileQueue<LogQueueFileNumID,PathBuf,LogFile<FileBuff>> = &queue;
+            q.write(&PreparedRecord::from(i)).unwrap();
+            if i < 2 { queue.switch().unwrap(); }
+        }
+        for (_, file, _) in queue.files() { files.push(file); }
+    }
+
+    let queue = conf(ValidateLevel::Full).open().unwrap();
+    assert_eq!(queue.files().len(), 3);
+
+    // снимок актуального лог файла - блок, дописываемый после снимка, не проверяется
+    let (tail_id, _, tail) = queue.tail();
+    let size = LogSize { bytes: tail.bytes_count().unwrap(), blocks: tail.count().unwrap() };
+    let sized: Vec<_> = queue.files().into_iter()
+        .map(|(id, file, log)| (file, log, if id == tail_id { Some(size) } else { None }))
+        .collect();
+    let unsized_files: Vec<_> = sized.iter().map(|(file, log, _)| (file.clone(), log.clone())).collect();
+    drop(queue);
+
+    let tail_file = std::fs::OpenOptions::new().append(true).open(&files[2]).unwrap();
+    std::io::Write::write_all(&mut &tail_file, &[1, 2, 3]).unwrap();
+    assert!(QueueValidator::new(ValidateLevel::Full).report_sized(&sized).is_ok());
+    assert!(!QueueValidator::new(ValidateLevel::Full).report(&unsized_files).is_ok());
+    tail_file.set_len(size.bytes).unwrap();
+    drop((sized, unsized_files));
+
+    // разрыв цепочки - удален средний лог файл
+    let removed = std::fs::read(&files[1]).unwrap();
+    remove_file(&files[1]).unwrap();
+    let err = conf(ValidateLevel::Quick).open().err().unwrap();
+    match err {
+        LoqErr::ValidationFailed { report } => {
+            assert_eq!(report.chain.len(), 1);
+            assert!(report.issues.iter().any(|i| matches!(i, ValidateIssue::Gap { id: 2, previous: 1, .. })), "{report}");
+        },
+        err => panic!("unexpected {err:?}")
+    }
+
+    // частичная очередь - удален начальный лог файл
+    std::fs::write(&files[1], removed).unwrap();
+    remove_file(&files[0]).unwrap();
+    let queue = conf(ValidateLevel::Headers).open().unwrap();
+    assert_eq!(queue.files().len(), 2);
+    drop(queue);
+
+    // только пустые лог файлы - предупреждение, создается новый лог файл
+    let root = test_dir("validate_report_empty");
+    std::fs::write(root.join("empty.binlog"), b"").unwrap();
+    let queue = test_queue_conf(&root, LogQueueFileNumIDOpen, QueueValidator::new(ValidateLevel::Headers)).open().unwrap();
+    assert_eq!(queue.files().len(), 1);
+}
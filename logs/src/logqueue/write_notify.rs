@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// Уведомление о записи в очередь
+///
+/// Содержит счетчик изменений очереди (запись, переключение лог файла),
+/// ожидающие потоки и задачи ([WriteNotify::changed()]) просыпаются при увеличении счетчика.
+///
+/// Что бы не пропустить уведомление, необходимо сначала получить текущее значение счетчика [WriteNotify::version()],
+/// затем проверить наличие данных и только после этого ожидать [WriteNotify::wait_change()] / [WriteNotify::changed()].
+#[derive(Clone,Debug,Default)]
+pub struct WriteNotify {
+    state: Arc<(Mutex<NotifyState>, Condvar)>
+}
+
+/// Счетчик изменений и ожидающие задачи
+#[derive(Debug,Default)]
+struct NotifyState {
+    version: u64,
+    wakers: BTreeMap<u64,Waker>,
+    next_waiter: u64,
+}
+
+impl WriteNotify {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Текущее значение счетчика изменений
+    pub fn version( &self ) -> u64 {
+        self.lock().version
+    }
+
+    fn lock( &self ) -> std::sync::MutexGuard<'_,NotifyState> {
+        let (lock, _) = &*self.state;
+        lock.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Уведомление ожидающих потоков и задач об изменении
+    pub fn notify_all( &self ) {
+        let (_, cvar) = &*self.state;
+        let wakers = {
+            let mut state = self.lock();
+            state.version += 1;
+            std::mem::take(&mut state.wakers)
+        };
+        cvar.notify_all();
+        wakers.into_values().for_each(Waker::wake);
+    }
+
+    /// Асинхронное ожидание изменения, поток не блокируется
+    ///
+    /// Аргументы
+    /// - `since` - значение счетчика, полученное до проверки наличия данных
+    ///
+    /// Результат - текущее значение счетчика, отличное от `since`;
+    /// время ожидания ограничивается таймером вызывающего кода
+    pub fn changed( &self, since: u64 ) -> WriteChanged {
+        WriteChanged { notify: self.clone(), since, waiter: None }
+    }
+
+    /// Ожидание изменения
+    ///
+    /// Аргументы
+    /// - `since` - значение счетчика, полученное до проверки наличия данных
+    /// - `timeout` - максимальное время ожидания, `None` - без ограничения
+    ///
+    /// Результат - текущее значение счетчика,
+    /// если оно равно `since`, то истекло время ожидания
+    pub fn wait_change( &self, since: u64, timeout: Option<Duration> ) -> u64 {
+        let (_, cvar) = &*self.state;
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut state = self.lock();
+        while state.version == since {
+            match deadline {
+                None => {
+                    state = cvar.wait(state).unwrap_or_else(|err| err.into_inner());
+                },
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline { break; }
+                    state = cvar.wait_timeout(state, deadline - now)
+                        .unwrap_or_else(|err| err.into_inner()).0;
+                }
+            }
+        }
+        state.version
+    }
+}
+
+/// Ожидание изменения, см. [WriteNotify::changed()]
+///
+/// Задача регистрируется в [WriteNotify] и удаляется из него при завершении или отмене ожидания
+pub struct WriteChanged {
+    notify: WriteNotify,
+    since: u64,
+    waiter: Option<u64>,
+}
+
+impl Future for WriteChanged {
+    type Output = u64;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut state = this.notify.lock();
+        if state.version != this.since {
+            if let Some(waiter) = this.waiter.take() {
+                state.wakers.remove(&waiter);
+            }
+            return Poll::Ready(state.version);
+        }
+
+        let waiter = match this.waiter {
+            Some(waiter) => waiter,
+            None => {
+                state.next_waiter += 1;
+                state.next_waiter
+            }
+        };
+        this.waiter = Some(waiter);
+        state.wakers.insert(waiter, cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for WriteChanged {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter.take() {
+            self.notify.lock().wakers.remove(&waiter);
+        }
+    }
+}
+
+#[test]
+fn test_write_notify() {
+    let notify = WriteNotify::new();
+    let v0 = notify.version();
+    assert_eq!(notify.wait_change(v0, Some(Duration::from_millis(10))), v0);
+
+    let n = notify.clone();
+    let th = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        n.notify_all();
+    });
+
+    assert_eq!(notify.wait_change(v0, None), v0 + 1);
+    th.join().unwrap();
+
+    // асинхронное ожидание
+    let waker = Waker::noop();
+    let mut cx = Context::from_waker(waker);
+    let mut changed = Box::pin(notify.changed(v0 + 1));
+    assert_eq!(changed.as_mut().poll(&mut cx), Poll::Pending);
+    assert_eq!(notify.lock().wakers.len(), 1);
+    notify.notify_all();
+    assert!(notify.lock().wakers.is_empty());
+    assert_eq!(changed.as_mut().poll(&mut cx), Poll::Ready(v0 + 2));
+
+    let mut cancelled = Box::pin(notify.changed(v0 + 2));
+    assert_eq!(cancelled.as_mut().poll(&mut cx), Poll::Pending);
+    drop(cancelled);
+    assert!(notify.lock().wakers.is_empty());
+}
@@ -1,14 +1,14 @@
-use std::{rc::Rc, cell::RefCell, sync::Mutex, collections::HashMap, fmt::Debug};
+use std::{cell::RefCell, sync::{Arc, Mutex}, collections::HashMap, fmt::Debug};
 
 use chrono::Utc;
 use date_format::{DateFormat, Format};
 use parse::{TemplateParser, Parser, NumberParser};
-use rand::{rngs::ThreadRng, RngCore};
+use rand::RngCore;
 
 /// Шаблон генерируемого файла
 #[derive(Clone)]
 pub struct PathTemplate<'a> {
-    generators: Vec<Rc<Mutex<dyn PathValue + 'a>>>
+    generators: Vec<Arc<Mutex<dyn PathValue + 'a>>>
 }
 
 impl<'a> PathTemplate<'a> {
@@ -64,9 +64,9 @@ impl<'a> Debug for PathTemplate<'a> {
 }
 
 /// Элемент имени файла
-pub trait PathValue: Debug {
+pub trait PathValue: Debug + Send {
     fn generate( &mut self ) -> String;
-    fn clone<'a,'r>( &'a self ) -> Rc<Mutex<dyn PathValue + 'r>>;
+    fn clone<'a,'r>( &'a self ) -> Arc<Mutex<dyn PathValue + 'r>>;
 }
 
 /// Обычный текст в имени файла
@@ -75,8 +75,8 @@ impl PathValue for PlainValue {
     fn generate( &mut self ) -> String {
         self.0.clone()
     }
-    fn clone<'a,'r>( &'a self ) -> Rc<Mutex<dyn PathValue + 'r>> {
-        Rc::new(Mutex::new(PlainValue(self.0.clone())))
+    fn clone<'a,'r>( &'a self ) -> Arc<Mutex<dyn PathValue + 'r>> {
+        Arc::new(Mutex::new(PlainValue(self.0.clone())))
     }
 }
 impl Debug for PlainValue {
@@ -93,8 +93,8 @@ impl PathValue for CurrentDateTimeValue {
         let dt = Utc::now();
         dt.format(&self.0)
     }
-    fn clone<'a,'r>( &'a self ) -> Rc<Mutex<dyn PathValue + 'r>> {
-        Rc::new(Mutex::new(CurrentDateTimeValue(self.0.clone())))
+    fn clone<'a,'r>( &'a self ) -> Arc<Mutex<dyn PathValue + 'r>> {
+        Arc::new(Mutex::new(CurrentDateTimeValue(self.0.clone())))
     }
 }
 impl Debug for CurrentDateTimeValue {
@@ -109,15 +109,15 @@ pub struct RandomValue {
     dic: String,
     dic_char_count: usize,
     count: u32,
-    rnd: ThreadRng,
 }
 
 impl PathValue for RandomValue {
     fn generate( &mut self ) -> String {
         let mut str = String::new();
+        let mut rnd = rand::thread_rng();
         if self.dic.len()>0 {
             for _x in 0..self.count {
-                let rndi = self.rnd.next_u64() as usize;
+                let rndi = rnd.next_u64() as usize;
                 let rndi = rndi % self.dic_char_count;
                 match &self.dic.chars().skip(rndi).next() {
                     Some(c) => { 
@@ -130,12 +130,11 @@ impl PathValue for RandomValue {
         str
     }
 
-    fn clone<'a,'r>( &'a self ) -> Rc<Mutex<dyn PathValue + 'r>> {
-        Rc::new(Mutex::new(RandomValue {
+    fn clone<'a,'r>( &'a self ) -> Arc<Mutex<dyn PathValue + 'r>> {
+        Arc::new(Mutex::new(RandomValue {
             dic: self.dic.clone(),
             dic_char_count: self.dic_char_count.clone(),
             count: self.count.clone(),
-            rnd: self.rnd.clone()
         }))
     }
 }
@@ -146,7 +145,6 @@ impl Debug for RandomValue {
         .field("dic", &self.dic)
         .field("dic_char_count", &self.dic_char_count)
         .field("count", &self.count)
-        .field("rnd", &self.rnd)
         .finish()
     }
 }
@@ -158,7 +156,6 @@ impl Default for RandomValue {
             dic: dic.to_string(), 
             dic_char_count: dic.chars().count(), 
             count: 1, 
-            rnd: rand::thread_rng()
         }
     }
 }
@@ -166,7 +163,7 @@ impl Default for RandomValue {
 /// Парсер шаблона имени файла
 #[derive(Clone)]
 pub struct PathTemplateParser<'a> {
-    pub variables: HashMap<String, Rc<Mutex<dyn PathValue + 'a>> >
+    pub variables: HashMap<String, Arc<Mutex<dyn PathValue + 'a>> >
 }
 
 impl<'a> Default for PathTemplateParser<'a> {
@@ -199,7 +196,7 @@ impl<'a> PathTemplateParser<'a> {
         K: Into<String>,
         V: Into<String>,
     {
-        self.variables.insert(name.into(), Rc::new(Mutex::new(PlainValue(value.into()))));
+        self.variables.insert(name.into(), Arc::new(Mutex::new(PlainValue(value.into()))));
     }
 
     /// Добавляет переменную в шаблон
@@ -208,7 +205,7 @@ impl<'a> PathTemplateParser<'a> {
         K: Into<String>,
         V: Into<String>,
     {
-        self.variables.insert(name.into(), Rc::new(Mutex::new(PlainValue(value.into()))));
+        self.variables.insert(name.into(), Arc::new(Mutex::new(PlainValue(value.into()))));
         self
     }
 
@@ -227,7 +224,7 @@ impl<'a> PathTemplateParser<'a> {
     /// - `${rnd:5}` - случайны набор из 5 букв, число 5 - указывает на кол-во букв и может быть заменено на другое число
     /// - `${env:...}` - в качестве значения - потенциально опасно
     pub fn parse<'r>(&self, source: &str) -> Result<PathTemplate<'r>, String> {
-        let p_tmpl = RefCell::new(Vec::<Rc<Mutex<dyn PathValue>>>::new());
+        let p_tmpl = RefCell::new(Vec::<Arc<Mutex<dyn PathValue>>>::new());
 
         let tmpl = TemplateParser::default();
         match tmpl.parse(source) {
@@ -241,7 +238,7 @@ impl<'a> PathTemplateParser<'a> {
                     if code.starts_with("time:") && code.len() > "time:".len() {
                         let sub_code = &code["time:".len()..];
                         p_tmpl.borrow_mut().push(
-                            Rc::new(Mutex::new(
+                            Arc::new(Mutex::new(
                                 CurrentDateTimeValue( DateFormat::parse(sub_code) )
                             ))
                         );
@@ -251,7 +248,7 @@ impl<'a> PathTemplateParser<'a> {
                         match std::env::var(sub_code) {
                             Ok(value) => {
                                 p_tmpl.borrow_mut().push(
-                                    Rc::new(Mutex::new(
+                                    Arc::new(Mutex::new(
                                         PlainValue(value)
                                     ))
                                 );
@@ -268,7 +265,7 @@ impl<'a> PathTemplateParser<'a> {
                                         let mut rnd = RandomValue::default();
                                         rnd.count = num;
                                         p_tmpl.borrow_mut().push(
-                                            Rc::new(Mutex::new(
+                                            Arc::new(Mutex::new(
                                                 rnd
                                             ))
                                         );
@@ -295,7 +292,7 @@ impl<'a> PathTemplateParser<'a> {
                 }, 
                 |_,text| {
                     p_tmpl.borrow_mut().push(
-                        Rc::new(Mutex::new(
+                        Arc::new(Mutex::new(
                             PlainValue(text.to_string())
                         ))
                     );
@@ -306,7 +303,7 @@ impl<'a> PathTemplateParser<'a> {
         };
 
         let p_tmpl = p_tmpl.borrow().clone();
-        let res: Result<Vec<Rc<Mutex<dyn PathValue>>>, String> = p_tmpl.iter().fold( Ok(Vec::<Rc<Mutex<dyn PathValue + 'r>>>::new()), |res,i| {
+        let res: Result<Vec<Arc<Mutex<dyn PathValue>>>, String> = p_tmpl.iter().fold( Ok(Vec::<Arc<Mutex<dyn PathValue + 'r>>>::new()), |res,i| {
             res.and_then(|mut res| {
                 match i.lock() {
                     Ok(i) => {
@@ -325,7 +322,7 @@ impl<'a> PathTemplateParser<'a> {
 fn parse_template_test() {
     let mut parser = PathTemplateParser::default();
 
-    let log_num = Rc::new(Mutex::new(PlainValue(format!("LOG-a"))));
+    let log_num = Arc::new(Mutex::new(PlainValue(format!("LOG-a"))));
     parser.variables.insert(format!("logn"), log_num.clone());
 
     let tmpl = parser.parse("/home/${env:USER}/${logn}/${time:yyyy-mm-dd}/${time:hh-mi-ss}-rnd${rnd:5}.log");